@@ -0,0 +1,134 @@
+//! Compares the assembunny interpreter with the compiled programs, on the
+//! day 12 program and on the day 25 search through initial values of `a`.
+//! Both sides run with the optimiser on or both with it off.
+//!
+//!     cargo bench --bench assembunny
+//!
+//! Compiling is scoped to a constant factor, not an order of magnitude: at
+//! least 3x without fused loops, on day 12 and on day 25, whose division loop
+//! does not fuse, and about as fast with them, where only a few hundred steps
+//! are left to run. The bench fails when a comparison misses its target.
+
+use aoc_2016::assembunny::compile::Exit;
+use aoc_2016::assembunny::Program;
+use aoc_2016::context::Context;
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+
+/// How many values the day 25 search reads for every `a`.
+const OUTPUTS: usize = 1000;
+
+/// The speedup compiling must reach where steps run one by one.
+const PLAIN: f64 = 3.0;
+
+/// And where fused loops do nearly all the work: about as fast, short of
+/// the noise in timing a few microseconds.
+const FUSED: f64 = 0.9;
+
+fn program(day: u8) -> Program {
+    let path = format!("{}/input/task_{}", env!("CARGO_MANIFEST_DIR"), day);
+    let input = fs::read_to_string(&path).unwrap();
+    Program::parse(&input, &Context::new().with_strict(true)).unwrap()
+}
+
+/// The fastest of a few rounds, and what the last one returned.
+fn fastest<F: FnMut() -> i64>(mut f: F) -> (Duration, i64) {
+    let mut best = Duration::MAX;
+    let mut result = 0;
+    for _ in 0..5 {
+        let start = Instant::now();
+        result = f();
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
+
+/// Prints how much faster compiling was, whether it reached `target`.
+fn compare(
+    name: &str,
+    interpreted: (Duration, i64),
+    compiled: (Duration, i64),
+    target: f64,
+) -> bool {
+    assert_eq!(
+        interpreted.1, compiled.1,
+        "{} gives different results",
+        name
+    );
+    let speedup = interpreted.0.as_secs_f64() / compiled.0.as_secs_f64();
+    println!(
+        "{:<40} interpreted {:>10.3?}  compiled {:>10.3?}  {:>6.1}x{}",
+        name,
+        interpreted.0,
+        compiled.0,
+        speedup,
+        if speedup < target {
+            format!(", below the {:.1}x target", target)
+        } else {
+            String::new()
+        }
+    );
+    speedup >= target
+}
+
+fn day_12() -> bool {
+    let program = program(12);
+    let mut met = true;
+    for (name, optimise, target) in [
+        ("day 12 part 2, plain", false, PLAIN),
+        ("day 12 part 2, optimised", true, FUSED),
+    ] {
+        let interpreted = fastest(|| {
+            let mut machine = program.machine().with_optimiser(optimise);
+            machine.set('c', 1).unwrap();
+            machine.finish().unwrap();
+            machine.get('a').unwrap()
+        });
+        let compiled = fastest(|| {
+            let mut compiled = program
+                .compile()
+                .with_optimiser(optimise)
+                .with('c', 1)
+                .unwrap();
+            compiled.finish().unwrap();
+            compiled.get('a').unwrap()
+        });
+        met &= compare(name, interpreted, compiled, target);
+    }
+    met
+}
+
+/// Reads the first values every `a` up to the answer sends, adding them up.
+fn day_25() -> bool {
+    let program = program(25);
+    let name = format!("day 25, {} values for a in 0..=158", OUTPUTS);
+    let interpreted = fastest(|| {
+        let mut sum = 0;
+        for a in 0..=158 {
+            let mut machine = program.machine().with('a', a).unwrap();
+            while machine.output().len() < OUTPUTS && machine.step().unwrap() {}
+            sum += machine.output().iter().sum::<i64>();
+        }
+        sum
+    });
+    let compiled = fastest(|| {
+        let mut sum = 0;
+        for a in 0..=158 {
+            let mut compiled = program.compile().with('a', a).unwrap();
+            while compiled.output().len() < OUTPUTS
+                && compiled.run(u64::MAX).unwrap() != Exit::Halted
+            {}
+            sum += compiled.output().iter().sum::<i64>();
+        }
+        sum
+    });
+    compare(&name, interpreted, compiled, PLAIN)
+}
+
+fn main() {
+    let met = day_12() & day_25();
+    if !met {
+        process::exit(1);
+    }
+}
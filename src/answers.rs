@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::Part;
+
+/// Recorded answers, one `<day> <part> <expected>` per line. Multi-line
+/// answers are stored with `\n` in place of line breaks and `\\` for a
+/// backslash.
+pub struct Answers(BTreeMap<(u8, Part), String>);
+
+struct Answer {
+    day: u8,
+    part: Part,
+    expected: String,
+}
+
+impl FromStr for Answer {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        let day = tokens.parse::<u8>(0, "day")?;
+        let part = tokens.parse::<Part>(1, "part `1` or `2`")?;
+        tokens.get(2, "expected answer")?;
+        let expected = unescape(s[tokens.column(2) - 1..].trim_end());
+        Ok(Answer {
+            day,
+            part,
+            expected,
+        })
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                result.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                result.push('\\');
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+impl Answers {
+    pub fn parse(input: &str, ctx: &Context) -> Result<Self, ParseError> {
+        Ok(Answers(
+            ctx.lines::<Answer>(input)?
+                .into_iter()
+                .map(|a| ((a.day, a.part), a.expected))
+                .collect(),
+        ))
+    }
+
+    pub fn get(&self, day: u8, part: Part) -> Option<&str> {
+        self.0.get(&(day, part)).map(|s| s.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+pub enum Verdict {
+    Pass,
+    Changed { expected: String, actual: String },
+    Fail(String),
+}
+
+impl Verdict {
+    pub fn new(expected: Option<&str>, actual: Result<String, Error>) -> Self {
+        match (expected, actual) {
+            (_, Err(e)) => Verdict::Fail(e.to_string()),
+            (None, Ok(actual)) => {
+                Verdict::Fail(format!("no recorded answer, got `{}`", escape(&actual)))
+            }
+            (Some(expected), Ok(actual)) if expected == actual => Verdict::Pass,
+            (Some(expected), Ok(actual)) => Verdict::Changed {
+                expected: expected.to_string(),
+                actual,
+            },
+        }
+    }
+
+    pub fn is_pass(&self) -> bool {
+        matches!(self, Verdict::Pass)
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::Changed { expected, actual } => write!(
+                f,
+                "changed: expected `{}`, got `{}`",
+                escape(expected),
+                escape(actual)
+            ),
+            Verdict::Fail(reason) => write!(f, "fail: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_escaped_answers() {
+        let answers =
+            Answers::parse("1 1 353\n8 2 .#\\n#.\n9 2 a\\\\nb  \n", &Context::new()).unwrap();
+        assert_eq!(answers.len(), 3);
+        assert_eq!(answers.get(1, Part::First), Some("353"));
+        assert_eq!(answers.get(8, Part::Second), Some(".#\n#."));
+        assert_eq!(answers.get(9, Part::Second), Some("a\\nb"));
+        assert_eq!(answers.get(1, Part::Second), None);
+        assert_eq!(unescape(&escape("a\\n\nb")), "a\\n\nb");
+    }
+
+    #[test]
+    fn rejects_malformed_answers() {
+        let ctx = Context::new().with_strict(true);
+        let e = Answers::parse("1 1 353\n1 3 1", &ctx).err().unwrap();
+        assert_eq!((e.line, e.column), (2, 3));
+        let e = Answers::parse("1 1", &ctx).err().unwrap();
+        assert_eq!(e.column, 4);
+    }
+
+    #[test]
+    fn compares_answers() {
+        assert!(Verdict::new(Some("1"), Ok("1".to_string())).is_pass());
+        let changed = Verdict::new(Some("1"), Ok("2".to_string()));
+        assert_eq!(changed.to_string(), "changed: expected `1`, got `2`");
+        let failed = Verdict::new(Some("1"), Err(Error::NoAnswer("nothing")));
+        assert_eq!(failed.to_string(), "fail: no answer: nothing");
+        assert!(!Verdict::new(None, Ok("1".to_string())).is_pass());
+    }
+}
@@ -0,0 +1,675 @@
+use crate::context::Context;
+use crate::error::ParseError;
+use crate::parse::Tokens;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub mod compile;
+pub mod debug;
+pub mod decompile;
+pub mod limits;
+pub mod optimise;
+pub mod profile;
+
+use limits::{Guard, Limits};
+use optimise::{optimise, Fused};
+use profile::Profile;
+
+/// The registers of the puzzles, `a` to `d`.
+pub const REGISTERS: usize = 4;
+
+/// Registers are named by lowercase letters, what a parser expects when a
+/// program may use the first `n` of them.
+const EXPECTED: [&str; 26] = [
+    "register a",
+    "register a-b",
+    "register a-c",
+    "register a-d",
+    "register a-e",
+    "register a-f",
+    "register a-g",
+    "register a-h",
+    "register a-i",
+    "register a-j",
+    "register a-k",
+    "register a-l",
+    "register a-m",
+    "register a-n",
+    "register a-o",
+    "register a-p",
+    "register a-q",
+    "register a-r",
+    "register a-s",
+    "register a-t",
+    "register a-u",
+    "register a-v",
+    "register a-w",
+    "register a-x",
+    "register a-y",
+    "register a-z",
+];
+
+/// The name of a register of the register file.
+pub fn register_name(i: usize) -> char {
+    (b'a' + i as u8) as char
+}
+
+/// Token `i` as one of the first `registers` registers.
+/// The instruction `offset` away from `pc`, `None` before the start or when
+/// it is too far to count.
+fn target(pc: usize, offset: i64) -> Option<usize> {
+    i64::try_from(pc)
+        .ok()
+        .and_then(|pc| pc.checked_add(offset))
+        .and_then(|t| usize::try_from(t).ok())
+}
+
+/// Where register `name` is in `registers`, shared by every engine running
+/// programs.
+fn index(registers: &[i64], name: char) -> Result<usize, Fault> {
+    match (name as u32).checked_sub('a' as u32) {
+        Some(i) if (i as usize) < registers.len() => Ok(i as usize),
+        _ => Err(Fault::UnknownRegister(name)),
+    }
+}
+
+fn register(tokens: &Tokens, i: usize, registers: usize) -> Result<Source, ParseError> {
+    let expected = EXPECTED[registers.clamp(1, EXPECTED.len()) - 1];
+    match tokens.get(i, expected)?.as_bytes() {
+        [r @ b'a'..=b'z'] if ((r - b'a') as usize) < registers => {
+            Ok(Source::Register((r - b'a') as usize))
+        }
+        _ => Err(tokens.error(i, expected)),
+    }
+}
+
+/// Token `i` as a literal or one of the first `registers` registers.
+fn source(tokens: &Tokens, i: usize, registers: usize) -> Result<Source, ParseError> {
+    match tokens.parse::<i64>(i, "integer or register") {
+        Ok(value) => Ok(Source::Value(value)),
+        Err(_) => {
+            register(tokens, i, registers).map_err(|_| tokens.error(i, "integer or register"))
+        }
+    }
+}
+
+/// An operand: a literal or a register, by its index in the register file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    Value(i64),
+    Register(usize),
+}
+
+impl Source {
+    /// The literal, or what its register holds in `registers`.
+    fn value(self, registers: &[i64]) -> i64 {
+        match self {
+            Source::Value(v) => v,
+            Source::Register(r) => registers[r],
+        }
+    }
+}
+
+/// Operands that are written to are `Source`s too, because `tgl` can turn
+/// a `jnz 1 c` into a `cpy 1 c` and back. The parser only accepts registers
+/// there; instructions writing to a value are skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Copy(Source, Source),
+    Inc(Source),
+    Dec(Source),
+    JumpNonZero(Source, Source),
+    Toggle(Source),
+    Out(Source),
+    /// `add x y` adds `x` to `y`.
+    Add(Source, Source),
+    /// `mul x y` multiplies `y` by `x`.
+    Mul(Source, Source),
+    Nop,
+}
+
+impl Instruction {
+    /// What `tgl` makes of the instruction.
+    pub fn toggled(self) -> Instruction {
+        use Instruction::*;
+        match self {
+            Inc(r) => Dec(r),
+            Dec(r) | Toggle(r) | Out(r) => Inc(r),
+            JumpNonZero(a, b) => Copy(a, b),
+            Copy(a, b) | Add(a, b) | Mul(a, b) => JumpNonZero(a, b),
+            Nop => Nop,
+        }
+    }
+
+    /// Parses an instruction of a program using the first `registers`
+    /// registers.
+    pub fn parse(s: &str, registers: usize) -> Result<Instruction, ParseError> {
+        let tokens = Tokens::new(s);
+        let register = |i: usize| register(&tokens, i, registers);
+        let source = |i: usize| source(&tokens, i, registers);
+        let instruction = match tokens.get(0, "instruction")? {
+            "cpy" => Instruction::Copy(source(1)?, register(2)?),
+            "inc" => Instruction::Inc(register(1)?),
+            "dec" => Instruction::Dec(register(1)?),
+            "jnz" => Instruction::JumpNonZero(source(1)?, source(2)?),
+            "tgl" => Instruction::Toggle(source(1)?),
+            "out" => Instruction::Out(source(1)?),
+            "add" => Instruction::Add(source(1)?, register(2)?),
+            "mul" => Instruction::Mul(source(1)?, register(2)?),
+            "nop" => Instruction::Nop,
+            _ => {
+                return Err(tokens.error(
+                    0,
+                    "one of `cpy`, `inc`, `dec`, `jnz`, `tgl`, `out`, `add`, `mul`, `nop`",
+                ))
+            }
+        };
+        if tokens.len() > instruction.arity() + 1 {
+            return Err(tokens.error(instruction.arity() + 1, "end of line"));
+        }
+        Ok(instruction)
+    }
+
+    fn arity(&self) -> usize {
+        use Instruction::*;
+        match self {
+            Nop => 0,
+            Inc(_) | Dec(_) | Toggle(_) | Out(_) => 1,
+            Copy(..) | JumpNonZero(..) | Add(..) | Mul(..) => 2,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Instruction::parse(s, REGISTERS)
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Value(v) => write!(f, "{}", v),
+            Source::Register(r) => write!(f, "{}", register_name(*r)),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Padding is applied to the whole instruction, not to its parts.
+        let s = match self {
+            Instruction::Copy(from, to) => format!("cpy {} {}", from, to),
+            Instruction::Inc(r) => format!("inc {}", r),
+            Instruction::Dec(r) => format!("dec {}", r),
+            Instruction::JumpNonZero(source, offset) => format!("jnz {} {}", source, offset),
+            Instruction::Toggle(offset) => format!("tgl {}", offset),
+            Instruction::Out(source) => format!("out {}", source),
+            Instruction::Add(x, y) => format!("add {} {}", x, y),
+            Instruction::Mul(x, y) => format!("mul {} {}", x, y),
+            Instruction::Nop => "nop".to_string(),
+        };
+        f.pad(&s)
+    }
+}
+
+/// Something a program did that the machine cannot go on from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    UnknownRegister(char),
+    Overflow {
+        pc: usize,
+        instruction: Instruction,
+    },
+    /// The machine ran all the steps its limits allow, counting from when
+    /// they were set.
+    OutOfSteps {
+        steps: u64,
+    },
+    /// The machine ran as long as its limits allow.
+    OutOfTime {
+        steps: u64,
+        time: Duration,
+    },
+    /// The machine is back in the state it was in before step `first`, at
+    /// step `again`: it never halts.
+    Loops {
+        pc: usize,
+        first: u64,
+        again: u64,
+    },
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::UnknownRegister(r) => write!(f, "unknown register `{}`", r),
+            Fault::Overflow { pc, instruction } => {
+                write!(f, "overflow at {}: {}", pc, instruction)
+            }
+            Fault::OutOfSteps { steps } => write!(f, "ran out of steps after {}", steps),
+            Fault::OutOfTime { steps, time } => {
+                write!(f, "ran out of time after {:?}, {} steps", time, steps)
+            }
+            Fault::Loops { pc, first, again } => write!(
+                f,
+                "loops forever: at step {} it is back at {} the way it was at step {}",
+                again, pc, first
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    registers: usize,
+}
+
+impl Program {
+    pub fn new(instructions: Vec<Instruction>, registers: usize) -> Self {
+        Program {
+            instructions,
+            registers,
+        }
+    }
+
+    /// Parses a program over the registers `a` to `d`, one instruction per
+    /// line.
+    pub fn parse(input: &str, ctx: &Context) -> Result<Program, ParseError> {
+        Program::parse_with_registers(input, ctx, REGISTERS)
+    }
+
+    pub fn parse_with_registers(
+        input: &str,
+        ctx: &Context,
+        registers: usize,
+    ) -> Result<Program, ParseError> {
+        let instructions = ctx.lines_with(input, |l| Instruction::parse(l, registers))?;
+        Ok(Program::new(instructions, registers))
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn registers(&self) -> usize {
+        self.registers
+    }
+
+    /// A machine at the start of the program, every register at zero, with
+    /// the optimiser on.
+    pub fn machine(&self) -> Machine {
+        Machine {
+            instructions: self.instructions.clone(),
+            fused: Some(optimise(&self.instructions)),
+            registers: vec![0; self.registers],
+            pc: 0,
+            output: vec![],
+            steps: 0,
+            guard: None,
+            profile: None,
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            writeln!(f, "{:>3}: {}", i, instruction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a copy of a program, so `tgl` can change it as it goes. Values sent
+/// with `out` are collected in `output`.
+///
+/// With the optimiser on, loops the peephole pass recognises run in a single
+/// step when the machine gets to their first instruction.
+#[derive(Clone, Debug)]
+pub struct Machine {
+    instructions: Vec<Instruction>,
+    fused: Option<Vec<Option<Fused>>>,
+    registers: Vec<i64>,
+    pc: usize,
+    output: Vec<i64>,
+    steps: u64,
+    guard: Option<Guard>,
+    profile: Option<Profile>,
+}
+
+/// Everything that decides how a program goes on: once a machine is back in
+/// a state it was in before, it repeats itself forever.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Snapshot {
+    instructions: Vec<Instruction>,
+    registers: Vec<i64>,
+    pc: usize,
+}
+
+impl Machine {
+    /// The machine with the peephole optimiser turned on or off.
+    pub fn with_optimiser(mut self, on: bool) -> Self {
+        self.set_optimiser(on);
+        self
+    }
+
+    /// Turns the peephole optimiser on or off for the steps still to run.
+    pub fn set_optimiser(&mut self, on: bool) {
+        self.fused = if on {
+            Some(optimise(&self.instructions))
+        } else {
+            None
+        };
+    }
+
+    /// The machine stopping with a fault when it goes beyond `limits`,
+    /// counted from now.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.set_limits(limits);
+        self
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.guard = Some(Guard::new(limits, self.steps));
+    }
+
+    /// The machine counting where it spends its steps from now on.
+    pub fn with_profile(mut self) -> Self {
+        self.profile = Some(Profile::new(self.instructions.len()));
+        self
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// How many steps the machine has run, a fused loop counting as one.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The machine with register `name` set to `value`.
+    pub fn with(mut self, name: char, value: i64) -> Result<Self, Fault> {
+        self.set(name, value)?;
+        Ok(self)
+    }
+
+    pub fn set(&mut self, name: char, value: i64) -> Result<(), Fault> {
+        let i = index(&self.registers, name)?;
+        self.registers[i] = value;
+        Ok(())
+    }
+
+    pub fn get(&self, name: char) -> Result<i64, Fault> {
+        index(&self.registers, name).map(|i| self.registers[i])
+    }
+
+    pub fn registers(&self) -> &[i64] {
+        &self.registers
+    }
+
+    /// The index of the next instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Every value sent with `out` so far.
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
+
+    /// The program as it is now, after whatever `tgl` did to it.
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn current(&self) -> Option<Instruction> {
+        self.instructions.get(self.pc).copied()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.instructions.len()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            instructions: self.instructions.clone(),
+            registers: self.registers.clone(),
+            pc: self.pc,
+        }
+    }
+
+    /// The registers as `a=1 b=2 …`.
+    pub fn describe(&self) -> String {
+        self.registers
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("{}={}", register_name(i), v))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn value(&self, source: Source) -> i64 {
+        source.value(&self.registers)
+    }
+
+    /// Applies `f` to the register `target` names, `None` when it is a
+    /// value: instructions writing to a value are skipped.
+    fn update<F: Fn(i64) -> Option<i64>>(&mut self, target: Source, f: F) -> Result<(), Fault> {
+        if let Source::Register(r) = target {
+            self.registers[r] = f(self.registers[r]).ok_or(Fault::Overflow {
+                pc: self.pc,
+                instruction: self.instructions[self.pc],
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Moves `offset` instructions away, jumping before the start or too
+    /// far to count halts.
+    fn jump(&mut self, offset: i64) {
+        self.pc = target(self.pc, offset).unwrap_or(self.instructions.len());
+    }
+
+    /// The fused loop starting at the current instruction, when it would
+    /// run at least once. Otherwise the plain instructions run, and do
+    /// whatever they do.
+    fn fused_at(&self) -> Option<Fused> {
+        let fused = (*self.fused.as_ref()?.get(self.pc)?)?;
+        Some(fused).filter(|f| f.runs(&self.registers))
+    }
+
+    fn run_fused(&mut self, fused: Fused) -> Result<(), Fault> {
+        fused.run(&mut self.registers).ok_or(Fault::Overflow {
+            pc: self.pc,
+            instruction: self.instructions[self.pc],
+        })?;
+        self.pc += fused.width();
+        Ok(())
+    }
+
+    /// Runs the next instruction, `false` when the program has halted.
+    pub fn step(&mut self) -> Result<bool, Fault> {
+        let instruction = match self.current() {
+            Some(instruction) => instruction,
+            None => return Ok(false),
+        };
+        if let Some(guard) = &mut self.guard {
+            guard.check(self.steps, self.pc, &self.registers, &self.instructions)?;
+        }
+        let (pc, fused) = (self.pc, self.fused_at());
+        match fused {
+            Some(fused) => self.run_fused(fused)?,
+            None => self.execute(instruction)?,
+        }
+        self.steps += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, self.pc, fused.is_some());
+        }
+        Ok(true)
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Fault> {
+        match instruction {
+            Instruction::Copy(from, to) => {
+                let v = self.value(from);
+                self.update(to, |_| Some(v))?;
+            }
+            Instruction::Inc(target) => self.update(target, |v| v.checked_add(1))?,
+            Instruction::Dec(target) => self.update(target, |v| v.checked_sub(1))?,
+            Instruction::JumpNonZero(condition, offset) => {
+                if self.value(condition) != 0 {
+                    self.jump(self.value(offset));
+                    return Ok(());
+                }
+            }
+            Instruction::Toggle(offset) => {
+                if let Some(instruction) =
+                    target(self.pc, self.value(offset)).and_then(|t| self.instructions.get_mut(t))
+                {
+                    *instruction = instruction.toggled();
+                    if self.fused.is_some() {
+                        self.fused = Some(optimise(&self.instructions));
+                    }
+                }
+            }
+            Instruction::Out(source) => {
+                let v = self.value(source);
+                self.output.push(v);
+            }
+            Instruction::Add(x, y) => {
+                let x = self.value(x);
+                self.update(y, |v| v.checked_add(x))?;
+            }
+            Instruction::Mul(x, y) => {
+                let x = self.value(x);
+                self.update(y, |v| v.checked_mul(x))?;
+            }
+            Instruction::Nop => (),
+        }
+        self.pc += 1;
+        Ok(())
+    }
+
+    /// Runs until the program halts.
+    pub fn finish(&mut self) -> Result<(), Fault> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// Runs until the program halts, tracing every step when the context
+    /// traces.
+    pub fn run(&mut self, ctx: &Context) -> Result<(), Fault> {
+        if ctx.trace().is_none() {
+            return self.finish();
+        }
+        loop {
+            let pc = self.pc;
+            let done = match (self.fused_at(), self.current()) {
+                (Some(fused), _) => fused.to_string(),
+                (None, Some(instruction)) => instruction.to_string(),
+                (None, None) => return Ok(()),
+            };
+            self.step()?;
+            ctx.step(|| format!("{:>3}: {:<12} {}", pc, done, self.describe()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(s: &str) -> Program {
+        Program::parse(s, &Context::new().with_strict(true)).unwrap()
+    }
+
+    #[test]
+    fn parses_instructions() {
+        use Source::*;
+        assert_eq!(
+            "cpy 41 a".parse::<Instruction>().unwrap(),
+            Instruction::Copy(Value(41), Register(0))
+        );
+        assert_eq!(
+            "jnz 1 c".parse::<Instruction>().unwrap(),
+            Instruction::JumpNonZero(Value(1), Register(2))
+        );
+        assert_eq!("nop".parse::<Instruction>().unwrap(), Instruction::Nop);
+        for (line, column, expected) in [
+            ("inc e", 5, "register a-d"),
+            (
+                "jmp a 2",
+                1,
+                "one of `cpy`, `inc`, `dec`, `jnz`, `tgl`, `out`, `add`, `mul`, `nop`",
+            ),
+            ("jnz a x", 7, "integer or register"),
+            ("cpy 1 2", 7, "register a-d"),
+            ("out a b", 7, "end of line"),
+        ] {
+            let e = line.parse::<Instruction>().err().unwrap();
+            assert_eq!((e.column, e.expected), (column, expected), "{}", line);
+        }
+        assert_eq!(
+            Instruction::parse("inc e", 5).unwrap(),
+            Instruction::Inc(Register(4))
+        );
+    }
+
+    #[test]
+    fn toggles_instructions() {
+        let program = program("cpy 2 a\ntgl a\ntgl -1\ninc a\ntgl 1\njnz 5 a\nout a");
+        let mut machine = program.machine();
+        machine.run(&Context::new()).unwrap();
+        assert_eq!(machine.output(), [5]);
+        let toggled = [1, 3, 5].map(|i| machine.instructions()[i].to_string());
+        assert_eq!(toggled, ["inc a", "dec a", "cpy 5 a"]);
+    }
+
+    #[test]
+    fn computes_in_64_bits() {
+        let program = program("cpy 100000 a\nmul a a\nmul 100000 a\nadd 1 a\nout a");
+        let mut machine = program.machine();
+        machine.run(&Context::new()).unwrap();
+        assert_eq!(machine.output(), [1_000_000_000_000_001]);
+    }
+
+    #[test]
+    fn reports_faults() {
+        let program = program("mul a a\nmul a a");
+        let mut machine = program.machine().with('a', 1 << 20).unwrap();
+        let fault = machine.run(&Context::new()).err().unwrap();
+        assert_eq!(fault.to_string(), "overflow at 1: mul a a");
+        assert_eq!(
+            program.machine().with('e', 1).err().unwrap(),
+            Fault::UnknownRegister('e')
+        );
+    }
+
+    #[test]
+    fn jumps_with_register_offsets() {
+        let jumps = program("cpy 2 b\njnz 1 b\ninc a\ninc a\njnz 1 -10\ninc a");
+        let mut machine = jumps.machine();
+        let mut steps = 0;
+        while machine.step().unwrap() {
+            steps += 1;
+        }
+        assert_eq!((steps, machine.get('a').unwrap()), (4, 1));
+        assert!(machine.is_halted());
+        // Offsets too far to count jump out of the program, or toggle
+        // nothing.
+        let far = program("inc b\ntgl a\njnz 1 a\ninc b");
+        for a in [i64::MAX, i64::MIN] {
+            let mut machine = far.machine().with('a', a).unwrap();
+            machine.finish().unwrap();
+            assert_eq!(machine.get('b').unwrap(), 1);
+        }
+    }
+}
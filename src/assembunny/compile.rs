@@ -0,0 +1,506 @@
+use super::limits::{Guard, Limits};
+use super::optimise::{optimise, Fused};
+use super::{index, target, Fault, Instruction, Machine, Program, Snapshot, Source};
+
+/// An instruction decoded once: operands resolved to register indices or
+/// literals, jump targets made absolute, with one past the end for jumps
+/// out of the program.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Set(i64, usize),
+    Copy(usize, usize),
+    Inc(usize),
+    Dec(usize),
+    AddValue(i64, usize),
+    AddRegister(usize, usize),
+    MulValue(i64, usize),
+    MulRegister(usize, usize),
+    Jump(usize),
+    JumpIf(usize, usize),
+    /// A jump by a register, only known when it runs.
+    JumpBy(Source, usize),
+    Toggle(Source),
+    Out(Source),
+    /// A fused loop, by its index in `Compiled::fused`, falling back to the
+    /// plain op when it would not run.
+    Fused(usize),
+    Nop,
+}
+
+/// Decodes the instruction at `pc`.
+fn lower(pc: usize, instruction: Instruction, len: usize) -> Op {
+    use Source::{Register as R, Value as V};
+    let target = |offset: i64| target(pc, offset).unwrap_or(len).min(len);
+    match instruction {
+        Instruction::Copy(V(v), R(to)) => Op::Set(v, to),
+        Instruction::Copy(R(from), R(to)) => Op::Copy(from, to),
+        Instruction::Inc(R(r)) => Op::Inc(r),
+        Instruction::Dec(R(r)) => Op::Dec(r),
+        Instruction::JumpNonZero(V(0), _) => Op::Nop,
+        Instruction::JumpNonZero(V(_), V(offset)) => Op::Jump(target(offset)),
+        Instruction::JumpNonZero(R(r), V(offset)) => Op::JumpIf(r, target(offset)),
+        Instruction::JumpNonZero(test, R(by)) => Op::JumpBy(test, by),
+        Instruction::Toggle(offset) => Op::Toggle(offset),
+        Instruction::Out(source) => Op::Out(source),
+        Instruction::Add(V(v), R(to)) => Op::AddValue(v, to),
+        Instruction::Add(R(from), R(to)) => Op::AddRegister(from, to),
+        Instruction::Mul(V(v), R(to)) => Op::MulValue(v, to),
+        Instruction::Mul(R(from), R(to)) => Op::MulRegister(from, to),
+        // Writing to a value does nothing.
+        Instruction::Copy(_, V(_))
+        | Instruction::Inc(V(_))
+        | Instruction::Dec(V(_))
+        | Instruction::Add(_, V(_))
+        | Instruction::Mul(_, V(_))
+        | Instruction::Nop => Op::Nop,
+    }
+}
+
+/// Why `Compiled::run` returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// The program sent a value.
+    Out(i64),
+    Halted,
+    /// It ran all the steps it was given.
+    Paused,
+}
+
+/// A program lowered to decoded ops, with the optimiser's fused loops built
+/// in unless it is off, and the state of a run of it. Does what a `Machine`
+/// does without decoding an instruction on every step; `tgl` decodes the
+/// program again.
+///
+/// That is worth a constant factor, at least 3x on code the optimiser
+/// leaves alone, not an order of magnitude: the machine already works on
+/// register indices, and one dispatch per step is left either way. Searches
+/// get their order of magnitude from the fused loops, which both run.
+#[derive(Clone, Debug)]
+pub struct Compiled {
+    instructions: Vec<Instruction>,
+    ops: Vec<Op>,
+    plain: Vec<Op>,
+    fused: Vec<Fused>,
+    optimise: bool,
+    registers: Vec<i64>,
+    pc: usize,
+    output: Vec<i64>,
+    steps: u64,
+    guard: Option<Guard>,
+}
+
+impl Program {
+    /// The program compiled, every register at zero.
+    pub fn compile(&self) -> Compiled {
+        let mut compiled = Compiled {
+            instructions: self.instructions.clone(),
+            ops: vec![],
+            plain: vec![],
+            fused: vec![],
+            optimise: true,
+            registers: vec![0; self.registers],
+            pc: 0,
+            output: vec![],
+            steps: 0,
+            guard: None,
+        };
+        compiled.lower();
+        compiled
+    }
+}
+
+impl Compiled {
+    fn lower(&mut self) {
+        let len = self.instructions.len();
+        self.plain = self
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(pc, instruction)| lower(pc, *instruction, len))
+            .collect();
+        self.fused.clear();
+        self.ops = self.plain.clone();
+        if !self.optimise {
+            return;
+        }
+        for (pc, fused) in optimise(&self.instructions).into_iter().enumerate() {
+            if let Some(fused) = fused {
+                self.ops[pc] = Op::Fused(self.fused.len());
+                self.fused.push(fused);
+            }
+        }
+    }
+
+    /// The program compiled with the peephole optimiser's fused loops, or
+    /// without them.
+    pub fn with_optimiser(mut self, on: bool) -> Self {
+        self.optimise = on;
+        self.lower();
+        self
+    }
+
+    /// The program stopping with a fault when it goes beyond `limits`,
+    /// counted from now. It runs one step at a time while they are set.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.set_limits(limits);
+        self
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.guard = Some(Guard::new(limits, self.steps));
+    }
+
+    /// The program compiled with register `name` set to `value`.
+    pub fn with(mut self, name: char, value: i64) -> Result<Self, Fault> {
+        self.set(name, value)?;
+        Ok(self)
+    }
+
+    pub fn set(&mut self, name: char, value: i64) -> Result<(), Fault> {
+        let i = index(&self.registers, name)?;
+        self.registers[i] = value;
+        Ok(())
+    }
+
+    pub fn get(&self, name: char) -> Result<i64, Fault> {
+        index(&self.registers, name).map(|i| self.registers[i])
+    }
+
+    pub fn registers(&self) -> &[i64] {
+        &self.registers
+    }
+
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
+
+    /// How many steps have run, a fused loop counting as one.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            instructions: self.instructions.clone(),
+            registers: self.registers.clone(),
+            pc: self.pc,
+        }
+    }
+
+    fn overflow(&self) -> Fault {
+        Fault::Overflow {
+            pc: self.pc,
+            instruction: self.instructions[self.pc],
+        }
+    }
+
+    /// Stores a result, `None` when it overflowed.
+    fn write(&mut self, x: usize, value: Option<i64>) -> Result<(), Fault> {
+        match value {
+            Some(value) => {
+                self.registers[x] = value;
+                Ok(())
+            }
+            None => Err(self.overflow()),
+        }
+    }
+
+    /// Runs the fused loop at `pc` when it would run at least once, what
+    /// it did.
+    fn run_fused(&mut self, fused: Fused) -> Result<bool, Fault> {
+        if !fused.runs(&self.registers) {
+            return Ok(false);
+        }
+        fused
+            .run(&mut self.registers)
+            .ok_or_else(|| self.overflow())?;
+        self.pc += fused.width();
+        Ok(true)
+    }
+
+    /// Runs at most `steps` steps, until the program sends a value or halts.
+    pub fn run(&mut self, steps: u64) -> Result<Exit, Fault> {
+        if self.guard.is_some() {
+            return self.run_guarded(steps);
+        }
+        let mut left = steps;
+        let exit = self.go(&mut left);
+        self.steps += steps - left;
+        exit
+    }
+
+    /// Runs one step at a time, checking the limits before every one the
+    /// way a `Machine` does.
+    fn run_guarded(&mut self, steps: u64) -> Result<Exit, Fault> {
+        for _ in 0..steps {
+            if self.pc >= self.ops.len() {
+                return Ok(Exit::Halted);
+            }
+            if let Some(guard) = &mut self.guard {
+                guard.check(self.steps, self.pc, &self.registers, &self.instructions)?;
+            }
+            let mut left = 1;
+            let exit = self.go(&mut left);
+            self.steps += 1 - left;
+            match exit? {
+                Exit::Paused => (),
+                exit => return Ok(exit),
+            }
+        }
+        Ok(Exit::Paused)
+    }
+
+    /// Runs the ops that only move values around and jump, on locals, up to
+    /// one it leaves to `go`: one that may fault, send, toggle or fuse.
+    fn fast(&mut self, left: &mut u64) {
+        let (ops, r) = (&self.ops, &mut self.registers[..]);
+        let (mut pc, mut n) = (self.pc, *left);
+        while n > 0 {
+            let Some(op) = ops.get(pc) else { break };
+            match *op {
+                Op::Set(v, to) => r[to] = v,
+                Op::Copy(from, to) => r[to] = r[from],
+                Op::Inc(x) => match r[x].checked_add(1) {
+                    Some(v) => r[x] = v,
+                    None => break,
+                },
+                Op::Dec(x) => match r[x].checked_sub(1) {
+                    Some(v) => r[x] = v,
+                    None => break,
+                },
+                Op::AddValue(v, to) => match r[to].checked_add(v) {
+                    Some(v) => r[to] = v,
+                    None => break,
+                },
+                Op::AddRegister(from, to) => match r[to].checked_add(r[from]) {
+                    Some(v) => r[to] = v,
+                    None => break,
+                },
+                Op::Jump(target) => {
+                    pc = target;
+                    n -= 1;
+                    continue;
+                }
+                Op::JumpIf(x, target) if r[x] != 0 => {
+                    pc = target;
+                    n -= 1;
+                    continue;
+                }
+                Op::JumpIf(..) | Op::Nop => (),
+                _ => break,
+            }
+            pc += 1;
+            n -= 1;
+        }
+        self.pc = pc;
+        *left = n;
+    }
+
+    fn go(&mut self, left: &mut u64) -> Result<Exit, Fault> {
+        let len = self.ops.len();
+        loop {
+            self.fast(left);
+            if self.pc >= len {
+                break;
+            }
+            if *left == 0 {
+                return Ok(Exit::Paused);
+            }
+            *left -= 1;
+            let mut op = self.ops[self.pc];
+            if let Op::Fused(i) = op {
+                if self.run_fused(self.fused[i])? {
+                    continue;
+                }
+                op = self.plain[self.pc];
+            }
+            let r = &self.registers;
+            match op {
+                Op::Set(v, to) => self.registers[to] = v,
+                Op::Copy(from, to) => self.registers[to] = r[from],
+                Op::Inc(x) => self.write(x, r[x].checked_add(1))?,
+                Op::Dec(x) => self.write(x, r[x].checked_sub(1))?,
+                Op::AddValue(v, to) => self.write(to, r[to].checked_add(v))?,
+                Op::AddRegister(from, to) => self.write(to, r[to].checked_add(r[from]))?,
+                Op::MulValue(v, to) => self.write(to, r[to].checked_mul(v))?,
+                Op::MulRegister(from, to) => self.write(to, r[to].checked_mul(r[from]))?,
+                Op::Jump(target) => {
+                    self.pc = target;
+                    continue;
+                }
+                Op::JumpIf(x, target) => {
+                    if r[x] != 0 {
+                        self.pc = target;
+                        continue;
+                    }
+                }
+                Op::JumpBy(test, by) => {
+                    if test.value(&self.registers) != 0 {
+                        self.pc = target(self.pc, self.registers[by]).unwrap_or(len).min(len);
+                        continue;
+                    }
+                }
+                Op::Toggle(offset) => {
+                    if let Some(instruction) = target(self.pc, offset.value(&self.registers))
+                        .and_then(|t| self.instructions.get_mut(t))
+                    {
+                        *instruction = instruction.toggled();
+                        self.lower();
+                    }
+                }
+                Op::Out(source) => {
+                    let v = source.value(&self.registers);
+                    self.output.push(v);
+                    self.pc += 1;
+                    return Ok(Exit::Out(v));
+                }
+                Op::Fused(_) | Op::Nop => (),
+            }
+            self.pc += 1;
+        }
+        Ok(Exit::Halted)
+    }
+
+    /// Runs until the program halts.
+    pub fn finish(&mut self) -> Result<(), Fault> {
+        while self.run(u64::MAX)? != Exit::Halted {}
+        Ok(())
+    }
+}
+
+impl From<&Machine> for Compiled {
+    /// Compiles a machine as it is now, to go on from where it got, fusing
+    /// loops if the machine does.
+    fn from(machine: &Machine) -> Self {
+        let mut compiled = Compiled {
+            instructions: machine.instructions.clone(),
+            ops: vec![],
+            plain: vec![],
+            fused: vec![],
+            optimise: machine.fused.is_some(),
+            registers: machine.registers.clone(),
+            pc: machine.pc,
+            output: machine.output.clone(),
+            steps: 0,
+            guard: None,
+        };
+        compiled.lower();
+        compiled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    fn program(s: &str) -> Program {
+        Program::parse(s, &Context::new().with_strict(true)).unwrap()
+    }
+
+    #[test]
+    fn runs_like_the_machine() {
+        let programs: [(&str, i64); 7] = [
+            // Fibonacci, the way day 12 computes it.
+            (
+                "cpy 1 a\ncpy 1 b\ncpy 20 d\ncpy a c\ninc a\ndec b\njnz b -2\ncpy c b\ndec d\njnz d -6",
+                0,
+            ),
+            // Factorial with a toggled jump, the way day 23 does.
+            (
+                "cpy a b\ndec b\ncpy a d\ncpy 0 a\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\n\
+                 dec b\ncpy b c\ncpy c d\ndec d\ninc c\njnz d -2\ntgl c\ncpy -16 c\njnz 1 c\n\
+                 cpy 7 c\njnz 9 d\ninc a\ninc d\njnz d -2\ninc c\njnz c -5",
+                7,
+            ),
+            ("cpy 2 b\njnz 1 b\ninc a\ninc a\njnz 1 -10\ninc a", 0),
+            ("add 3 a\nmul a a\nadd a b\nmul -2 b\nout b\njnz 0 -5", 4),
+            ("cpy 2 a\ntgl a\ntgl -1\ninc a\ntgl 1\njnz 5 a\nout a", 0),
+            // Offsets too far to count.
+            ("inc b\ntgl a\njnz 1 a\ninc b", i64::MAX),
+            ("inc b\njnz 1 9223372036854775807\ninc b", 0),
+        ];
+        for (source, a) in programs {
+            let program = program(source);
+            let mut machine = program.machine().with('a', a).unwrap();
+            machine.finish().unwrap();
+            for optimise in [true, false] {
+                let mut compiled = program
+                    .compile()
+                    .with_optimiser(optimise)
+                    .with('a', a)
+                    .unwrap();
+                let fused = compiled.ops.iter().any(|op| matches!(op, Op::Fused(_)));
+                assert!(optimise || !fused, "{}", source);
+                compiled.finish().unwrap();
+                assert_eq!(
+                    (compiled.registers(), compiled.output()),
+                    (machine.registers(), machine.output()),
+                    "{}",
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stops_at_output_and_after_steps() {
+        let mut compiled = program("inc a\nout a\njnz 1 -2").compile();
+        assert_eq!(compiled.run(1).unwrap(), Exit::Paused);
+        assert_eq!(compiled.run(10).unwrap(), Exit::Out(1));
+        assert_eq!(compiled.run(10).unwrap(), Exit::Out(2));
+        assert_eq!(compiled.steps(), 5);
+        let mut halts = program("out 7").compile();
+        assert_eq!(halts.run(10).unwrap(), Exit::Out(7));
+        assert_eq!(halts.run(10).unwrap(), Exit::Halted);
+        // Pausing between a `dec` and its `jnz` leaves the same state as the
+        // machine after as many steps.
+        let countdown = program("cpy 3 a\ndec a\njnz a -1\ninc b");
+        for steps in 0..9 {
+            let mut compiled = countdown.compile().with_optimiser(false);
+            compiled.run(steps).unwrap();
+            let mut machine = countdown.machine().with_optimiser(false);
+            for _ in 0..steps {
+                machine.step().unwrap();
+            }
+            assert_eq!(compiled.snapshot(), machine.snapshot(), "{}", steps);
+        }
+    }
+
+    #[test]
+    fn stops_at_limits_like_the_machine() {
+        let limits = Limits::new().with_loop_detection().with_steps(1000);
+        for (program, fault) in [
+            ("cpy 5 a\ndec a\njnz a -1\ninc a\njnz 1 -3", "loops forever"),
+            ("inc a\njnz 1 -1", "ran out of steps after 1000"),
+        ] {
+            let program = self::program(program);
+            let mut compiled = program.compile().with_limits(limits.clone());
+            let mut machine = program.machine().with_limits(limits.clone());
+            let (compiled_fault, machine_fault) = (compiled.finish(), machine.finish());
+            assert_eq!(compiled_fault, machine_fault);
+            assert!(compiled_fault.unwrap_err().to_string().starts_with(fault));
+            assert_eq!(compiled.snapshot(), machine.snapshot());
+            assert_eq!(compiled.steps(), machine.steps());
+        }
+        // The limit counts from when it is set, and output goes on as usual.
+        let mut compiled = program("out 1\ninc a\njnz 1 -1").compile();
+        assert_eq!(compiled.run(5).unwrap(), Exit::Out(1));
+        compiled.set_limits(Limits::new().with_steps(4));
+        assert_eq!(compiled.finish(), Err(Fault::OutOfSteps { steps: 4 }));
+        assert_eq!(compiled.steps(), 5);
+    }
+
+    #[test]
+    fn goes_on_from_a_machine() {
+        let program = program("cpy 2 a\nmul a a\nmul a a\nmul a a\nmul a a\nmul a a\nmul a a");
+        let mut machine = program.machine();
+        for _ in 0..3 {
+            machine.step().unwrap();
+        }
+        let mut compiled = Compiled::from(&machine);
+        assert_eq!(compiled.get('a').unwrap(), 16);
+        let fault = compiled.finish().err().unwrap();
+        assert_eq!(fault.to_string(), "overflow at 6: mul a a");
+    }
+}
@@ -0,0 +1,598 @@
+use super::limits::Limits;
+use super::{register, register_name, source, Fault, Machine, Source};
+use crate::error::ParseError;
+use crate::parse::Tokens;
+use std::fmt::{Display, Formatter};
+
+pub const HELP: &str = "\
+step [<n>]                 run one or <n> steps (s)
+continue                   run to the next breakpoint or the end (c)
+break                      list breakpoints
+break <pc> [if <cond>]     stop before the instruction at <pc> (b)
+break if <cond>            stop as soon as <cond> holds
+delete <n>                 remove breakpoint <n>
+watch <expr>               show <expr> at every stop
+unwatch <n>                stop showing watch <n>
+set <register> <expr>      change a register
+show                       show the current instruction and the registers
+profile                    show how often every instruction ran
+quit                       leave the debugger (q)
+Expressions add, subtract and multiply registers and integers, `a + b * 2`;
+conditions compare two of them with ==, !=, <, <=, > or >=. Tokens are
+separated by spaces. An empty line repeats the last command.";
+
+/// How many instructions are shown around the current one.
+const CONTEXT: usize = 2;
+
+/// How many of the last values sent with `out` are shown.
+const OUTPUT: usize = 16;
+
+/// Products of registers and integers, added or subtracted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    /// Every term with `true` when it is subtracted.
+    terms: Vec<(bool, Vec<Source>)>,
+}
+
+impl Expr {
+    /// Parses the expression starting at token `i`, up to the first token
+    /// that is not part of it, the index of which comes with it.
+    fn parse(tokens: &Tokens, mut i: usize, registers: usize) -> Result<(Expr, usize), ParseError> {
+        let mut terms = vec![(false, vec![source(tokens, i, registers)?])];
+        i += 1;
+        while i < tokens.len() {
+            let operand = source(tokens, i + 1, registers);
+            match tokens.get(i, "operator")? {
+                "*" => terms.last_mut().unwrap().1.push(operand?),
+                "+" => terms.push((false, vec![operand?])),
+                "-" => terms.push((true, vec![operand?])),
+                _ => break,
+            }
+            i += 2;
+        }
+        Ok((Expr { terms }, i))
+    }
+
+    /// The value for the given registers, `None` when it does not fit.
+    pub fn eval(&self, registers: &[i64]) -> Option<i64> {
+        self.terms
+            .iter()
+            .try_fold(0i64, |sum, (negative, factors)| {
+                let product = factors.iter().try_fold(1i64, |product, factor| {
+                    product.checked_mul(match factor {
+                        Source::Value(v) => *v,
+                        Source::Register(r) => registers[*r],
+                    })
+                })?;
+                if *negative {
+                    sum.checked_sub(product)
+                } else {
+                    sum.checked_add(product)
+                }
+            })
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (negative, factors)) in self.terms.iter().enumerate() {
+            match (i, negative) {
+                (0, false) => (),
+                (0, true) => write!(f, "-")?,
+                (_, false) => write!(f, " + ")?,
+                (_, true) => write!(f, " - ")?,
+            }
+            let factors = factors
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+            write!(f, "{}", factors.join(" * "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    const ALL: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<", Comparison::Less),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        (">=", Comparison::GreaterOrEqual),
+    ];
+
+    fn holds(self, left: i64, right: i64) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (symbol, _) = Comparison::ALL.iter().find(|(_, c)| c == self).unwrap();
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Two expressions compared, `a + b > 10`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    left: Expr,
+    comparison: Comparison,
+    right: Expr,
+}
+
+impl Condition {
+    fn parse(
+        tokens: &Tokens,
+        i: usize,
+        registers: usize,
+    ) -> Result<(Condition, usize), ParseError> {
+        let expected = "one of `==`, `!=`, `<`, `<=`, `>`, `>=`";
+        let (left, i) = Expr::parse(tokens, i, registers)?;
+        let comparison = match Comparison::ALL
+            .iter()
+            .find(|(symbol, _)| tokens.get(i, expected).ok() == Some(*symbol))
+        {
+            Some((_, comparison)) => *comparison,
+            None => return Err(tokens.error(i, expected)),
+        };
+        let (right, i) = Expr::parse(tokens, i + 1, registers)?;
+        let condition = Condition {
+            left,
+            comparison,
+            right,
+        };
+        Ok((condition, i))
+    }
+
+    /// Whether the condition holds for the given registers. A side that does
+    /// not fit in 64 bits makes it false.
+    pub fn holds(&self, registers: &[i64]) -> bool {
+        match (self.left.eval(registers), self.right.eval(registers)) {
+            (Some(left), Some(right)) => self.comparison.holds(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.comparison, self.right)
+    }
+}
+
+/// Stops the machine before the instruction at `pc`, when `condition`
+/// holds, or both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+    pc: Option<usize>,
+    condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    fn is_hit(&self, machine: &Machine) -> bool {
+        self.pc.is_none_or(|pc| pc == machine.pc())
+            && self
+                .condition
+                .as_ref()
+                .is_none_or(|c| c.holds(machine.registers()))
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.pc, &self.condition) {
+            (Some(pc), Some(condition)) => write!(f, "at {} if {}", pc, condition),
+            (Some(pc), None) => write!(f, "at {}", pc),
+            (None, Some(condition)) => write!(f, "if {}", condition),
+            (None, None) => write!(f, "everywhere"),
+        }
+    }
+}
+
+/// A line typed into the debugger.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Step(u64),
+    Continue,
+    Breakpoints,
+    Break(Breakpoint),
+    Delete(usize),
+    Watch(Expr),
+    Unwatch(usize),
+    Set(usize, Expr),
+    Show,
+    Profile,
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parses a command for a machine with `registers` registers.
+    pub fn parse(line: &str, registers: usize) -> Result<Command, ParseError> {
+        let tokens = Tokens::new(line);
+        let (command, end) = match tokens.get(0, "command")? {
+            "step" | "s" if tokens.len() == 1 => (Command::Step(1), 1),
+            "step" | "s" => (Command::Step(tokens.parse(1, "number of steps")?), 2),
+            "continue" | "c" => (Command::Continue, 1),
+            "break" | "b" if tokens.len() == 1 => (Command::Breakpoints, 1),
+            "break" | "b" => {
+                let (pc, i) = match tokens.get(1, "instruction or `if`")? {
+                    "if" => (None, 1),
+                    _ => (Some(tokens.parse(1, "instruction or `if`")?), 2),
+                };
+                let (condition, end) = if i < tokens.len() {
+                    tokens.expect(i, "if")?;
+                    let (condition, end) = Condition::parse(&tokens, i + 1, registers)?;
+                    (Some(condition), end)
+                } else {
+                    (None, i)
+                };
+                (Command::Break(Breakpoint { pc, condition }), end)
+            }
+            "delete" => (Command::Delete(tokens.parse(1, "breakpoint")?), 2),
+            "watch" => {
+                let (expr, end) = Expr::parse(&tokens, 1, registers)?;
+                (Command::Watch(expr), end)
+            }
+            "unwatch" => (Command::Unwatch(tokens.parse(1, "watch")?), 2),
+            "set" => {
+                let r = match register(&tokens, 1, registers)? {
+                    Source::Register(r) => r,
+                    Source::Value(_) => unreachable!(),
+                };
+                let (expr, end) = Expr::parse(&tokens, 2, registers)?;
+                (Command::Set(r, expr), end)
+            }
+            "show" => (Command::Show, 1),
+            "profile" => (Command::Profile, 1),
+            "help" | "h" => (Command::Help, 1),
+            "quit" | "q" => (Command::Quit, 1),
+            _ => {
+                return Err(tokens.error(
+                    0,
+                    "one of `step`, `continue`, `break`, `delete`, `watch`, `unwatch`, `set`, \
+                     `show`, `profile`, `help`, `quit`",
+                ))
+            }
+        };
+        if tokens.len() > end {
+            return Err(tokens.error(end, "end of line"));
+        }
+        Ok(command)
+    }
+}
+
+/// Why the machine stopped running.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stop {
+    /// It ran all the steps it was asked to.
+    Stepped,
+    /// The breakpoint with the given number was hit.
+    Breakpoint(usize),
+    Halted,
+    Fault(Fault),
+}
+
+/// Runs a machine a few steps at a time, stopping at breakpoints and showing
+/// watched expressions. Breakpoints and watches keep their numbers when
+/// others are removed.
+///
+/// Fused loops run as one step; while there are breakpoints the optimiser
+/// is off, so that no instruction or value in between is jumped over. A machine
+/// coming back to a state it was in before stops with a fault rather than
+/// running forever.
+pub struct Debugger {
+    machine: Machine,
+    steps: u64,
+    breakpoints: Vec<Option<Breakpoint>>,
+    watches: Vec<Option<Expr>>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Debugger {
+            machine: machine
+                .with_limits(Limits::new().with_loop_detection())
+                .with_profile(),
+            steps: 0,
+            breakpoints: vec![],
+            watches: vec![],
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// How many steps the machine has run.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Runs at most `limit` steps, stopping before an instruction where a
+    /// breakpoint is hit. The first step always runs, so continuing from a
+    /// breakpoint does not stop at it again.
+    pub fn run(&mut self, limit: Option<u64>) -> Stop {
+        let mut done = 0;
+        loop {
+            if limit.is_some_and(|limit| done >= limit) {
+                return Stop::Stepped;
+            }
+            match self.machine.step() {
+                Ok(true) => (),
+                Ok(false) => return Stop::Halted,
+                Err(fault) => return Stop::Fault(fault),
+            }
+            self.steps += 1;
+            done += 1;
+            if self.machine.is_halted() {
+                return Stop::Halted;
+            }
+            let hit = self.breakpoints.iter().position(|b| {
+                b.as_ref()
+                    .is_some_and(|breakpoint| breakpoint.is_hit(&self.machine))
+            });
+            if let Some(i) = hit {
+                return Stop::Breakpoint(i + 1);
+            }
+        }
+    }
+
+    /// The instructions around the current one, the registers, the output
+    /// and the watched expressions.
+    pub fn view(&self) -> String {
+        let mut lines = vec![];
+        let instructions = self.machine.instructions();
+        let pc = self.machine.pc();
+        let from = pc.saturating_sub(CONTEXT);
+        let to = (pc + CONTEXT + 1).min(instructions.len());
+        for (i, instruction) in instructions.iter().enumerate().take(to).skip(from) {
+            let breakpoint = self.breakpoints.iter().flatten().any(|b| b.pc == Some(i));
+            lines.push(format!(
+                "{}{}{:>3}: {}",
+                if i == pc { "=>" } else { "  " },
+                if breakpoint { '*' } else { ' ' },
+                i,
+                instruction
+            ));
+        }
+        if self.machine.is_halted() {
+            lines.push(format!("halted after {} steps", self.steps));
+        }
+        lines.push(self.machine.describe());
+        let output = self.machine.output();
+        if !output.is_empty() {
+            let last = output[output.len().saturating_sub(OUTPUT)..]
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>();
+            let more = if output.len() > OUTPUT { "… " } else { "" };
+            lines.push(format!("out: {}{}", more, last.join(" ")));
+        }
+        for (i, watch) in self.watches.iter().enumerate() {
+            if let Some(expr) = watch {
+                let value = expr
+                    .eval(self.machine.registers())
+                    .map_or("overflow".to_string(), |v| v.to_string());
+                lines.push(format!("watch {}: {} = {}", i + 1, expr, value));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Runs a command, what it prints.
+    pub fn execute(&mut self, command: Command) -> String {
+        match command {
+            Command::Step(n) => self.report(Some(n)),
+            Command::Continue => self.report(None),
+            Command::Breakpoints => {
+                let listed = self
+                    .breakpoints
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, b)| b.as_ref().map(|b| format!("{}: {}", i + 1, b)))
+                    .collect::<Vec<String>>();
+                if listed.is_empty() {
+                    "no breakpoints".to_string()
+                } else {
+                    listed.join("\n")
+                }
+            }
+            Command::Break(breakpoint) => {
+                let added = format!("breakpoint {}: {}", self.breakpoints.len() + 1, breakpoint);
+                self.breakpoints.push(Some(breakpoint));
+                self.update_optimiser();
+                added
+            }
+            Command::Delete(n) => match self.breakpoints.get_mut(n.wrapping_sub(1)) {
+                Some(b @ Some(_)) => {
+                    *b = None;
+                    self.update_optimiser();
+                    format!("deleted breakpoint {}", n)
+                }
+                _ => format!("no breakpoint {}", n),
+            },
+            Command::Watch(expr) => {
+                self.watches.push(Some(expr));
+                self.view()
+            }
+            Command::Unwatch(n) => match self.watches.get_mut(n.wrapping_sub(1)) {
+                Some(w @ Some(_)) => {
+                    *w = None;
+                    format!("removed watch {}", n)
+                }
+                _ => format!("no watch {}", n),
+            },
+            Command::Set(r, expr) => match expr.eval(self.machine.registers()) {
+                Some(value) => match self.machine.set(register_name(r), value) {
+                    Ok(()) => {
+                        // The states seen before say nothing about the new one.
+                        self.machine.set_limits(Limits::new().with_loop_detection());
+                        self.view()
+                    }
+                    Err(fault) => format!("fault: {}", fault),
+                },
+                None => format!("{} does not fit in 64 bits", expr),
+            },
+            Command::Show => self.view(),
+            Command::Profile => match self.machine.profile() {
+                Some(profile) => profile.report(self.machine.instructions()),
+                None => "no profile".to_string(),
+            },
+            Command::Help => HELP.to_string(),
+            Command::Quit => String::new(),
+        }
+    }
+
+    /// Runs and shows where the machine stopped.
+    fn report(&mut self, limit: Option<u64>) -> String {
+        match self.run(limit) {
+            Stop::Stepped | Stop::Halted => self.view(),
+            Stop::Breakpoint(n) => format!(
+                "breakpoint {}: {}\n{}",
+                n,
+                self.breakpoints[n - 1].as_ref().unwrap(),
+                self.view()
+            ),
+            Stop::Fault(fault) => format!("fault: {}\n{}", fault, self.view()),
+        }
+    }
+
+    fn update_optimiser(&mut self) {
+        let breakpoints = self.breakpoints.iter().flatten().next().is_some();
+        self.machine.set_optimiser(!breakpoints);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembunny::Program;
+    use crate::context::Context;
+
+    /// Fibonacci, the way day 12 computes it.
+    const FIBONACCI: &str = "cpy 1 a
+cpy 1 b
+cpy 6 d
+cpy a c
+inc a
+dec b
+jnz b -2
+cpy c b
+dec d
+jnz d -6";
+
+    fn debugger() -> Debugger {
+        let program = Program::parse(FIBONACCI, &Context::new().with_strict(true)).unwrap();
+        Debugger::new(program.machine())
+    }
+
+    fn execute(debugger: &mut Debugger, line: &str) -> String {
+        debugger.execute(Command::parse(line, 4).unwrap())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("s", 4).unwrap(), Command::Step(1));
+        assert_eq!(Command::parse("step 10", 4).unwrap(), Command::Step(10));
+        let command = Command::parse("break 3 if a + b * 2 >= -4", 4).unwrap();
+        match &command {
+            Command::Break(b) => assert_eq!(b.to_string(), "at 3 if a + b * 2 >= -4"),
+            _ => panic!("{:?}", command),
+        }
+        let command = Command::parse("set c d - 1", 4).unwrap();
+        assert_eq!(
+            command,
+            Command::Set(
+                2,
+                Expr {
+                    terms: vec![
+                        (false, vec![Source::Register(3)]),
+                        (true, vec![Source::Value(1)])
+                    ]
+                }
+            )
+        );
+        for (line, column, expected) in [
+            ("jump 3", 1, "one of `step`, `continue`, `break`, `delete`, `watch`, `unwatch`, `set`, `show`, `profile`, `help`, `quit`"),
+            ("step x", 6, "number of steps"),
+            ("break 3 when a", 9, "if"),
+            ("break if a = 1", 12, "one of `==`, `!=`, `<`, `<=`, `>`, `>=`"),
+            ("watch a + e", 11, "integer or register"),
+            ("set 1 2", 5, "register a-d"),
+            ("continue now", 10, "end of line"),
+        ] {
+            let e = Command::parse(line, 4).err().unwrap();
+            assert_eq!((e.column, e.expected), (column, expected), "{}", line);
+        }
+    }
+
+    #[test]
+    fn steps_and_shows_context() {
+        let mut debugger = debugger();
+        assert_eq!(
+            execute(&mut debugger, "step 3"),
+            "     1: cpy 1 b\n     2: cpy 6 d\n=>   3: cpy a c\n     4: inc a\n     5: dec b\n\
+             a=1 b=1 c=0 d=6"
+        );
+        assert_eq!(debugger.steps(), 3);
+    }
+
+    #[test]
+    fn stops_at_breakpoints() {
+        let mut debugger = debugger();
+        assert_eq!(execute(&mut debugger, "break 7"), "breakpoint 1: at 7");
+        assert_eq!(
+            execute(&mut debugger, "break if a > 10"),
+            "breakpoint 2: if a > 10"
+        );
+        let stop = execute(&mut debugger, "c");
+        assert!(stop.starts_with("breakpoint 1: at 7\n"), "{}", stop);
+        assert!(stop.contains("=>*  7: cpy c b"), "{}", stop);
+        assert!(stop.ends_with("a=2 b=0 c=1 d=6"), "{}", stop);
+        execute(&mut debugger, "delete 1");
+        let stop = execute(&mut debugger, "c");
+        assert!(stop.starts_with("breakpoint 2: if a > 10\n"), "{}", stop);
+        assert!(debugger.machine().get('a').unwrap() > 10);
+        execute(&mut debugger, "delete 2");
+        assert!(execute(&mut debugger, "c").contains("halted after"));
+        assert_eq!(debugger.machine().get('a').unwrap(), 21);
+        assert_eq!(execute(&mut debugger, "delete 2"), "no breakpoint 2");
+        let profile = execute(&mut debugger, "profile");
+        assert!(profile.starts_with("  0: cpy 1 a"), "{}", profile);
+        assert!(profile.contains("hottest loop"), "{}", profile);
+    }
+
+    #[test]
+    fn watches_and_edits_registers() {
+        let mut debugger = debugger();
+        execute(&mut debugger, "step 3");
+        let view = execute(&mut debugger, "watch a + b * 2");
+        assert!(view.ends_with("watch 1: a + b * 2 = 3"), "{}", view);
+        let view = execute(&mut debugger, "set d 1");
+        assert!(view.contains("a=1 b=1 c=0 d=1"), "{}", view);
+        execute(&mut debugger, "c");
+        assert_eq!(debugger.machine().get('a').unwrap(), 2);
+        assert_eq!(execute(&mut debugger, "unwatch 1"), "removed watch 1");
+        assert!(!execute(&mut debugger, "show").contains("watch"));
+    }
+}
@@ -0,0 +1,664 @@
+use super::optimise::{optimise, Fused};
+use super::{register_name, Instruction, Program, Source};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// A piece of structured code, with the position of the instruction it
+/// comes from where there is one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    /// An instruction that does not jump.
+    Plain(usize, Instruction),
+    /// A loop the optimiser recognises, and the loop as it is for when the
+    /// fused form does not apply: the machine only fuses it while its
+    /// counters are positive.
+    Fused(usize, Fused, Vec<Statement>),
+    /// Runs `body` when `test` is zero, or when it is not zero.
+    If {
+        test: Source,
+        zero: bool,
+        body: Vec<Statement>,
+    },
+    /// Runs `body`, then again as long as `test` is not zero.
+    Loop { test: Source, body: Vec<Statement> },
+    /// An instruction that could not be fitted in: a toggle, a jump by a
+    /// register or a jump across the structure around it.
+    Raw(usize, Instruction),
+}
+
+/// The jumps of a program with a known target, where `to` may lie outside
+/// of it.
+struct Flow {
+    jumps: Vec<(usize, i64)>,
+}
+
+impl Flow {
+    fn new(instructions: &[Instruction]) -> Self {
+        let jumps = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(from, instruction)| match instruction {
+                Instruction::JumpNonZero(Source::Value(0), _) => None,
+                Instruction::JumpNonZero(_, Source::Value(offset)) => {
+                    Some((from, (from as i64).saturating_add(*offset)))
+                }
+                _ => None,
+            })
+            .collect();
+        Flow { jumps }
+    }
+
+    /// Whether the range is only entered at its start and only left at its
+    /// end, so it can be turned into a block.
+    fn is_closed(&self, range: &Range<usize>) -> bool {
+        let (start, end) = (range.start as i64, range.end as i64);
+        self.jumps.iter().all(|(from, to)| {
+            if range.contains(from) {
+                (start..=end).contains(to)
+            } else {
+                !(start + 1..end).contains(to)
+            }
+        })
+    }
+
+    /// The last instruction in `range` jumping back to `start`, which makes
+    /// the outermost loop starting there.
+    fn loop_end(&self, start: usize, range: &Range<usize>) -> Option<usize> {
+        self.jumps
+            .iter()
+            .filter(|(from, to)| *to == start as i64 && *from >= start && range.contains(from))
+            .map(|(from, _)| *from)
+            .max()
+    }
+}
+
+/// A program turned back into loops and conditions.
+pub struct Decompiled {
+    statements: Vec<Statement>,
+    registers: usize,
+}
+
+/// Recovers the structure of a program from its jumps. Backward jumps make
+/// loops, forward jumps over a block make conditions, and the loops the
+/// optimiser fuses become arithmetic, kept as loops for the counters the
+/// machine does not fuse them for. Toggles and jumps by a register can
+/// send the program anywhere; they are kept as they are.
+pub fn decompile(program: &Program) -> Decompiled {
+    let instructions = program.instructions();
+    let decompiler = Decompiler {
+        instructions,
+        fused: optimise(instructions),
+        flow: Flow::new(instructions),
+    };
+    Decompiled {
+        statements: decompiler.structure(0..instructions.len()),
+        registers: program.registers(),
+    }
+}
+
+struct Decompiler<'a> {
+    instructions: &'a [Instruction],
+    fused: Vec<Option<Fused>>,
+    flow: Flow,
+}
+
+impl Decompiler<'_> {
+    fn structure(&self, range: Range<usize>) -> Vec<Statement> {
+        use Instruction::*;
+        use Source::{Register as R, Value as V};
+        let mut statements = vec![];
+        let mut i = range.start;
+        while i < range.end {
+            if let Some(fused) = self.fused[i] {
+                let block = i..i + fused.width();
+                if block.end <= range.end && self.flow.is_closed(&block) {
+                    statements.push(Statement::Fused(i, fused, self.unfused(block.clone())));
+                    i = block.end;
+                    continue;
+                }
+            }
+            if let Some(end) = self.flow.loop_end(i, &range) {
+                if self.flow.is_closed(&(i..end + 1)) {
+                    if let JumpNonZero(test, _) = self.instructions[end] {
+                        let body = self.structure(i..end);
+                        statements.push(Statement::Loop { test, body });
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+            let instruction = self.instructions[i];
+            let next = self.instructions.get(i + 1).copied();
+            match (instruction, next) {
+                // `jnz x 2; jnz 1 k` runs the k - 1 instructions after them
+                // only when x is not zero.
+                (JumpNonZero(R(x), V(2)), Some(JumpNonZero(V(v), V(k))))
+                    if v != 0
+                        && k > 0
+                        && i + 1 + k as usize <= range.end
+                        && self.flow.is_closed(&(i..i + 1 + k as usize)) =>
+                {
+                    let end = i + 1 + k as usize;
+                    let body = self.structure(i + 2..end);
+                    statements.push(Statement::If {
+                        test: R(x),
+                        zero: false,
+                        body,
+                    });
+                    i = end;
+                }
+                (JumpNonZero(R(x), V(k)), _)
+                    if k > 1
+                        && i + k as usize <= range.end
+                        && self.flow.is_closed(&(i..i + k as usize)) =>
+                {
+                    let end = i + k as usize;
+                    let body = self.structure(i + 1..end);
+                    statements.push(Statement::If {
+                        test: R(x),
+                        zero: true,
+                        body,
+                    });
+                    i = end;
+                }
+                // Jumps that never jump, or only to the next instruction.
+                (JumpNonZero(V(0), _), _) | (JumpNonZero(_, V(1)), _) => i += 1,
+                (Copy(_, R(_)) | Inc(R(_)) | Dec(R(_)) | Add(_, R(_)) | Mul(_, R(_)), _)
+                | (Out(_) | Nop, _) => {
+                    statements.push(Statement::Plain(i, instruction));
+                    i += 1;
+                }
+                _ => {
+                    statements.push(Statement::Raw(i, instruction));
+                    i += 1;
+                }
+            }
+        }
+        statements
+    }
+
+    /// A fused loop as the plain loop it is made of, with any loop fused
+    /// inside it.
+    fn unfused(&self, block: Range<usize>) -> Vec<Statement> {
+        let end = block.end - 1;
+        match self.instructions[end] {
+            Instruction::JumpNonZero(test, _) => vec![Statement::Loop {
+                test,
+                body: self.structure(block.start..end),
+            }],
+            _ => unreachable!("fused loops end with a jump back"),
+        }
+    }
+}
+
+/// When the machine runs a loop fused, the way `Machine::fused_at` checks it.
+fn guard(fused: &Fused) -> String {
+    match fused {
+        Fused::Add { from, .. } => format!("{} > 0", register_name(*from)),
+        Fused::Mul {
+            by: Source::Value(v),
+            times,
+            ..
+        } if *v > 0 => format!("{} > 0", register_name(*times)),
+        Fused::Mul { by, times, .. } => {
+            format!("{} > 0 && {} > 0", by, register_name(*times))
+        }
+    }
+}
+
+impl Decompiled {
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Whether every instruction found its place, so the code does exactly
+    /// what the program does.
+    pub fn is_structured(&self) -> bool {
+        fn structured(statements: &[Statement]) -> bool {
+            statements.iter().all(|s| match s {
+                Statement::Raw(..) => false,
+                Statement::If { body, .. }
+                | Statement::Loop { body, .. }
+                | Statement::Fused(_, _, body) => structured(body),
+                _ => true,
+            })
+        }
+        structured(&self.statements)
+    }
+
+    /// A Rust function doing what the program does, taking and returning the
+    /// registers, and collecting what the program sends when it has `out`.
+    /// `None` when the program could not be structured.
+    pub fn to_rust(&self, name: &str) -> Option<String> {
+        if !self.is_structured() {
+            return None;
+        }
+        let names = (0..self.registers)
+            .map(register_name)
+            .collect::<Vec<char>>();
+        let array = format!("[i64; {}]", self.registers);
+        let out = if sends(&self.statements) {
+            ", out: &mut Vec<i64>"
+        } else {
+            ""
+        };
+        let mut lines = vec![
+            format!("fn {}(registers: {}{}) -> {} {{", name, array, out, array),
+            format!(
+                "    let [{}] = registers;",
+                names
+                    .iter()
+                    .map(|r| format!("mut {}", r))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        ];
+        lines.extend(indent(rust(&self.statements)));
+        lines.push(format!(
+            "    [{}]",
+            names
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+        lines.push("}".to_string());
+        Some(lines.join("\n"))
+    }
+}
+
+fn sends(statements: &[Statement]) -> bool {
+    statements.iter().any(|s| match s {
+        Statement::Plain(_, Instruction::Out(_)) => true,
+        Statement::If { body, .. } | Statement::Loop { body, .. } => sends(body),
+        _ => false,
+    })
+}
+
+fn indent(lines: Vec<String>) -> impl Iterator<Item = String> {
+    lines.into_iter().map(|line| format!("    {}", line))
+}
+
+/// What a plain instruction does, as an assignment.
+fn assignment(instruction: Instruction) -> Option<String> {
+    match instruction {
+        Instruction::Copy(x, y) => Some(format!("{} = {}", y, x)),
+        Instruction::Inc(x) => Some(format!("{} += 1", x)),
+        Instruction::Dec(x) => Some(format!("{} -= 1", x)),
+        Instruction::Add(x, y) => Some(format!("{} += {}", y, x)),
+        Instruction::Mul(x, y) => Some(format!("{} *= {}", y, x)),
+        _ => None,
+    }
+}
+
+fn pseudo(statements: &[Statement]) -> Vec<String> {
+    let mut lines = vec![];
+    for statement in statements {
+        match statement {
+            Statement::Plain(_, Instruction::Nop) => (),
+            Statement::Plain(_, Instruction::Out(x)) => lines.push(format!("out {}", x)),
+            Statement::Plain(_, instruction) => lines.extend(assignment(*instruction)),
+            Statement::Fused(_, fused, plain) => {
+                lines.push(format!("if {} {{", guard(fused)));
+                lines.push(format!("    {}", fused));
+                lines.push("} else {".to_string());
+                lines.extend(indent(pseudo(plain)));
+                lines.push("}".to_string());
+            }
+            Statement::If { test, zero, body } => {
+                let comparison = if *zero { "==" } else { "!=" };
+                lines.push(format!("if {} {} 0 {{", test, comparison));
+                lines.extend(indent(pseudo(body)));
+                lines.push("}".to_string());
+            }
+            Statement::Loop {
+                test: Source::Value(_),
+                body,
+            } => {
+                lines.push("loop {".to_string());
+                lines.extend(indent(pseudo(body)));
+                lines.push("}".to_string());
+            }
+            Statement::Loop { test, body } => {
+                lines.push("do {".to_string());
+                lines.extend(indent(pseudo(body)));
+                lines.push(format!("}} while {} != 0", test));
+            }
+            Statement::Raw(pc, instruction) => {
+                lines.push(format!("{:<12} // {}: left as is", instruction, pc))
+            }
+        }
+    }
+    lines
+}
+
+fn rust(statements: &[Statement]) -> Vec<String> {
+    let mut lines = vec![];
+    for statement in statements {
+        match statement {
+            Statement::Plain(_, Instruction::Out(x)) => lines.push(format!("out.push({});", x)),
+            Statement::Plain(_, instruction) => {
+                lines.extend(assignment(*instruction).map(|a| format!("{};", a)))
+            }
+            Statement::Fused(_, fused, plain) => {
+                lines.push(format!("if {} {{", guard(fused)));
+                match *fused {
+                    Fused::Add { from, to } => {
+                        let (from, to) = (register_name(from), register_name(to));
+                        lines.push(format!("    {} += {};", to, from));
+                        lines.push(format!("    {} = 0;", from));
+                    }
+                    Fused::Mul {
+                        by,
+                        counter,
+                        to,
+                        times,
+                    } => {
+                        let (counter, to, times) = (
+                            register_name(counter),
+                            register_name(to),
+                            register_name(times),
+                        );
+                        lines.push(format!("    {} += {} * {};", to, by, times));
+                        lines.push(format!("    {} = 0;", counter));
+                        lines.push(format!("    {} = 0;", times));
+                    }
+                }
+                lines.push("} else {".to_string());
+                lines.extend(indent(rust(plain)));
+                lines.push("}".to_string());
+            }
+            Statement::If { test, zero, body } => {
+                let comparison = if *zero { "==" } else { "!=" };
+                lines.push(format!("if {} {} 0 {{", test, comparison));
+                lines.extend(indent(rust(body)));
+                lines.push("}".to_string());
+            }
+            Statement::Loop { test, body } => {
+                lines.push("loop {".to_string());
+                lines.extend(indent(rust(body)));
+                if let Source::Register(_) = test {
+                    lines.push(format!("    if {} == 0 {{", test));
+                    lines.push("        break;".to_string());
+                    lines.push("    }".to_string());
+                }
+                lines.push("}".to_string());
+            }
+            Statement::Raw(..) => unreachable!("only structured programs become Rust"),
+        }
+    }
+    lines
+}
+
+/// The program as pseudo-code.
+impl Display for Decompiled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in pseudo(&self.statements) {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembunny::limits::Limits;
+    use crate::assembunny::Fault;
+    use crate::context::Context;
+
+    fn program(s: &str) -> Program {
+        Program::parse(s, &Context::new().with_strict(true)).unwrap()
+    }
+
+    /// The shape of the day 12 programs, with smaller numbers.
+    const FIBONACCI: &str = "cpy 1 a
+cpy 1 b
+cpy 6 d
+jnz c 2
+jnz 1 5
+cpy 7 c
+inc d
+dec c
+jnz c -2
+cpy a c
+inc a
+dec b
+jnz b -2
+cpy c b
+dec d
+jnz d -6
+cpy 3 c
+cpy 4 d
+inc a
+dec d
+jnz d -2
+dec c
+jnz c -5";
+
+    #[test]
+    fn recovers_loops_and_conditions() {
+        let decompiled = decompile(&program(FIBONACCI));
+        assert!(decompiled.is_structured());
+        assert_eq!(
+            decompiled.to_string(),
+            "a = 1
+b = 1
+d = 6
+if c != 0 {
+    c = 7
+    if c > 0 {
+        d += c, c = 0
+    } else {
+        do {
+            d += 1
+            c -= 1
+        } while c != 0
+    }
+}
+do {
+    c = a
+    if b > 0 {
+        a += b, b = 0
+    } else {
+        do {
+            a += 1
+            b -= 1
+        } while b != 0
+    }
+    b = c
+    d -= 1
+} while d != 0
+c = 3
+if c > 0 {
+    a += 4 * c, d = 0, c = 0
+} else {
+    do {
+        d = 4
+        if d > 0 {
+            a += d, d = 0
+        } else {
+            do {
+                a += 1
+                d -= 1
+            } while d != 0
+        }
+        c -= 1
+    } while c != 0
+}
+"
+        );
+    }
+
+    #[test]
+    fn writes_rust() {
+        let decompiled = decompile(&program("cpy 3 b\ninc a\nout a\ndec b\njnz b -3"));
+        assert_eq!(
+            decompiled.to_rust("count").unwrap(),
+            "fn count(registers: [i64; 4], out: &mut Vec<i64>) -> [i64; 4] {
+    let [mut a, mut b, mut c, mut d] = registers;
+    b = 3;
+    loop {
+        a += 1;
+        out.push(a);
+        b -= 1;
+        if b == 0 {
+            break;
+        }
+    }
+    [a, b, c, d]
+}"
+        );
+    }
+
+    #[test]
+    fn leaves_what_it_cannot_structure() {
+        // A jump into the middle of a loop and a toggle.
+        let decompiled = decompile(&program("cpy 4 b\njnz 1 2\ninc a\ndec b\njnz b -2\ntgl a"));
+        assert!(!decompiled.is_structured());
+        assert_eq!(decompiled.to_rust("f"), None);
+        let far = decompile(&program("inc a\njnz 1 9223372036854775807\ninc a"));
+        assert!(!far.is_structured());
+        assert_eq!(
+            decompiled.to_string(),
+            "b = 4
+jnz 1 2      // 1: left as is
+a += 1
+b -= 1
+jnz b -2     // 4: left as is
+tgl a        // 5: left as is
+"
+        );
+    }
+
+    /// Runs structured code the way the Rust function would, for at most
+    /// `fuel` statements; whether it got to the end.
+    fn run(
+        statements: &[Statement],
+        registers: &mut [i64],
+        out: &mut Vec<i64>,
+        fuel: &mut u64,
+    ) -> bool {
+        let value = |s: Source, registers: &[i64]| match s {
+            Source::Value(v) => v,
+            Source::Register(r) => registers[r],
+        };
+        for statement in statements {
+            if *fuel == 0 {
+                return false;
+            }
+            *fuel -= 1;
+            match statement {
+                Statement::Plain(_, instruction) => match *instruction {
+                    Instruction::Copy(x, Source::Register(y)) => registers[y] = value(x, registers),
+                    Instruction::Inc(Source::Register(x)) => registers[x] += 1,
+                    Instruction::Dec(Source::Register(x)) => registers[x] -= 1,
+                    Instruction::Add(x, Source::Register(y)) => registers[y] += value(x, registers),
+                    Instruction::Mul(x, Source::Register(y)) => registers[y] *= value(x, registers),
+                    Instruction::Out(x) => out.push(value(x, registers)),
+                    _ => (),
+                },
+                Statement::Fused(_, fused, _) if fused.runs(registers) => {
+                    fused.run(registers).expect("no overflow");
+                }
+                Statement::Fused(_, _, plain) => {
+                    if !run(plain, registers, out, fuel) {
+                        return false;
+                    }
+                }
+                Statement::If { test, zero, body } => {
+                    if (value(*test, registers) == 0) == *zero && !run(body, registers, out, fuel) {
+                        return false;
+                    }
+                }
+                Statement::Loop { test, body } => loop {
+                    if !run(body, registers, out, fuel) {
+                        return false;
+                    }
+                    if value(*test, registers) == 0 {
+                        break;
+                    }
+                },
+                Statement::Raw(..) => panic!("not structured"),
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn computes_what_the_program_does() {
+        let programs = [
+            FIBONACCI,
+            "cpy 3 b\ninc a\nout a\ndec b\njnz b -3",
+            "cpy 2 a\ncpy 3 d\ncpy a c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\nout a",
+            "jnz a 2\ncpy 5 b\nadd b c\nmul 3 c\nout c",
+        ];
+        for source in programs {
+            let program = program(source);
+            let decompiled = decompile(&program);
+            assert!(decompiled.is_structured(), "{}", decompiled);
+            for (c, a) in [(0, 0), (1, 1)] {
+                let mut machine = program.machine().with('a', a).unwrap();
+                machine.set('c', c).unwrap();
+                machine.finish().unwrap();
+                let (mut registers, mut out, mut fuel) = (vec![a, 0, c, 0], vec![], u64::MAX);
+                assert!(run(
+                    decompiled.statements(),
+                    &mut registers,
+                    &mut out,
+                    &mut fuel
+                ));
+                assert_eq!(
+                    (registers.as_slice(), out.as_slice()),
+                    (machine.registers(), machine.output()),
+                    "{}",
+                    decompiled
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_loops_with_non_positive_counters() {
+        let add = "inc a\ndec b\njnz b -2";
+        let mul = "cpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5";
+        for (source, b, d) in [
+            (add, 0, 0),
+            (add, -3, 0),
+            (mul, 2, 0),
+            (mul, 0, 2),
+            (mul, -1, 2),
+        ] {
+            let program = program(source);
+            let decompiled = decompile(&program);
+            let text = decompiled.to_string();
+            assert!(text.contains("} else {\n    do {"), "{}", text);
+            let mut machine = program
+                .machine()
+                .with_limits(Limits::new().with_steps(1000))
+                .with('b', b)
+                .unwrap();
+            machine.set('d', d).unwrap();
+            assert_eq!(machine.finish(), Err(Fault::OutOfSteps { steps: 1000 }));
+            // The fused form would stop at once, the loop runs on as the
+            // program does.
+            let (mut registers, mut out) = (vec![0, b, 0, d], vec![]);
+            assert!(
+                !run(decompiled.statements(), &mut registers, &mut out, &mut 1000),
+                "{} b={} d={}",
+                source,
+                b,
+                d
+            );
+            assert!(registers[0] > 0);
+        }
+        let rust = decompile(&program(add)).to_rust("add").unwrap();
+        assert!(
+            rust.contains(
+                "    if b > 0 {\n        a += b;\n        b = 0;\n    } else {\n        loop {"
+            ),
+            "{}",
+            rust
+        );
+    }
+}
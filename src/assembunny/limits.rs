@@ -0,0 +1,167 @@
+use super::{Fault, Instruction, Snapshot};
+use std::time::{Duration, Instant};
+
+/// How many steps run between two looks at the clock.
+const CLOCK: u64 = 1 << 12;
+
+/// What a machine may do before it gives up with a fault: at most so many
+/// steps, for at most so long, and, when loops are watched for, never come
+/// back to a state it was in before.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    steps: Option<u64>,
+    time: Option<Duration>,
+    loops: bool,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Limits::default()
+    }
+
+    pub fn with_steps(mut self, steps: u64) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Stops a machine that is back in a state it was in before: it would
+    /// repeat itself forever.
+    pub fn with_loop_detection(mut self) -> Self {
+        self.loops = true;
+        self
+    }
+}
+
+/// Keeps a running machine within its limits. Repeated states are found the
+/// way Brent finds cycles: the state at every power of two steps is kept and
+/// every later state compared with it, so a loop is caught within twice its
+/// length of steps after the machine enters it, without storing every state.
+#[derive(Clone, Debug)]
+pub(super) struct Guard {
+    limits: Limits,
+    /// The steps the machine had run when the limits were set.
+    start: u64,
+    started: Option<Instant>,
+    saved: Option<(u64, Snapshot)>,
+    /// When the state is saved again.
+    next: u64,
+}
+
+impl Guard {
+    pub(super) fn new(limits: Limits, start: u64) -> Self {
+        Guard {
+            limits,
+            start,
+            started: None,
+            saved: None,
+            next: 1,
+        }
+    }
+
+    /// Checks the machine before it runs step `steps` (counting from zero).
+    pub(super) fn check(
+        &mut self,
+        steps: u64,
+        pc: usize,
+        registers: &[i64],
+        instructions: &[Instruction],
+    ) -> Result<(), Fault> {
+        let run = steps - self.start;
+        if self.limits.steps.is_some_and(|limit| run >= limit) {
+            return Err(Fault::OutOfSteps { steps: run });
+        }
+        if let Some(time) = self.limits.time {
+            let started = *self.started.get_or_insert_with(Instant::now);
+            if run.is_multiple_of(CLOCK) && started.elapsed() >= time {
+                return Err(Fault::OutOfTime { steps: run, time });
+            }
+        }
+        if !self.limits.loops {
+            return Ok(());
+        }
+        if let Some((first, saved)) = &self.saved {
+            // The program rarely changes, so it is compared last.
+            if saved.pc == pc && saved.registers == registers && saved.instructions == instructions
+            {
+                return Err(Fault::Loops {
+                    pc,
+                    first: *first,
+                    again: steps,
+                });
+            }
+        }
+        if self.saved.is_none() || steps >= self.next {
+            let snapshot = Snapshot {
+                instructions: instructions.to_vec(),
+                registers: registers.to_vec(),
+                pc,
+            };
+            self.saved = Some((steps, snapshot));
+            self.next = (steps * 2).max(1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembunny::Program;
+    use crate::context::Context;
+
+    fn program(s: &str) -> Program {
+        Program::parse(s, &Context::new().with_strict(true)).unwrap()
+    }
+
+    #[test]
+    fn stops_after_steps_or_time() {
+        let forever = program("inc a\njnz 1 -1");
+        let mut machine = forever.machine().with_limits(Limits::new().with_steps(10));
+        assert_eq!(machine.finish(), Err(Fault::OutOfSteps { steps: 10 }));
+        assert_eq!((machine.steps(), machine.get('a').unwrap()), (10, 5));
+        // A limit set later counts from there.
+        machine.set_limits(Limits::new().with_steps(4));
+        assert_eq!(machine.finish(), Err(Fault::OutOfSteps { steps: 4 }));
+        assert_eq!((machine.steps(), machine.get('a').unwrap()), (14, 7));
+        let mut machine = forever
+            .machine()
+            .with_limits(Limits::new().with_time(Duration::from_millis(10)));
+        let fault = machine.finish().err().unwrap();
+        assert!(
+            fault.to_string().starts_with("ran out of time"),
+            "{}",
+            fault
+        );
+    }
+
+    #[test]
+    fn finds_repeated_states() {
+        let limits = Limits::new().with_loop_detection();
+        // Counts down to zero and then back up, forever.
+        let bounce = program("cpy 5 a\ndec a\njnz a -1\ninc a\njnz 1 -3");
+        let mut machine = bounce.machine().with_limits(limits.clone());
+        match machine.finish() {
+            Err(Fault::Loops { pc, first, again }) => {
+                assert!(first < again, "{} {}", first, again);
+                assert_eq!((again - first) % 4, 0);
+                assert!(again < 64);
+                assert!(pc < 5);
+            }
+            r => panic!("{:?}", r),
+        }
+        // Growing registers never repeat, a halting program is not stopped.
+        let mut machine = program("inc a\njnz 1 -1")
+            .machine()
+            .with_limits(limits.clone().with_steps(1000));
+        assert_eq!(machine.finish(), Err(Fault::OutOfSteps { steps: 1000 }));
+        let mut machine = program("cpy 3 a\ndec a\njnz a -1")
+            .machine()
+            .with_limits(limits);
+        assert_eq!(machine.finish(), Ok(()));
+    }
+}
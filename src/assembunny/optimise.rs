@@ -0,0 +1,257 @@
+use super::{register_name, Instruction, Source};
+use std::fmt::{Display, Formatter};
+
+/// A loop of plain instructions done as arithmetic in a single step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fused {
+    /// `inc to; dec from; jnz from -2`, in either order of the first two:
+    /// `to += from`, `from = 0`.
+    Add { from: usize, to: usize },
+    /// `cpy by counter` followed by the add loop of `counter` into `to`,
+    /// `dec times; jnz times -5`: `to += by * times`, `counter = 0`,
+    /// `times = 0`.
+    Mul {
+        by: Source,
+        counter: usize,
+        to: usize,
+        times: usize,
+    },
+}
+
+impl Fused {
+    /// How many instructions the loop takes up.
+    pub fn width(&self) -> usize {
+        match self {
+            Fused::Add { .. } => 3,
+            Fused::Mul { .. } => 6,
+        }
+    }
+
+    /// Whether the loop would run at least once on `registers`. It is only
+    /// done as arithmetic then; a loop that would not start is left to the
+    /// plain instructions, which do whatever they do.
+    pub fn runs(&self, registers: &[i64]) -> bool {
+        match *self {
+            Fused::Add { from, .. } => registers[from] > 0,
+            Fused::Mul { by, times, .. } => by.value(registers) > 0 && registers[times] > 0,
+        }
+    }
+
+    /// Does the whole loop at once on `registers`, `None` when the result
+    /// overflows, leaving them as they were.
+    pub fn run(&self, registers: &mut [i64]) -> Option<()> {
+        match *self {
+            Fused::Add { from, to } => {
+                registers[to] = registers[to].checked_add(registers[from])?;
+                registers[from] = 0;
+            }
+            Fused::Mul {
+                by,
+                counter,
+                to,
+                times,
+            } => {
+                let product = by.value(registers).checked_mul(registers[times])?;
+                registers[to] = registers[to].checked_add(product)?;
+                registers[counter] = 0;
+                registers[times] = 0;
+            }
+        }
+        Some(())
+    }
+
+    /// The registers the loop changes, which must be apart from each other
+    /// and from what it reads for the arithmetic to be right.
+    fn distinct(&self) -> bool {
+        match *self {
+            Fused::Add { from, to } => from != to,
+            Fused::Mul {
+                by,
+                counter,
+                to,
+                times,
+            } => {
+                [counter, to, times]
+                    .iter()
+                    .all(|r| Source::Register(*r) != by)
+                    && counter != to
+                    && counter != times
+                    && to != times
+            }
+        }
+    }
+}
+
+impl Display for Fused {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match *self {
+            Fused::Add { from, to } => {
+                let (from, to) = (register_name(from), register_name(to));
+                format!("{} += {}, {} = 0", to, from, from)
+            }
+            Fused::Mul {
+                by,
+                counter,
+                to,
+                times,
+            } => {
+                let (counter, to, times) = (
+                    register_name(counter),
+                    register_name(to),
+                    register_name(times),
+                );
+                format!(
+                    "{} += {} * {}, {} = 0, {} = 0",
+                    to, by, times, counter, times
+                )
+            }
+        };
+        f.pad(&s)
+    }
+}
+
+/// `inc to; dec from; jnz from -2` or `dec from; inc to; jnz from -2`.
+fn add_loop(window: &[Instruction]) -> Option<(usize, usize)> {
+    use Instruction::*;
+    use Source::{Register as R, Value as V};
+    let (from, to) = match *window.get(..3)? {
+        [Inc(R(to)), Dec(R(from)), JumpNonZero(R(j), V(-2))]
+        | [Dec(R(from)), Inc(R(to)), JumpNonZero(R(j), V(-2))]
+            if j == from =>
+        {
+            (from, to)
+        }
+        _ => return None,
+    };
+    Some((from, to))
+}
+
+/// The loop starting at the first instruction of `window`, if it is one.
+fn fuse(window: &[Instruction]) -> Option<Fused> {
+    use Instruction::*;
+    use Source::{Register as R, Value as V};
+    let mul = match *window {
+        [Copy(by, R(counter)), _, _, _, Dec(R(times)), JumpNonZero(R(j), V(-5)), ..]
+            if j == times =>
+        {
+            add_loop(&window[1..])
+                .filter(|(from, _)| *from == counter)
+                .map(|(_, to)| Fused::Mul {
+                    by,
+                    counter,
+                    to,
+                    times,
+                })
+        }
+        _ => None,
+    };
+    mul.or_else(|| add_loop(window).map(|(from, to)| Fused::Add { from, to }))
+        .filter(Fused::distinct)
+}
+
+/// The peephole pass: for every instruction the loop that starts there, if
+/// any. Loops are not cut out of the program, so jumps into them still land
+/// on the plain instructions; only running into a loop from its first
+/// instruction takes the fused step.
+pub fn optimise(instructions: &[Instruction]) -> Vec<Option<Fused>> {
+    (0..instructions.len())
+        .map(|i| fuse(&instructions[i..]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembunny::Program;
+    use crate::context::Context;
+
+    fn program(s: &str) -> Program {
+        Program::parse(s, &Context::new().with_strict(true)).unwrap()
+    }
+
+    #[test]
+    fn finds_loops() {
+        let program = program(
+            "cpy 3 b\ninc a\ndec b\njnz b -2\ncpy a c\ndec c\ninc d\njnz c -2\n\
+             cpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\ninc a\ndec a\njnz a -2",
+        );
+        let fused = optimise(program.instructions())
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| f.map(|f| format!("{}: {}", i, f)))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            fused,
+            [
+                "1: a += b, b = 0",
+                "5: d += c, c = 0",
+                "8: a += b * d, c = 0, d = 0",
+                "9: a += c, c = 0",
+            ]
+        );
+    }
+
+    /// Runs a program with and without the optimiser, from the same registers.
+    fn both(program: &str, registers: &[(char, i64)]) -> [(Vec<i64>, Vec<i64>); 2] {
+        let program = self::program(program);
+        [true, false].map(|optimise| {
+            let mut machine = program.machine().with_optimiser(optimise);
+            for (name, value) in registers {
+                machine.set(*name, *value).unwrap();
+            }
+            machine.finish().unwrap();
+            (machine.registers().to_vec(), machine.output().to_vec())
+        })
+    }
+
+    #[test]
+    fn keeps_results() {
+        let programs: [(&str, &[(char, i64)]); 4] = [
+            // Fibonacci, the way day 12 computes it.
+            (
+                "cpy 1 a\ncpy 1 b\ncpy 20 d\ncpy a c\ninc a\ndec b\njnz b -2\ncpy c b\ndec d\njnz d -6",
+                &[],
+            ),
+            // Factorial with a toggled jump, the way day 23 does.
+            (
+                "cpy a b\ndec b\ncpy a d\ncpy 0 a\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\n\
+                 dec b\ncpy b c\ncpy c d\ndec d\ninc c\njnz d -2\ntgl c\ncpy -16 c\njnz 1 c\n\
+                 cpy 7 c\njnz 9 d\ninc a\ninc d\njnz d -2\ninc c\njnz c -5",
+                &[('a', 7)],
+            ),
+            // A jump into the middle of a loop.
+            ("cpy 4 b\njnz 1 2\ninc a\ndec b\njnz b -2\nout a", &[]),
+            // A multiplication reading the register it adds to is left alone.
+            (
+                "cpy 2 a\ncpy 3 d\ncpy a c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\nout a",
+                &[],
+            ),
+        ];
+        for (program, registers) in programs {
+            let [optimised, plain] = both(program, registers);
+            assert_eq!(optimised, plain, "{}", program);
+        }
+        let [(registers, _), _] = both(programs[1].0, programs[1].1);
+        assert_eq!(registers[0], 5040 + 7 * 9);
+    }
+
+    #[test]
+    fn runs_loops_at_once() {
+        let mul = Fused::Mul {
+            by: Source::Register(1),
+            counter: 2,
+            to: 0,
+            times: 3,
+        };
+        let mut registers = [1, 6, 6, 7];
+        assert!(mul.runs(&registers));
+        assert_eq!(mul.run(&mut registers), Some(()));
+        assert_eq!(registers, [43, 6, 0, 0]);
+        assert!(!mul.runs(&registers));
+
+        let add = Fused::Add { from: 1, to: 0 };
+        let mut registers = [i64::MAX, 1, 0, 0];
+        assert_eq!(add.run(&mut registers), None);
+        assert_eq!(registers, [i64::MAX, 1, 0, 0]);
+    }
+}
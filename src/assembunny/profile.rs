@@ -0,0 +1,150 @@
+use super::Instruction;
+use std::collections::HashMap;
+
+/// Where a machine spent its steps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    hits: Vec<u64>,
+    fused: Vec<u64>,
+    /// How often every jump back was taken, by where it jumped from and to.
+    back: HashMap<(usize, usize), u64>,
+}
+
+/// A loop found by the jump back closing it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    /// How often the jump back was taken.
+    pub jumps: u64,
+    /// The steps run inside the loop, its inner loops included.
+    pub steps: u64,
+}
+
+impl Profile {
+    pub fn new(len: usize) -> Self {
+        Profile {
+            hits: vec![0; len],
+            fused: vec![0; len],
+            back: HashMap::new(),
+        }
+    }
+
+    /// Counts a step from `pc`, which took the machine to `next`.
+    pub(super) fn record(&mut self, pc: usize, next: usize, fused: bool) {
+        self.hits[pc] += 1;
+        if fused {
+            self.fused[pc] += 1;
+        }
+        if next <= pc {
+            *self.back.entry((pc, next)).or_insert(0) += 1;
+        }
+    }
+
+    /// How many steps started at every instruction.
+    pub fn hits(&self) -> &[u64] {
+        &self.hits
+    }
+
+    /// How many of those ran a whole fused loop in one step.
+    pub fn fused(&self) -> &[u64] {
+        &self.fused
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.hits.iter().sum()
+    }
+
+    /// The loop that jumped back most often, the innermost of those jumping
+    /// back as often.
+    pub fn hottest_loop(&self) -> Option<Loop> {
+        self.back
+            .iter()
+            .map(|(&(end, start), &jumps)| Loop {
+                start,
+                end,
+                jumps,
+                steps: self.hits[start..=end].iter().sum(),
+            })
+            .max_by_key(|l| (l.jumps, l.start, usize::MAX - l.end))
+    }
+
+    /// The hits of every instruction next to it, and the hottest loop.
+    pub fn report(&self, instructions: &[Instruction]) -> String {
+        let total = self.steps().max(1);
+        let mut lines = instructions
+            .iter()
+            .enumerate()
+            .map(|(pc, instruction)| {
+                let fused = match self.fused[pc] {
+                    0 => String::new(),
+                    n => format!(" ({} fused)", n),
+                };
+                format!(
+                    "{:>3}: {:<12} {:>10} {:>5.1}%{}",
+                    pc,
+                    instruction,
+                    self.hits[pc],
+                    self.hits[pc] as f64 * 100.0 / total as f64,
+                    fused
+                )
+            })
+            .collect::<Vec<String>>();
+        if let Some(hottest) = self.hottest_loop() {
+            lines.push(format!(
+                "hottest loop {}..={}: jumped back {} times, {} steps ({:.1}%)",
+                hottest.start,
+                hottest.end,
+                hottest.jumps,
+                hottest.steps,
+                hottest.steps as f64 * 100.0 / total as f64
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembunny::Program;
+    use crate::context::Context;
+
+    #[test]
+    fn counts_hits_and_finds_hottest_loop() {
+        // 3 times round the outer loop, 2 times round the inner one each.
+        let program = Program::parse(
+            "cpy 3 d\ncpy 2 c\ninc a\ndec c\njnz c -2\ndec d\ninc b\njnz d -6",
+            &Context::new().with_strict(true),
+        )
+        .unwrap();
+        let mut machine = program.machine().with_optimiser(false).with_profile();
+        machine.finish().unwrap();
+        let profile = machine.profile().unwrap();
+        assert_eq!(profile.hits(), [1, 3, 6, 6, 6, 3, 3, 3]);
+        assert_eq!(profile.steps(), machine.steps());
+        assert_eq!(
+            profile.hottest_loop(),
+            Some(Loop {
+                start: 2,
+                end: 4,
+                jumps: 3,
+                steps: 18
+            })
+        );
+        let report = profile.report(machine.instructions());
+        assert!(
+            report.contains("  2: inc a                 6  19.4%"),
+            "{}",
+            report
+        );
+        assert!(report.ends_with("hottest loop 2..=4: jumped back 3 times, 18 steps (58.1%)"));
+
+        let mut machine = program.machine().with_profile();
+        machine.finish().unwrap();
+        let profile = machine.profile().unwrap();
+        assert_eq!(profile.hits(), [1, 3, 3, 0, 0, 3, 3, 3]);
+        assert_eq!(profile.fused()[2], 3);
+        assert!(profile.report(machine.instructions()).contains("(3 fused)"));
+    }
+}
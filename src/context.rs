@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::error::{Error, ParseError};
+use crate::progress::{Progress, Stopped};
+use crate::solver::Param;
+use crate::trace::Trace;
+
+/// Everything a solver needs to know about the run besides the puzzle input.
+#[derive(Default)]
+pub struct Context {
+    source: Option<String>,
+    strict: bool,
+    threads: usize,
+    params: BTreeMap<String, String>,
+    skipped: Mutex<Vec<ParseError>>,
+    trace: Option<Trace>,
+    progress: Option<Progress>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context::default()
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// How many threads a solver may use for itself.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.max(1)
+    }
+
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Records a step of the solver when tracing; `event` describes its state.
+    pub fn step<F: FnOnce() -> String>(&self, event: F) {
+        if let Some(trace) = &self.trace {
+            trace.step(event);
+        }
+    }
+
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn progress(&self) -> Option<&Progress> {
+        self.progress.as_ref()
+    }
+
+    /// Reports how far a long running solver got and tells it whether to go
+    /// on. Without progress attached it never stops the solver.
+    pub fn tick(&self, done: u64, total: Option<u64>) -> Result<(), Stopped> {
+        match &self.progress {
+            Some(progress) => progress.tick(done, total),
+            None => Ok(()),
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// The first given parameter a solver does not declare, if any.
+    pub fn unknown_param(&self, accepted: &[Param]) -> Option<&str> {
+        self.params
+            .keys()
+            .find(|name| accepted.iter().all(|p| p.name != name.as_str()))
+            .map(|name| name.as_str())
+    }
+
+    /// The value of a parameter, or `default` when it was not given.
+    pub fn param<T: FromStr>(
+        &self,
+        name: &'static str,
+        default: T,
+        expected: &'static str,
+    ) -> Result<T, Error> {
+        match self.params.get(name) {
+            Some(value) => value.parse::<T>().map_err(|_| Error::Param {
+                name,
+                value: value.clone(),
+                expected,
+            }),
+            None => Ok(default),
+        }
+    }
+
+    /// Like `param`, for a comma separated list of values.
+    pub fn param_list<T: FromStr>(
+        &self,
+        name: &'static str,
+        default: Vec<T>,
+        expected: &'static str,
+    ) -> Result<Vec<T>, Error> {
+        match self.params.get(name) {
+            Some(value) if value.is_empty() => Ok(vec![]),
+            Some(value) => value
+                .split(',')
+                .map(|v| v.trim().parse::<T>())
+                .collect::<Result<Vec<T>, _>>()
+                .map_err(|_| Error::Param {
+                    name,
+                    value: value.clone(),
+                    expected,
+                }),
+            None => Ok(default),
+        }
+    }
+
+    /// Like `param`, for exactly two comma separated values.
+    pub fn param_pair<T: FromStr>(
+        &self,
+        name: &'static str,
+        default: (T, T),
+        expected: &'static str,
+    ) -> Result<(T, T), Error> {
+        let (a, b) = default;
+        let mut values = self.param_list(name, vec![a, b], expected)?;
+        match (values.pop(), values.pop(), values.is_empty()) {
+            (Some(b), Some(a), true) => Ok((a, b)),
+            _ => Err(Error::Param {
+                name,
+                value: self.params[name].clone(),
+                expected,
+            }),
+        }
+    }
+
+    fn locate(&self, e: ParseError, line: usize) -> ParseError {
+        let e = e.at_line(line);
+        match &self.source {
+            Some(source) => e.in_source(source),
+            None => e,
+        }
+    }
+
+    /// Parses every non-empty line of the input. Malformed lines fail the run
+    /// in strict mode and are skipped otherwise; skipped lines are remembered
+    /// once and can be reported with `skipped`.
+    pub fn lines<T: FromStr<Err = ParseError>>(&self, input: &str) -> Result<Vec<T>, ParseError> {
+        self.lines_with(input, str::parse::<T>)
+    }
+
+    /// Like `lines`, for values that need more than the line to be parsed.
+    pub fn lines_with<T, F>(&self, input: &str, parse: F) -> Result<Vec<T>, ParseError>
+    where
+        F: Fn(&str) -> Result<T, ParseError>,
+    {
+        let mut result = vec![];
+        for (i, l) in input.lines().enumerate() {
+            if l.trim().is_empty() {
+                continue;
+            }
+            match parse(l) {
+                Ok(value) => result.push(value),
+                Err(e) => {
+                    let e = self.locate(e, i + 1);
+                    if self.strict {
+                        return Err(e);
+                    }
+                    let mut skipped = self.skipped.lock().unwrap();
+                    if !skipped.contains(&e) {
+                        skipped.push(e);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Parses the whole input, without surrounding whitespace, as a single value.
+    pub fn parse<T: FromStr<Err = ParseError>>(&self, input: &str) -> Result<T, ParseError> {
+        let trimmed = input.trim_start();
+        let offset = input.len() - trimmed.len();
+        let line = input[..offset].matches('\n').count() + 1;
+        trimmed
+            .trim_end()
+            .parse::<T>()
+            .map_err(|e| self.locate(e, line))
+    }
+
+    pub fn skipped(&self) -> Vec<ParseError> {
+        self.skipped.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Digit(u32);
+
+    impl FromStr for Digit {
+        type Err = ParseError;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse::<u32>()
+                .map(Digit)
+                .map_err(|_| ParseError::new(1, s, "digit"))
+        }
+    }
+
+    #[test]
+    fn skips_malformed_lines_unless_strict() {
+        let input = "1\n\nx\n3";
+        let ctx = Context::new().with_source("digits");
+        let digits = ctx.lines::<Digit>(input).unwrap();
+        assert_eq!(digits.iter().map(|d| d.0).collect::<Vec<u32>>(), [1, 3]);
+        ctx.lines::<Digit>(input).unwrap();
+        let skipped = ctx.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            skipped[0].to_string(),
+            "digits:3:1: expected digit, found `x`"
+        );
+
+        let ctx = Context::new().with_strict(true);
+        let e = ctx.lines::<Digit>(input).err().unwrap();
+        assert_eq!(e.line, 3);
+        assert!(ctx.skipped().is_empty());
+    }
+
+    #[test]
+    fn parses_whole_input() {
+        let ctx = Context::new();
+        assert_eq!(ctx.parse::<Digit>("\n  7 \n").unwrap().0, 7);
+        assert_eq!(ctx.parse::<Digit>("\n\nx").err().unwrap().line, 3);
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::{Display, Formatter};
+
+use crate::assembunny::Fault;
+use crate::progress::Stopped;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub source: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub expected: &'static str,
+}
+
+impl ParseError {
+    pub fn new(column: usize, token: &str, expected: &'static str) -> Self {
+        ParseError {
+            source: None,
+            line: 0,
+            column,
+            token: token.to_string(),
+            expected,
+        }
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub fn shifted(mut self, columns: usize) -> Self {
+        self.column += columns;
+        self
+    }
+
+    pub fn in_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(source) = &self.source {
+            write!(f, "{}:", source)?;
+        }
+        if self.line > 0 {
+            write!(f, "{}:", self.line)?;
+        }
+        write!(f, "{}: expected {}", self.column, self.expected)?;
+        if self.token.is_empty() {
+            write!(f, ", found end of line")
+        } else {
+            write!(f, ", found `{}`", self.token)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    NoAnswer(&'static str),
+    Param {
+        name: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    Stopped(Stopped),
+    Fault(Fault),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::NoAnswer(reason) => write!(f, "no answer: {}", reason),
+            Error::Param {
+                name,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid parameter {}={}: expected {}",
+                name, value, expected
+            ),
+            Error::Stopped(stopped) => write!(f, "stopped: {}", stopped),
+            Error::Fault(fault) => write!(f, "fault: {}", fault),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<Stopped> for Error {
+    fn from(stopped: Stopped) -> Self {
+        Error::Stopped(stopped)
+    }
+}
+
+impl From<Fault> for Error {
+    fn from(fault: Fault) -> Self {
+        Error::Fault(fault)
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Sub};
+
+/// A location on a grid. `y` grows downwards, the way maps are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    pub fn new(x: i64, y: i64) -> Self {
+        Point { x, y }
+    }
+
+    pub fn manhattan(self, other: Point) -> i64 {
+        (self - other).manhattan()
+    }
+
+    /// The point one step away in `direction`.
+    pub fn step(self, direction: Direction) -> Point {
+        self + direction.vector()
+    }
+
+    /// The four points one step away, in the order of `Direction::ALL`.
+    pub fn neighbours(self) -> impl Iterator<Item = Point> {
+        Direction::ALL.iter().map(move |d| self.step(*d))
+    }
+}
+
+/// The offset between two points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vector {
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Vector {
+    pub fn new(dx: i64, dy: i64) -> Self {
+        Vector { dx, dy }
+    }
+
+    pub fn manhattan(self) -> i64 {
+        self.dx.abs() + self.dy.abs()
+    }
+}
+
+impl Add<Vector> for Point {
+    type Output = Point;
+    fn add(self, v: Vector) -> Point {
+        Point::new(self.x + v.dx, self.y + v.dy)
+    }
+}
+
+impl AddAssign<Vector> for Point {
+    fn add_assign(&mut self, v: Vector) {
+        *self = *self + v;
+    }
+}
+
+impl Sub for Point {
+    type Output = Vector;
+    fn sub(self, other: Point) -> Vector {
+        Vector::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<i64> for Vector {
+    type Output = Vector;
+    fn mul(self, n: i64) -> Vector {
+        Vector::new(self.dx * n, self.dy * n)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    /// Clockwise, starting up.
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn clockwise(self) -> Self {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    pub fn counter_clockwise(self) -> Self {
+        self.clockwise().clockwise().clockwise()
+    }
+
+    pub fn opposite(self) -> Self {
+        self.clockwise().clockwise()
+    }
+
+    pub fn vector(self) -> Vector {
+        match self {
+            Direction::North => Vector::new(0, -1),
+            Direction::East => Vector::new(1, 0),
+            Direction::South => Vector::new(0, 1),
+            Direction::West => Vector::new(-1, 0),
+        }
+    }
+
+    /// Reads one of `U`, `D`, `L` and `R`.
+    pub fn from_letter(c: char) -> Option<Self> {
+        match c {
+            'U' => Some(Direction::North),
+            'D' => Some(Direction::South),
+            'L' => Some(Direction::West),
+            'R' => Some(Direction::East),
+            _ => None,
+        }
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Direction::North => 'U',
+            Direction::South => 'D',
+            Direction::West => 'L',
+            Direction::East => 'R',
+        }
+    }
+}
+
+/// A rectangle of cells with its top left corner at the origin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+}
+
+impl Grid<char> {
+    /// One row per line, short lines are padded with spaces.
+    pub fn from_text(text: &str) -> Self {
+        let rows = text.lines().collect::<Vec<&str>>();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        let mut grid = Grid::new(width, rows.len(), ' ');
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid.cells[y * width + x] = c;
+            }
+        }
+        grid
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn<F: FnMut(Point) -> T>(width: usize, height: usize, mut f: F) -> Self {
+        let cells = (0..width * height)
+            .map(|i| f(Point::new((i % width) as i64, (i / width) as i64)))
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        0 <= p.x && (p.x as usize) < self.width && 0 <= p.y && (p.y as usize) < self.height
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        if self.contains(p) {
+            Some(&self.cells[p.y as usize * self.width + p.x as usize])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        if self.contains(p) {
+            Some(&mut self.cells[p.y as usize * self.width + p.x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Every point of the grid, row by row.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width;
+        (0..self.width * self.height)
+            .map(move |i| Point::new((i % width) as i64, (i / width) as i64))
+    }
+
+    /// The neighbours of `p` that are on the grid.
+    pub fn neighbours(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        p.neighbours().filter(move |n| self.contains(*n))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [T] {
+        &mut self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Draws the grid one character per cell, one line per row.
+    pub fn render<F: Fn(&T) -> char>(&self, f: F) -> String {
+        self.rows()
+            .map(|row| row.iter().map(&f).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+    fn index(&self, p: Point) -> &T {
+        self.get(p)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", p))
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, p: Point) -> &mut T {
+        self.get_mut(p)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", p))
+    }
+}
+
+/// Cells anywhere on the plane, only the ones set take up space.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point, T>,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> Self {
+        SparseGrid::default()
+    }
+
+    /// Sets a cell, returning what it held before.
+    pub fn insert(&mut self, p: Point, value: T) -> Option<T> {
+        self.cells.insert(p, value)
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        self.cells.get(&p)
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        self.cells.contains_key(&p)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.cells.iter().map(|(p, v)| (*p, v))
+    }
+
+    /// The top left and bottom right corners of the cells set.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.cells.keys();
+        let first = *points.next()?;
+        Some(points.fold((first, first), |(min, max), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        }))
+    }
+
+    /// Draws the cells within `bounds`, cells not set become `empty`.
+    pub fn render<F: Fn(&T) -> char>(&self, empty: char, f: F) -> String {
+        let (min, max) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        (min.y..=max.y)
+            .map(|y| {
+                (min.x..=max.x)
+                    .map(|x| self.get(Point::new(x, y)).map_or(empty, &f))
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl<T> FromIterator<(Point, T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = (Point, T)>>(iter: I) -> Self {
+        SparseGrid {
+            cells: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_and_steps() {
+        let d = Direction::North;
+        assert_eq!(d.clockwise(), Direction::East);
+        assert_eq!(d.counter_clockwise(), Direction::West);
+        assert_eq!(d.opposite(), Direction::South);
+        let p = Point::ORIGIN.step(Direction::North) + Direction::East.vector() * 3;
+        assert_eq!(p, Point::new(3, -1));
+        assert_eq!(p.manhattan(Point::ORIGIN), 4);
+        assert_eq!(Direction::from_letter('L').map(|d| d.letter()), Some('L'));
+    }
+
+    #[test]
+    fn keeps_cells_on_the_grid() {
+        let mut grid = Grid::from_text("ab\nc");
+        assert_eq!((grid.width(), grid.height()), (2, 2));
+        assert_eq!(grid[Point::new(1, 0)], 'b');
+        assert_eq!(grid.get(Point::new(2, 0)), None);
+        grid[Point::new(1, 1)] = 'd';
+        let neighbours = grid.neighbours(Point::ORIGIN).collect::<Vec<Point>>();
+        assert_eq!(neighbours, [Point::new(1, 0), Point::new(0, 1)]);
+        assert_eq!(grid.render(|c| *c), "ab\ncd");
+    }
+
+    #[test]
+    fn grows_sparse_grid() {
+        let mut grid = SparseGrid::new();
+        assert_eq!(grid.insert(Point::new(-1, 2), 1), None);
+        assert_eq!(grid.insert(Point::new(1, 3), 2), None);
+        assert_eq!(grid.insert(Point::new(1, 3), 3), Some(2));
+        assert_eq!(grid.bounds(), Some((Point::new(-1, 2), Point::new(1, 3))));
+        assert_eq!(grid.render('.', |_| '#'), "#..\n..#");
+    }
+}
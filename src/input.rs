@@ -0,0 +1,47 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    File(PathBuf),
+    Stdin,
+    Inline(String),
+}
+
+impl InputSource {
+    pub fn for_day(day: u8) -> Self {
+        InputSource::File(PathBuf::from(format!("input/task_{}", day)))
+    }
+
+    pub fn from_arg(arg: &str) -> Self {
+        if arg == "-" {
+            InputSource::Stdin
+        } else {
+            InputSource::File(PathBuf::from(arg))
+        }
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        match self {
+            InputSource::File(path) => fs::read_to_string(path),
+            InputSource::Stdin => {
+                let mut buffer = String::new();
+                io::stdin().read_to_string(&mut buffer)?;
+                Ok(buffer)
+            }
+            InputSource::Inline(s) => Ok(s.clone()),
+        }
+    }
+}
+
+impl Display for InputSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputSource::File(path) => write!(f, "{}", path.display()),
+            InputSource::Stdin => write!(f, "<stdin>"),
+            InputSource::Inline(_) => write!(f, "<inline>"),
+        }
+    }
+}
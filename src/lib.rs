@@ -0,0 +1,39 @@
+pub mod answers;
+pub mod assembunny;
+pub mod context;
+pub mod error;
+pub mod grid;
+pub mod input;
+pub mod json;
+pub mod mining;
+pub mod parse;
+pub mod progress;
+pub mod runner;
+pub mod search;
+pub mod solver;
+pub mod task_1;
+pub mod task_10;
+pub mod task_11;
+pub mod task_12;
+pub mod task_13;
+pub mod task_14;
+pub mod task_15;
+pub mod task_16;
+pub mod task_17;
+pub mod task_18;
+pub mod task_19;
+pub mod task_2;
+pub mod task_20;
+pub mod task_21;
+pub mod task_22;
+pub mod task_23;
+pub mod task_24;
+pub mod task_25;
+pub mod task_3;
+pub mod task_4;
+pub mod task_5;
+pub mod task_6;
+pub mod task_7;
+pub mod task_8;
+pub mod task_9;
+pub mod trace;
@@ -1,81 +1,533 @@
+use aoc_2016::answers::{Answers, Verdict};
+use aoc_2016::assembunny::debug::{Command, Debugger};
+use aoc_2016::assembunny::decompile;
+use aoc_2016::assembunny::Program;
+use aoc_2016::context::Context;
+use aoc_2016::input::InputSource;
+use aoc_2016::json::Value;
+use aoc_2016::progress::Progress;
+use aoc_2016::runner::{self, Job, Timed};
+use aoc_2016::solver::{Part, Registry, Solver};
+use aoc_2016::trace::Trace;
 use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::{Duration, Instant};
 
-mod task_1;
-mod task_10;
-mod task_11;
-mod task_12;
-mod task_13;
-mod task_14;
-mod task_15;
-mod task_16;
-mod task_17;
-mod task_18;
-mod task_19;
-mod task_2;
-mod task_20;
-mod task_21;
-mod task_22;
-mod task_3;
-mod task_4;
-mod task_5;
-mod task_6;
-mod task_7;
-mod task_8;
-mod task_9;
+#[derive(PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+struct Options {
+    input: Option<InputSource>,
+    answers: InputSource,
+    strict: bool,
+    threads: usize,
+    format: Format,
+    params: Vec<(String, String)>,
+    trace: bool,
+    trace_from: usize,
+    trace_limit: Option<usize>,
+    trace_filter: Option<String>,
+    progress: bool,
+    budget: Option<Duration>,
+}
+
+impl Options {
+    fn parse(args: Vec<String>) -> Result<(Options, Vec<String>), String> {
+        let mut options = Options {
+            input: None,
+            answers: InputSource::from_arg("answers"),
+            strict: false,
+            threads: 1,
+            format: Format::Text,
+            params: vec![],
+            trace: false,
+            trace_from: 0,
+            trace_limit: None,
+            trace_filter: None,
+            progress: false,
+            budget: None,
+        };
+        let mut positional = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--input" => {
+                    let path = args.next().ok_or("--input requires a path or `-`")?;
+                    options.input = Some(InputSource::from_arg(&path));
+                }
+                "--inline" => {
+                    let text = args.next().ok_or("--inline requires a value")?;
+                    options.input = Some(InputSource::Inline(text));
+                }
+                "--answers" => {
+                    let path = args.next().ok_or("--answers requires a path or `-`")?;
+                    options.answers = InputSource::from_arg(&path);
+                }
+                "--threads" => {
+                    let threads = args.next().ok_or("--threads requires a number")?;
+                    options.threads = match threads.parse::<usize>() {
+                        Ok(threads) if threads > 0 => threads,
+                        _ => return Err(format!("Invalid number of threads: {}", threads)),
+                    };
+                }
+                "--format" => {
+                    options.format = match args.next().as_deref() {
+                        Some("text") => Format::Text,
+                        Some("json") => Format::Json,
+                        _ => return Err("--format requires `text` or `json`".to_string()),
+                    };
+                }
+                "--param" => {
+                    let param = args.next().ok_or("--param requires `<key>=<value>`")?;
+                    match param.split_once('=') {
+                        Some((key, value)) => {
+                            options.params.push((key.to_string(), value.to_string()))
+                        }
+                        None => {
+                            return Err(format!(
+                                "Invalid parameter, expected `<key>=<value>`: {}",
+                                param
+                            ))
+                        }
+                    }
+                }
+                "--trace" => options.trace = true,
+                "--trace-from" => {
+                    let step = args.next().ok_or("--trace-from requires a step")?;
+                    options.trace_from = step
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid step: {}", step))?;
+                    options.trace = true;
+                }
+                "--trace-limit" => {
+                    let steps = args.next().ok_or("--trace-limit requires a number")?;
+                    options.trace_limit = Some(
+                        steps
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid number of steps: {}", steps))?,
+                    );
+                    options.trace = true;
+                }
+                "--trace-filter" => {
+                    let text = args.next().ok_or("--trace-filter requires a text")?;
+                    options.trace_filter = Some(text);
+                    options.trace = true;
+                }
+                "--progress" => options.progress = true,
+                "--budget" => {
+                    let seconds = args.next().ok_or("--budget requires a number of seconds")?;
+                    options.budget = match seconds.parse::<f64>() {
+                        Ok(s) if s > 0.0 && s.is_finite() => Some(Duration::from_secs_f64(s)),
+                        _ => return Err(format!("Invalid time budget: {}", seconds)),
+                    };
+                }
+                "--strict" => options.strict = true,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+                _ => positional.push(arg),
+            }
+        }
+        Ok((options, positional))
+    }
+
+    fn trace(&self) -> Trace {
+        let mut trace = Trace::new().with_from(self.trace_from);
+        if let Some(limit) = self.trace_limit {
+            trace = trace.with_limit(limit);
+        }
+        if let Some(filter) = &self.trace_filter {
+            trace = trace.with_filter(filter);
+        }
+        trace
+    }
+
+    /// Progress reports and the time budget, if either was asked for.
+    fn progress(&self) -> Option<Progress> {
+        if !self.progress && self.budget.is_none() {
+            return None;
+        }
+        let mut progress = Progress::new();
+        if self.progress {
+            progress = progress.with_reports(Duration::from_secs(1));
+        }
+        if let Some(budget) = self.budget {
+            progress = progress.with_budget(budget);
+        }
+        Some(progress)
+    }
+}
+
+fn read_input(source: &InputSource) -> String {
+    match source.read() {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Cannot read input {}: {}", source, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// A job whose solver may use `threads` threads for itself.
+fn job<'a>(
+    solver: &'a dyn Solver,
+    source: &InputSource,
+    options: &Options,
+    threads: usize,
+) -> Job<'a> {
+    let mut ctx = Context::new()
+        .with_source(&source.to_string())
+        .with_strict(options.strict)
+        .with_threads(threads);
+    if let Some(progress) = options.progress() {
+        ctx = ctx.with_progress(progress);
+    }
+    Job {
+        solver,
+        input: read_input(source),
+        ctx,
+    }
+}
+
+fn report_skipped(jobs: &[Job]) {
+    for job in jobs {
+        for e in job.ctx.skipped() {
+            eprintln!("warning: skipped {}", e);
+        }
+    }
+}
+
+fn digest(jobs: &[Job], day: u8) -> String {
+    jobs.iter()
+        .find(|job| job.solver.day() == day)
+        .map(|job| job.digest())
+        .unwrap_or_default()
+}
+
+fn run(registry: &Registry, options: &Options, day: &str, part: Option<&str>) -> bool {
+    let solver = match day.parse::<u8>().ok().and_then(|d| registry.get(d)) {
+        Some(solver) => solver,
+        None => {
+            println!("Unresolved task: {}", day);
+            return false;
+        }
+    };
+    let parts = match part {
+        Some(part) => match part.parse::<Part>() {
+            Ok(part) => vec![part],
+            Err(_) => {
+                println!("Unresolved part: {}", part);
+                return false;
+            }
+        },
+        None => Part::both().to_vec(),
+    };
+    let source = options
+        .input
+        .clone()
+        .unwrap_or_else(|| InputSource::for_day(solver.day()));
+    let mut jobs = [job(solver, &source, options, options.threads)];
+    for (key, value) in &options.params {
+        jobs[0].ctx = std::mem::take(&mut jobs[0].ctx).with_param(key, value);
+    }
+    if let Some(name) = jobs[0].ctx.unknown_param(solver.params()) {
+        let accepted = solver
+            .params()
+            .iter()
+            .map(|p| p.name)
+            .collect::<Vec<&str>>();
+        eprintln!(
+            "Day {} does not take parameter `{}`, accepted: {}",
+            solver.day(),
+            name,
+            if accepted.is_empty() {
+                "none".to_string()
+            } else {
+                accepted.join(", ")
+            }
+        );
+        return false;
+    }
+    if options.trace {
+        jobs[0].ctx = std::mem::take(&mut jobs[0].ctx).with_trace(options.trace());
+    }
+    let mut ok = true;
+    for part in parts {
+        if let Some(trace) = jobs[0].ctx.trace() {
+            trace.begin(&format!("Day {} part {}", solver.day(), part));
+        }
+        let timed = runner::solve_timed(solver, part, &jobs[0].input, &jobs[0].ctx);
+        ok &= timed.answer.is_ok();
+        match (&options.format, &timed.answer) {
+            (Format::Json, _) => println!("{}", timed.to_json(&jobs[0].digest())),
+            (Format::Text, Ok(answer)) if answer.contains('\n') => {
+                println!("Day {} part {}:\n{}", timed.day, part, answer)
+            }
+            (Format::Text, Ok(answer)) => println!("Day {} part {}: {}", timed.day, part, answer),
+            (Format::Text, Err(e)) => eprintln!("Day {} part {}: error: {}", timed.day, part, e),
+        }
+    }
+    report_skipped(&jobs);
+    ok
+}
+
+/// Reads the day's input as an assembunny program.
+fn assembunny(registry: &Registry, options: &Options, day: &str) -> Option<Program> {
+    let solver = match day.parse::<u8>().ok().and_then(|d| registry.get(d)) {
+        Some(solver) => solver,
+        None => {
+            println!("Unresolved task: {}", day);
+            return None;
+        }
+    };
+    let source = options
+        .input
+        .clone()
+        .unwrap_or_else(|| InputSource::for_day(solver.day()));
+    let ctx = Context::new()
+        .with_source(&source.to_string())
+        .with_strict(options.strict);
+    let program = match Program::parse(&read_input(&source), &ctx) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Day {} is not an assembunny program: {}", solver.day(), e);
+            return None;
+        }
+    };
+    for e in ctx.skipped() {
+        eprintln!("warning: skipped {}", e);
+    }
+    Some(program)
+}
+
+/// Runs the day's assembunny program under the debugger, reading commands
+/// from stdin.
+fn debug(registry: &Registry, options: &Options, day: &str) -> bool {
+    if let Some(InputSource::Stdin) = options.input {
+        eprintln!("debug reads its commands from stdin, the program cannot come from there");
+        return false;
+    }
+    let program = match assembunny(registry, options, day) {
+        Some(program) => program,
+        None => return false,
+    };
+    let mut debugger = Debugger::new(program.machine());
+    println!("{}", debugger.view());
+    let mut last = None;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(debug) ");
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => {
+                println!();
+                return true;
+            }
+        };
+        let command = match (line.trim().is_empty(), &last) {
+            (true, Some(command)) => Ok(Command::clone(command)),
+            (true, None) => continue,
+            (false, _) => Command::parse(&line, program.registers()),
+        };
+        match command {
+            Ok(Command::Quit) => return true,
+            Ok(command) => {
+                last = Some(command.clone());
+                println!("{}", debugger.execute(command));
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// Prints the day's assembunny program as pseudo-code, or as a Rust function.
+fn decompile(registry: &Registry, options: &Options, day: &str, rust: bool) -> bool {
+    let program = match assembunny(registry, options, day) {
+        Some(program) => program,
+        None => return false,
+    };
+    let decompiled = decompile::decompile(&program);
+    if !rust {
+        print!("{}", decompiled);
+        return true;
+    }
+    match decompiled.to_rust(&format!("day_{}", day)) {
+        Some(function) => {
+            println!("{}", function);
+            true
+        }
+        None => {
+            eprintln!("Day {} toggles or jumps in ways Rust has no loop for", day);
+            false
+        }
+    }
+}
+
+/// Every day as a job. `--threads` is the budget for all of them: days run
+/// side by side on it, and share what is left over between them.
+fn jobs<'a>(registry: &'a Registry, options: &Options) -> Vec<Job<'a>> {
+    let threads = runner::threads_per_job(registry.iter().count(), options.threads);
+    registry
+        .iter()
+        .map(|solver| {
+            job(
+                solver,
+                &InputSource::for_day(solver.day()),
+                options,
+                threads,
+            )
+        })
+        .collect()
+}
+
+fn print_table(results: &[Timed]) {
+    println!("Day Part {:>12} Answer", "Time");
+    for timed in results {
+        let elapsed = format!("{:.3?}", timed.elapsed);
+        match &timed.answer {
+            Ok(answer) if answer.contains('\n') => {
+                println!("{:>3} {:>4} {:>12}", timed.day, timed.part, elapsed);
+                println!("{}", answer);
+            }
+            Ok(answer) => println!(
+                "{:>3} {:>4} {:>12} {}",
+                timed.day, timed.part, elapsed, answer
+            ),
+            Err(e) => println!(
+                "{:>3} {:>4} {:>12} error: {}",
+                timed.day, timed.part, elapsed, e
+            ),
+        }
+    }
+}
+
+fn all(registry: &Registry, options: &Options) -> bool {
+    let jobs = jobs(registry, options);
+    let start = Instant::now();
+    let results = runner::run(&jobs, options.threads);
+    let wall = start.elapsed();
+
+    if options.format == Format::Json {
+        for timed in &results {
+            println!("{}", timed.to_json(&digest(&jobs, timed.day)));
+        }
+    } else {
+        print_table(&results);
+        let total = results.iter().map(|t| t.elapsed).sum::<Duration>();
+        println!(
+            "Total {:.3?} in {:.3?} on {} thread(s)",
+            total, wall, options.threads
+        );
+    }
+    report_skipped(&jobs);
+    results.iter().all(|t| t.answer.is_ok())
+}
+
+fn verify(registry: &Registry, options: &Options) -> bool {
+    let answers = read_input(&options.answers);
+    let answers = match Answers::parse(
+        &answers,
+        &Context::new()
+            .with_source(&options.answers.to_string())
+            .with_strict(true),
+    ) {
+        Ok(answers) => answers,
+        Err(e) => {
+            eprintln!("Cannot read answers: {}", e);
+            process::exit(1);
+        }
+    };
+    let (mut passed, mut failed) = (0, 0);
+    let jobs = jobs(registry, options);
+    if options.format == Format::Text {
+        println!("Day Part Result");
+    }
+    for timed in runner::run(&jobs, options.threads) {
+        let json = timed.to_json(&digest(&jobs, timed.day));
+        let verdict = Verdict::new(answers.get(timed.day, timed.part), timed.answer);
+        match (&options.format, json) {
+            (Format::Json, Value::Object(mut fields)) => {
+                fields.push(("verdict", Value::String(verdict.to_string())));
+                println!("{}", Value::Object(fields));
+            }
+            _ => println!("{:>3} {:>4} {}", timed.day, timed.part, verdict),
+        }
+        if verdict.is_pass() {
+            passed += 1;
+        } else {
+            failed += 1;
+        }
+    }
+    report_skipped(&jobs);
+    if options.format == Format::Text {
+        println!("{} passed, {} failed", passed, failed);
+    }
+    failed == 0
+}
+
+fn usage() {
+    println!("Usage:");
+    println!("  aoc-2016 list");
+    println!("  aoc-2016 run <day> [<part>] [--input <path>|- | --inline <text>]");
+    println!("      [--param <key>=<value>]... [--threads <n>] [--strict]");
+    println!("      [--trace] [--trace-from <step>] [--trace-limit <n>] [--trace-filter <text>]");
+    println!("  aoc-2016 all [--threads <n>] [--strict]");
+    println!("  aoc-2016 verify [--answers <path>] [--threads <n>] [--strict]");
+    println!("  aoc-2016 debug <day> [--input <path> | --inline <text>] [--strict]");
+    println!("  aoc-2016 decompile <day> [rust] [--input <path>|- | --inline <text>] [--strict]");
+    println!("Every command but list takes --format text|json, --progress to report how");
+    println!("far long running parts got, and --budget <seconds> to stop them after a while.");
+    println!("--threads is the most threads to use: run gives them all to the day, all and");
+    println!("verify run days side by side and share what is left over between them.");
+}
 
 fn main() {
-    let arg = env::args().nth(1);
-    match arg {
-        Some(x) => {
-            println!("Task: {}", x);
-            match x.as_str() {
-                "1" => task_1::run(),
-                "1e" => task_1::run_e(),
-                "2" => task_2::run(),
-                "2e" => task_2::run_e(),
-                "3" => task_3::run(),
-                "3e" => task_3::run_e(),
-                "4" => task_4::run(),
-                "4e" => task_4::run_e(),
-                "5" => task_5::run(),
-                "5e" => task_5::run_e(),
-                "6" => task_6::run(),
-                "6e" => task_6::run_e(),
-                "7" => task_7::run(),
-                "7e" => task_7::run_e(),
-                "8" => task_8::run(),
-                "8e" => task_8::run_e(),
-                "9" => task_9::run(),
-                "9e" => task_9::run_e(),
-                "10" => task_10::run(),
-                "10e" => task_10::run_e(),
-                "11" => task_11::run(),
-                "11e" => task_11::run_e(),
-                "12" => task_12::run(),
-                "12e" => task_12::run_e(),
-                "13" => task_13::run(),
-                "13e" => task_13::run_e(),
-                "14" => task_14::run(),
-                "14e" => task_14::run_e(),
-                "15" => task_15::run(),
-                "15e" => task_15::run_e(),
-                "16" => task_16::run(),
-                "16e" => task_16::run_e(),
-                "17" => task_17::run(),
-                "17e" => task_17::run_e(),
-                "18" => task_18::run(),
-                "18e" => task_18::run_e(),
-                "19" => task_19::run(),
-                "19e" => task_19::run_e(),
-                "20" => task_20::run(),
-                "20e" => task_20::run_e(),
-                "21" => task_21::run(),
-                "21e" => task_21::run_e(),
-                "22" => task_22::run(),
-                "22e" => task_22::run_e(),
-                _ => println!("Unresolved task"),
-            };
-        }
-        None => println!("Provide task"),
+    let registry = Registry::new();
+    let (options, args) = match Options::parse(env::args().skip(1).collect()) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            usage();
+            process::exit(2);
+        }
+    };
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<&str>>();
+
+    let ok = match args.as_slice() {
+        ["list"] => {
+            for solver in registry.iter() {
+                println!("{:>2}: {}", solver.day(), solver.title());
+                for param in solver.params() {
+                    println!("    {}: {}", param.name, param.help);
+                }
+            }
+            true
+        }
+        ["run", day] => run(&registry, &options, day, None),
+        ["run", day, part] => run(&registry, &options, day, Some(part)),
+        ["all"] | ["verify"] | ["debug", _] | ["decompile", ..] if !options.params.is_empty() => {
+            eprintln!("--param is only accepted by run");
+            false
+        }
+        ["all"] | ["verify"] | ["debug", _] | ["decompile", ..] if options.trace => {
+            eprintln!("--trace is only accepted by run");
+            false
+        }
+        ["all"] => all(&registry, &options),
+        ["verify"] => verify(&registry, &options),
+        ["debug", day] => debug(&registry, &options, day),
+        ["decompile", day] => decompile(&registry, &options, day, false),
+        ["decompile", day, "rust"] => decompile(&registry, &options, day, true),
+        _ => {
+            usage();
+            process::exit(2);
+        }
+    };
+    if !ok {
+        process::exit(1);
     }
 }
@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::thread;
+
+use crate::context::Context;
+use crate::progress::Stopped;
+
+/// How many indices a thread checks at a time.
+const CHUNK: usize = 4096;
+
+/// An md5 digest. Its nibbles are the digits of its hex form, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn of(data: &[u8]) -> Self {
+        Digest(md5::compute(data).0)
+    }
+
+    /// The `i`th hex digit, `0..32`.
+    pub fn nibble(&self, i: usize) -> u8 {
+        let byte = self.0[i / 2];
+        if i.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0xf
+        }
+    }
+
+    pub fn nibbles(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..32).map(move |i| self.nibble(i))
+    }
+
+    /// How many hex digits at the start are `0`.
+    pub fn leading_zeros(&self) -> usize {
+        (u128::from_be_bytes(self.0).leading_zeros() / 4) as usize
+    }
+
+    /// The nibble of the first run of `len` equal nibbles.
+    pub fn first_run(&self, len: usize) -> Option<u8> {
+        let mut run = 0;
+        let mut last = None;
+        for n in self.nibbles() {
+            run = if last == Some(n) { run + 1 } else { 1 };
+            last = Some(n);
+            if run == len {
+                return last;
+            }
+        }
+        None
+    }
+
+    /// A bit for every nibble value that occurs `len` times in a row.
+    pub fn runs(&self, len: usize) -> u16 {
+        let mut runs = 0;
+        let mut run = 0;
+        let mut last = None;
+        for n in self.nibbles() {
+            run = if last == Some(n) { run + 1 } else { 1 };
+            last = Some(n);
+            if run >= len {
+                runs |= 1 << n;
+            }
+        }
+        runs
+    }
+
+    /// The lowercase hex form, as bytes.
+    pub fn hex(&self) -> [u8; 32] {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = [0; 32];
+        for (i, h) in hex.iter_mut().enumerate() {
+            *h = DIGITS[self.nibble(i) as usize];
+        }
+        hex
+    }
+
+    /// Hashes the hex form of the digest again, `times` times over.
+    pub fn stretch(self, times: usize) -> Digest {
+        (0..times).fold(self, |d, _| Digest::of(&d.hex()))
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(std::str::from_utf8(&self.hex()).unwrap())
+    }
+}
+
+/// Digests of a salt followed by a decimal index. The salt is only hashed
+/// once, every index continues from there.
+#[derive(Clone)]
+pub struct Salted {
+    salt: md5::Context,
+}
+
+impl Salted {
+    pub fn new(salt: &[u8]) -> Self {
+        let mut context = md5::Context::new();
+        context.consume(salt);
+        Salted { salt: context }
+    }
+
+    pub fn digest(&self, index: usize) -> Digest {
+        let mut digits = [0; 20];
+        let mut start = digits.len();
+        let mut n = index;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        let mut context = self.salt.clone();
+        context.consume(&digits[start..]);
+        Digest(context.compute().0)
+    }
+}
+
+/// The indices whose salted digest `check` accepts, in increasing order,
+/// together with what `check` made of them. Never ends on its own, but
+/// yields an error when the context stops it.
+pub struct Mine<'a, T, F> {
+    ctx: &'a Context,
+    salted: Salted,
+    check: F,
+    threads: usize,
+    next: usize,
+    found: VecDeque<(usize, T)>,
+}
+
+/// Mines `salt` on as many threads as the context allows. Whatever the
+/// number of threads the indices come out in the same order.
+pub fn mine<'a, T, F>(salt: &[u8], ctx: &'a Context, check: F) -> Mine<'a, T, F>
+where
+    T: Send,
+    F: Fn(Digest) -> Option<T> + Sync,
+{
+    Mine {
+        ctx,
+        salted: Salted::new(salt),
+        check,
+        threads: ctx.threads(),
+        next: 0,
+        found: VecDeque::new(),
+    }
+}
+
+fn chunk<T, F>(salted: &Salted, check: &F, from: usize) -> Vec<(usize, T)>
+where
+    F: Fn(Digest) -> Option<T>,
+{
+    (from..from + CHUNK)
+        .filter_map(|i| check(salted.digest(i)).map(|v| (i, v)))
+        .collect()
+}
+
+impl<T, F> Iterator for Mine<'_, T, F>
+where
+    T: Send,
+    F: Fn(Digest) -> Option<T> + Sync,
+{
+    type Item = Result<(usize, T), Stopped>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            if let Err(stopped) = self.ctx.tick(self.next as u64, None) {
+                return Some(Err(stopped));
+            }
+            let starts = (0..self.threads)
+                .map(|t| self.next + t * CHUNK)
+                .collect::<Vec<usize>>();
+            let (salted, check) = (&self.salted, &self.check);
+            let chunks = if self.threads == 1 {
+                vec![chunk(salted, check, starts[0])]
+            } else {
+                thread::scope(|scope| {
+                    let handles = starts
+                        .iter()
+                        .map(|from| scope.spawn(move || chunk(salted, check, *from)))
+                        .collect::<Vec<_>>();
+                    handles
+                        .into_iter()
+                        .map(|h| h.join().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            };
+            self.next += self.threads * CHUNK;
+            self.found.extend(chunks.into_iter().flatten());
+        }
+        self.found.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+
+    #[test]
+    fn reads_nibbles() {
+        let digest = Salted::new(b"abc").digest(3231929);
+        assert!(digest.to_string().starts_with("000001"));
+        assert_eq!(
+            digest.to_string(),
+            format!("{:x}", md5::compute("abc3231929"))
+        );
+        assert_eq!(digest.leading_zeros(), 5);
+        assert_eq!(digest.nibble(5), 1);
+    }
+
+    #[test]
+    fn finds_runs() {
+        let digest = Salted::new(b"abc").digest(18);
+        assert!(digest.to_string().contains("cc38887a5"));
+        assert_eq!(digest.first_run(3), Some(8));
+        assert_eq!(digest.runs(3), 1 << 8);
+        assert_eq!(digest.runs(5), 0);
+    }
+
+    #[test]
+    fn stretches_digests() {
+        assert_eq!(
+            Salted::new(b"abc").digest(0).stretch(2016).to_string(),
+            "a107ff634856bb300138cac6568c0f24"
+        );
+    }
+
+    #[test]
+    fn mines_in_index_order() {
+        let check = |d: Digest| d.first_run(4).filter(|n| *n > 0xa);
+        let ctx = Context::new();
+        let one = mine(b"abc", &ctx, check)
+            .take(20)
+            .collect::<Result<Vec<_>, _>>();
+        let ctx = Context::new().with_threads(4);
+        let four = mine(b"abc", &ctx, check)
+            .take(20)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(one, four);
+        assert!(one.unwrap().windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let ctx = Context::new().with_progress(Progress::new());
+        let mut mined = mine(b"abc", &ctx, |d| d.first_run(3));
+        assert!(mined.next().unwrap().is_ok());
+        ctx.progress().unwrap().cancel();
+        let stopped = mined.find_map(|m| m.err()).unwrap();
+        assert_eq!(stopped.reason, "cancelled");
+        assert!(stopped.done > 0);
+    }
+}
@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+use crate::error::ParseError;
+
+/// Whitespace separated tokens of a single line, remembering the column every
+/// token starts at so errors can point at it.
+pub struct Tokens<'a> {
+    line: &'a str,
+    tokens: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(line: &'a str) -> Self {
+        let mut tokens = vec![];
+        let mut start = None;
+        for (i, c) in line.char_indices() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(s)) => {
+                    tokens.push((s + 1, &line[s..i]));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            tokens.push((s + 1, &line[s..]));
+        }
+        Tokens { line, tokens }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn error(&self, i: usize, expected: &'static str) -> ParseError {
+        match self.tokens.get(i) {
+            Some((column, token)) => ParseError::new(*column, token, expected),
+            None => ParseError::new(self.line.len() + 1, "", expected),
+        }
+    }
+
+    pub fn get(&self, i: usize, expected: &'static str) -> Result<&'a str, ParseError> {
+        self.tokens
+            .get(i)
+            .map(|(_, token)| *token)
+            .ok_or_else(|| self.error(i, expected))
+    }
+
+    pub fn column(&self, i: usize) -> usize {
+        self.tokens
+            .get(i)
+            .map(|(column, _)| *column)
+            .unwrap_or(self.line.len() + 1)
+    }
+
+    pub fn expect(&self, i: usize, literal: &'static str) -> Result<(), ParseError> {
+        if self.get(i, literal)? == literal {
+            Ok(())
+        } else {
+            Err(self.error(i, literal))
+        }
+    }
+
+    pub fn parse<T: FromStr>(&self, i: usize, expected: &'static str) -> Result<T, ParseError> {
+        self.get(i, expected)?
+            .parse::<T>()
+            .map_err(|_| self.error(i, expected))
+    }
+
+    /// Parses the token after stripping the given punctuation, e.g. the
+    /// trailing `.` of `position 10.`
+    pub fn parse_trimmed<T: FromStr>(
+        &self,
+        i: usize,
+        trim: &[char],
+        expected: &'static str,
+    ) -> Result<T, ParseError> {
+        self.get(i, expected)?
+            .trim_matches(trim)
+            .parse::<T>()
+            .map_err(|_| self.error(i, expected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_tokens_with_columns() {
+        let tokens = Tokens::new("  cpy  41 a");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens.get(1, "value").unwrap(), "41");
+        assert_eq!(
+            (tokens.column(0), tokens.column(1), tokens.column(2)),
+            (3, 8, 11)
+        );
+    }
+
+    #[test]
+    fn points_errors_at_tokens() {
+        let tokens = Tokens::new("rect 3x2 now");
+        let e = tokens.parse::<u8>(1, "number").err().unwrap();
+        assert_eq!(
+            (e.column, e.token.as_str(), e.expected),
+            (6, "3x2", "number")
+        );
+        let e = tokens.get(5, "value").err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (13, ""));
+        assert!(tokens.expect(0, "rect").is_ok());
+        assert_eq!(tokens.expect(2, "later").err().unwrap().column, 10);
+        assert_eq!(
+            tokens.parse_trimmed::<u8>(1, &['x', '3'], "size").unwrap(),
+            2
+        );
+    }
+}
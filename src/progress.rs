@@ -0,0 +1,164 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Watches a long running solver: reports how far it got and stops it when
+/// it is cancelled or runs out of time. Solvers call `tick` every now and
+/// then and give up when it tells them to.
+#[derive(Default)]
+pub struct Progress {
+    every: Option<Duration>,
+    budget: Option<Duration>,
+    cancelled: AtomicBool,
+    state: Mutex<State>,
+}
+
+struct State {
+    label: String,
+    started: Instant,
+    reported: Instant,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            label: String::new(),
+            started: Instant::now(),
+            reported: Instant::now(),
+        }
+    }
+}
+
+/// Why and where a solver gave up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stopped {
+    pub reason: &'static str,
+    pub done: u64,
+    pub partial: Option<String>,
+}
+
+impl Stopped {
+    /// Adds what the solver had found so far.
+    pub fn with_partial(mut self, partial: String) -> Self {
+        self.partial = Some(partial);
+        self
+    }
+}
+
+impl Display for Stopped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} after {} items", self.reason, self.done)?;
+        match &self.partial {
+            Some(partial) => write!(f, ", got as far as {}", partial),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Progress::default()
+    }
+
+    /// Writes a report to stderr at most once per `every`.
+    pub fn with_reports(mut self, every: Duration) -> Self {
+        self.every = Some(every);
+        self
+    }
+
+    /// Stops a part once it has run for `budget`.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Starts timing a new part.
+    pub fn begin(&self, label: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.label = label.to_string();
+        state.started = Instant::now();
+        state.reported = state.started;
+    }
+
+    /// Stops the solver at its next `tick`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Records that `done` out of `total` items are processed, when the
+    /// total is known.
+    pub fn tick(&self, done: u64, total: Option<u64>) -> Result<(), Stopped> {
+        let stopped = |reason| Stopped {
+            reason,
+            done,
+            partial: None,
+        };
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(stopped("cancelled"));
+        }
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now - state.started;
+        if self.budget.is_some_and(|b| elapsed >= b) {
+            return Err(stopped("out of time"));
+        }
+        if self.every.is_some_and(|e| now - state.reported >= e) {
+            state.reported = now;
+            eprintln!("{}: {}", state.label, report(done, total, elapsed));
+        }
+        Ok(())
+    }
+}
+
+/// Items done, the rate and, with a total, the share done and time left.
+fn report(done: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let rate = done as f64 / elapsed.as_secs_f64().max(1e-9);
+    let mut s = match total {
+        Some(total) => format!(
+            "{}/{} items ({:.1}%)",
+            done,
+            total,
+            100.0 * done as f64 / total.max(1) as f64
+        ),
+        None => format!("{} items", done),
+    };
+    s += &format!(", {:.0}/s", rate);
+    if let Some(total) = total {
+        if done > 0 {
+            let left = total.saturating_sub(done) as f64 / rate;
+            s += &format!(", {:.0}s left", left);
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_rate_and_time_left() {
+        assert_eq!(
+            report(250, Some(1000), Duration::from_secs(5)),
+            "250/1000 items (25.0%), 50/s, 15s left"
+        );
+        assert_eq!(report(30, None, Duration::from_secs(2)), "30 items, 15/s");
+    }
+
+    #[test]
+    fn stops_when_cancelled_or_out_of_time() {
+        let progress = Progress::new();
+        assert!(progress.tick(1, None).is_ok());
+        progress.cancel();
+        let stopped = progress.tick(2, None).err().unwrap();
+        assert_eq!(stopped.to_string(), "cancelled after 2 items");
+        let progress = Progress::new().with_budget(Duration::ZERO);
+        progress.begin("test");
+        let stopped = progress.tick(3, Some(10)).err().unwrap();
+        assert_eq!(
+            stopped.with_partial("abc".to_string()).to_string(),
+            "out of time after 3 items, got as far as abc"
+        );
+    }
+}
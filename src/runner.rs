@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::json::Value;
+use crate::solver::{Part, Solver};
+
+/// A day to be solved together with everything it needs.
+pub struct Job<'a> {
+    pub solver: &'a dyn Solver,
+    pub input: String,
+    pub ctx: Context,
+}
+
+impl Job<'_> {
+    /// Hex encoded md5 of the input, to tell which input an answer belongs to.
+    pub fn digest(&self) -> String {
+        format!("{:x}", md5::compute(self.input.as_bytes()))
+    }
+}
+
+/// The outcome of solving a single part, with the wall-clock time it took.
+pub struct Timed {
+    pub day: u8,
+    pub part: Part,
+    pub answer: Result<String, Error>,
+    pub elapsed: Duration,
+}
+
+impl Timed {
+    /// Multi-line answers become an array of lines, failed runs carry the error.
+    pub fn to_json(&self, digest: &str) -> Value {
+        let (answer, error) = match &self.answer {
+            Ok(answer) if answer.contains('\n') => (
+                Value::Array(answer.lines().map(Value::from).collect()),
+                Value::Null,
+            ),
+            Ok(answer) => (Value::from(answer.as_str()), Value::Null),
+            Err(e) => (Value::Null, Value::String(e.to_string())),
+        };
+        Value::Object(vec![
+            ("day", Value::Number(self.day as f64)),
+            ("part", Value::Number(self.part.number() as f64)),
+            ("answer", answer),
+            ("error", error),
+            (
+                "duration_ms",
+                Value::Number(self.elapsed.as_secs_f64() * 1000.0),
+            ),
+            ("input_md5", Value::from(digest)),
+        ])
+    }
+}
+
+pub fn solve_timed(solver: &dyn Solver, part: Part, input: &str, ctx: &Context) -> Timed {
+    if let Some(progress) = ctx.progress() {
+        progress.begin(&format!("Day {} part {}", solver.day(), part.number()));
+    }
+    let start = Instant::now();
+    let answer = solver.solve(part, input, ctx);
+    Timed {
+        day: solver.day(),
+        part,
+        answer,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn solve_job(job: &Job) -> Vec<Timed> {
+    Part::both()
+        .iter()
+        .map(|part| solve_timed(job.solver, *part, &job.input, &job.ctx))
+        .collect()
+}
+
+/// How many days run at once when `jobs` days share `threads` threads.
+pub fn workers(jobs: usize, threads: usize) -> usize {
+    threads.clamp(1, jobs.max(1))
+}
+
+/// The threads each day may use for itself when `jobs` days share
+/// `threads` threads: what the days running at once leave over, at least
+/// one, so that together they stay within the budget.
+pub fn threads_per_job(jobs: usize, threads: usize) -> usize {
+    (threads / workers(jobs, threads)).max(1)
+}
+
+/// Solves both parts of every job on up to `threads` threads. Days are
+/// independent of each other, the parts of a day run one after another.
+/// Results come back in the order of the jobs.
+pub fn run(jobs: &[Job], threads: usize) -> Vec<Timed> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(jobs.iter().map(|_| vec![]).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..workers(jobs.len(), threads) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                match jobs.get(i) {
+                    Some(job) => {
+                        let timed = solve_job(job);
+                        results.lock().unwrap()[i] = timed;
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_threads_between_days() {
+        assert_eq!((workers(25, 8), threads_per_job(25, 8)), (8, 1));
+        assert_eq!((workers(25, 64), threads_per_job(25, 64)), (25, 2));
+        assert_eq!((workers(1, 8), threads_per_job(1, 8)), (1, 8));
+        assert_eq!((workers(3, 8), threads_per_job(3, 8)), (3, 2));
+        assert_eq!((workers(0, 1), threads_per_job(0, 1)), (1, 1));
+    }
+}
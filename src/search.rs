@@ -0,0 +1,308 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+/// A node of a search graph.
+pub trait SearchState: Clone {
+    type Key: Hash + Eq;
+
+    /// The states one move away, each with the cost of the move.
+    fn successors(&self) -> Vec<(Self, usize)>;
+
+    fn is_goal(&self) -> bool;
+
+    /// A lower bound of the cost left to reach a goal. Never overestimate it,
+    /// or `astar` may return a path that is not the cheapest.
+    fn heuristic(&self) -> usize {
+        0
+    }
+
+    /// States with the same key are the same node, whichever way they were
+    /// reached.
+    fn key(&self) -> Self::Key;
+}
+
+/// A path from the start to a goal, both included.
+pub struct Found<S> {
+    pub path: Vec<S>,
+    pub cost: usize,
+}
+
+impl<S> Found<S> {
+    pub fn goal(&self) -> &S {
+        self.path.last().unwrap()
+    }
+}
+
+/// The outcome of a search, with the number of states whose successors were
+/// generated along the way.
+pub struct Search<S> {
+    pub found: Option<Found<S>>,
+    pub expanded: usize,
+}
+
+/// Every state reached so far, with the one it was reached from.
+struct Tree<S> {
+    nodes: Vec<(S, Option<usize>)>,
+}
+
+impl<S: Clone> Tree<S> {
+    fn new() -> Self {
+        Tree { nodes: vec![] }
+    }
+
+    fn push(&mut self, state: S, parent: Option<usize>) -> usize {
+        self.nodes.push((state, parent));
+        self.nodes.len() - 1
+    }
+
+    fn path(&self, mut i: usize) -> Vec<S> {
+        let mut path = vec![self.nodes[i].0.clone()];
+        while let Some(parent) = self.nodes[i].1 {
+            path.push(self.nodes[parent].0.clone());
+            i = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Breadth first search, the path found has the fewest moves whatever they
+/// cost.
+pub fn bfs<S: SearchState>(start: S) -> Search<S> {
+    let mut tree = Tree::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    let mut expanded = 0;
+    seen.insert(start.key());
+    queue.push_back((tree.push(start, None), 0));
+    while let Some((i, cost)) = queue.pop_front() {
+        if tree.nodes[i].0.is_goal() {
+            return Search {
+                found: Some(Found {
+                    path: tree.path(i),
+                    cost,
+                }),
+                expanded,
+            };
+        }
+        expanded += 1;
+        for (next, step) in tree.nodes[i].0.successors() {
+            if seen.insert(next.key()) {
+                queue.push_back((tree.push(next, Some(i)), cost + step));
+            }
+        }
+    }
+    Search {
+        found: None,
+        expanded,
+    }
+}
+
+/// The cheapest path to a goal.
+pub fn dijkstra<S: SearchState>(start: S) -> Search<S> {
+    best_first(start, |_| 0)
+}
+
+/// The cheapest path to a goal, guided by `SearchState::heuristic`.
+pub fn astar<S: SearchState>(start: S) -> Search<S> {
+    best_first(start, S::heuristic)
+}
+
+fn best_first<S: SearchState, H: Fn(&S) -> usize>(start: S, heuristic: H) -> Search<S> {
+    let mut tree = Tree::new();
+    let mut best = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    let mut expanded = 0;
+    best.insert(start.key(), 0);
+    queue.push(Reverse((heuristic(&start), 0, tree.push(start, None))));
+    while let Some(Reverse((_, cost, i))) = queue.pop() {
+        let state = &tree.nodes[i].0;
+        if best.get(&state.key()).is_some_and(|b| *b < cost) {
+            continue;
+        }
+        if state.is_goal() {
+            return Search {
+                found: Some(Found {
+                    path: tree.path(i),
+                    cost,
+                }),
+                expanded,
+            };
+        }
+        expanded += 1;
+        for (next, step) in state.successors() {
+            let cost = cost + step;
+            let key = next.key();
+            if best.get(&key).is_none_or(|b| cost < *b) {
+                best.insert(key, cost);
+                let estimate = cost + heuristic(&next);
+                queue.push(Reverse((estimate, cost, tree.push(next, Some(i)))));
+            }
+        }
+    }
+    Search {
+        found: None,
+        expanded,
+    }
+}
+
+/// Every state that costs at most `limit` to reach, with its cost.
+pub fn within<S: SearchState>(start: S, limit: usize) -> Vec<(S, usize)> {
+    let mut best = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    let mut states = vec![];
+    best.insert(start.key(), 0);
+    queue.push(Reverse((0, 0)));
+    states.push(start);
+    let mut reached = vec![];
+    while let Some(Reverse((cost, i))) = queue.pop() {
+        let state = states[i].clone();
+        if best.get(&state.key()).is_some_and(|b| *b < cost) {
+            continue;
+        }
+        for (next, step) in state.successors() {
+            let cost = cost + step;
+            let key = next.key();
+            if cost <= limit && best.get(&key).is_none_or(|b| cost < *b) {
+                best.insert(key, cost);
+                queue.push(Reverse((cost, states.len())));
+                states.push(next);
+            }
+        }
+        reached.push((state, cost));
+    }
+    reached
+}
+
+/// The most expensive path to a goal that visits no state twice. Goals end
+/// a path, their successors are not followed. Every such path is walked, so
+/// this is only feasible for small or tree shaped graphs.
+pub fn longest<S: SearchState>(start: S) -> Search<S> {
+    let mut path = vec![];
+    let mut on_path = HashSet::new();
+    let mut stack = vec![(start, 0, 0)];
+    let mut found: Option<Found<S>> = None;
+    let mut expanded = 0;
+    while let Some((state, cost, depth)) = stack.pop() {
+        while path.len() > depth {
+            let left: S = path.pop().unwrap();
+            on_path.remove(&left.key());
+        }
+        if state.is_goal() {
+            if found.as_ref().is_none_or(|f| f.cost < cost) {
+                let mut states = path.clone();
+                states.push(state);
+                found = Some(Found { path: states, cost });
+            }
+            continue;
+        }
+        expanded += 1;
+        on_path.insert(state.key());
+        for (next, step) in state.successors() {
+            if !on_path.contains(&next.key()) {
+                stack.push((next, cost + step, depth + 1));
+            }
+        }
+        path.push(state);
+    }
+    Search { found, expanded }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A walk along the number line up to `max`: one step costs 1, a jump
+    /// to the double costs 3.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Number {
+        value: usize,
+        goal: usize,
+        max: usize,
+    }
+
+    impl SearchState for Number {
+        type Key = usize;
+        fn successors(&self) -> Vec<(Self, usize)> {
+            let mut next = vec![(self.value + 1, 1), (self.value * 2, 3)];
+            if self.value > 0 {
+                next.push((self.value - 1, 1));
+            }
+            next.into_iter()
+                .filter(|(v, _)| *v <= self.max)
+                .map(|(value, cost)| (Number { value, ..*self }, cost))
+                .collect()
+        }
+        fn is_goal(&self) -> bool {
+            self.value == self.goal
+        }
+        fn heuristic(&self) -> usize {
+            usize::from(self.value != self.goal)
+        }
+        fn key(&self) -> usize {
+            self.value
+        }
+    }
+
+    fn start(goal: usize, max: usize) -> Number {
+        Number {
+            value: 1,
+            goal,
+            max,
+        }
+    }
+
+    fn values(found: &Found<Number>) -> Vec<usize> {
+        found.path.iter().map(|n| n.value).collect()
+    }
+
+    #[test]
+    fn finds_fewest_moves() {
+        let search = bfs(start(8, 16));
+        let found = search.found.unwrap();
+        assert_eq!(values(&found), [1, 2, 4, 8]);
+        assert_eq!(found.cost, 7);
+        assert!(search.expanded > 0);
+    }
+
+    #[test]
+    fn finds_cheapest_path() {
+        for search in [dijkstra(start(8, 16)), astar(start(8, 16))] {
+            let found = search.found.unwrap();
+            assert_eq!(values(&found), [1, 2, 3, 4, 8]);
+            assert_eq!(found.cost, 6);
+        }
+        assert!(astar(start(8, 16)).expanded <= dijkstra(start(8, 16)).expanded);
+    }
+
+    #[test]
+    fn lists_states_within_limit() {
+        let mut reached = within(start(8, 16), 2)
+            .into_iter()
+            .map(|(n, cost)| (n.value, cost))
+            .collect::<Vec<_>>();
+        reached.sort();
+        assert_eq!(reached, [(0, 1), (1, 0), (2, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn finds_longest_path() {
+        let found = longest(start(3, 4)).found.unwrap();
+        assert_eq!(values(&found), [1, 2, 4, 3]);
+        assert_eq!(found.cost, 7);
+        assert_eq!(found.goal().value, 3);
+    }
+
+    #[test]
+    fn reports_unreachable_goals() {
+        for search in [
+            bfs(start(10, 5)),
+            astar(start(10, 5)),
+            longest(start(10, 5)),
+        ] {
+            assert!(search.found.is_none());
+            assert!(search.expanded >= 6);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Part {
+    First,
+    Second,
+}
+
+impl Part {
+    pub fn both() -> [Part; 2] {
+        [Part::First, Part::Second]
+    }
+
+    pub fn number(self) -> u8 {
+        match self {
+            Part::First => 1,
+            Part::Second => 2,
+        }
+    }
+}
+
+impl Display for Part {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
+impl FromStr for Part {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Part::First),
+            "2" => Ok(Part::Second),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A constant of the puzzle a solver lets the user override with `--param`.
+pub struct Param {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+pub trait Solver: Sync {
+    fn day(&self) -> u8;
+    fn title(&self) -> &'static str;
+    fn params(&self) -> &'static [Param] {
+        &[]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error>;
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error>;
+
+    fn solve(&self, part: Part, input: &str, ctx: &Context) -> Result<String, Error> {
+        match part {
+            Part::First => self.part1(input, ctx),
+            Part::Second => self.part2(input, ctx),
+        }
+    }
+}
+
+pub struct Registry {
+    solvers: Vec<Box<dyn Solver>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            solvers: vec![
+                Box::new(task_1::Solution),
+                Box::new(task_2::Solution),
+                Box::new(task_3::Solution),
+                Box::new(task_4::Solution),
+                Box::new(task_5::Solution),
+                Box::new(task_6::Solution),
+                Box::new(task_7::Solution),
+                Box::new(task_8::Solution),
+                Box::new(task_9::Solution),
+                Box::new(task_10::Solution),
+                Box::new(task_11::Solution),
+                Box::new(task_12::Solution),
+                Box::new(task_13::Solution),
+                Box::new(task_14::Solution),
+                Box::new(task_15::Solution),
+                Box::new(task_16::Solution),
+                Box::new(task_17::Solution),
+                Box::new(task_18::Solution),
+                Box::new(task_19::Solution),
+                Box::new(task_20::Solution),
+                Box::new(task_21::Solution),
+                Box::new(task_22::Solution),
+                Box::new(task_23::Solution),
+                Box::new(task_24::Solution),
+                Box::new(task_25::Solution),
+            ],
+        }
+    }
+
+    pub fn get(&self, day: u8) -> Option<&dyn Solver> {
+        self.iter().find(|s| s.day() == day)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Solver> {
+        self.solvers.iter().map(|s| s.as_ref())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
@@ -1,191 +1,141 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::{Direction, Point, SparseGrid};
+use crate::solver::Solver;
+use std::str::FromStr;
 
-enum Destination {
-    North,
-    South,
-    East,
-    West,
+#[derive(Debug)]
+enum RoutePart {
+    Clockwise(u8),
+    CounterClockwise(u8),
 }
 
-impl Destination {
-    fn clockwise(&self) -> Self {
-        match self {
-            Destination::North => Destination::East,
-            Destination::East => Destination::South,
-            Destination::South => Destination::West,
-            Destination::West => Destination::North,
-        }
-    }
-    fn counter_clockwise(&self) -> Self {
-        match self {
-            Destination::North => Destination::West,
-            Destination::West => Destination::South,
-            Destination::South => Destination::East,
-            Destination::East => Destination::North,
+impl FromStr for RoutePart {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let distance = || {
+            let d = s.get(1..).unwrap_or("");
+            d.parse::<u8>()
+                .map_err(|_| ParseError::new(2, d, "distance in blocks"))
+        };
+        match s.chars().next() {
+            Some('L') => Ok(RoutePart::CounterClockwise(distance()?)),
+            Some('R') => Ok(RoutePart::Clockwise(distance()?)),
+            _ => Err(ParseError::new(1, s, "turn `L` or `R`")),
         }
     }
 }
 
-#[derive(Debug)]
-enum RoutePart {
-    Clockwise(u8),
-    CounterClockwise(u8),
-}
+struct Route(Vec<RoutePart>);
 
-#[derive(Eq, PartialEq, Clone)]
-struct Coords {
-    x: i32,
-    y: i32,
+impl FromStr for Route {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut offset = 0;
+        let mut parts = vec![];
+        for p in s.split(", ") {
+            parts.push(p.parse::<RoutePart>().map_err(|e| e.shifted(offset))?);
+            offset += p.len() + 2;
+        }
+        Ok(Route(parts))
+    }
 }
 
-impl Coords {
-    fn move_by(&mut self, r: u8, destination: &Destination) -> Vec<Coords> {
-        match destination {
-            Destination::North => {
-                self.y += r as i32;
-                (0..r)
-                    .map(|i| Coords {
-                        x: self.x,
-                        y: self.y + i as i32 - r as i32,
-                    })
-                    .collect()
-            }
-            Destination::East => {
-                self.x += r as i32;
-                (1..r)
-                    .map(|i| Coords {
-                        x: self.x + i as i32 - r as i32,
-                        y: self.y,
-                    })
-                    .collect()
-            }
-            Destination::South => {
-                self.y -= r as i32;
-                (1..r)
-                    .map(|i| Coords {
-                        x: self.x,
-                        y: self.y - i as i32 + r as i32,
-                    })
-                    .collect()
-            }
-            Destination::West => {
-                self.x -= r as i32;
-                (1..r)
-                    .map(|i| Coords {
-                        x: self.x - i as i32 + r as i32,
-                        y: self.y,
-                    })
-                    .collect()
+impl Route {
+    /// Every point passed on the way, one block apart, after the start.
+    fn walk(&self) -> Vec<Point> {
+        let mut facing = Direction::North;
+        let mut position = Point::ORIGIN;
+        let mut points = vec![];
+        for part in &self.0 {
+            let blocks = match part {
+                RoutePart::Clockwise(blocks) => {
+                    facing = facing.clockwise();
+                    blocks
+                }
+                RoutePart::CounterClockwise(blocks) => {
+                    facing = facing.counter_clockwise();
+                    blocks
+                }
+            };
+            for _ in 0..*blocks {
+                position = position.step(facing);
+                points.push(position);
             }
         }
+        points
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_1").unwrap();
-    let mut input = BufReader::new(input);
+pub fn part1(input: &str, ctx: &Context) -> Result<i64, Error> {
+    let route = ctx.parse::<Route>(input)?;
+    let destination = route.walk().last().copied().unwrap_or(Point::ORIGIN);
+    Ok(destination.manhattan(Point::ORIGIN))
+}
 
-    let mut s = String::new();
+pub fn part2(input: &str, ctx: &Context) -> Result<i64, Error> {
+    let route = ctx.parse::<Route>(input)?;
+    let mut visited = SparseGrid::new();
+    visited.insert(Point::ORIGIN, ());
+    route
+        .walk()
+        .into_iter()
+        .find(|p| visited.insert(*p, ()).is_some())
+        .map(|p| p.manhattan(Point::ORIGIN))
+        .ok_or(Error::NoAnswer("no location is visited twice"))
+}
 
-    input.read_line(&mut s).unwrap();
-    let s = s
-        .split(", ")
-        .map(|s| {
-            if s.starts_with("L") {
-                RoutePart::CounterClockwise(s[1..].to_string().parse::<u8>().unwrap())
-            } else {
-                RoutePart::Clockwise(s[1..].parse::<u8>().unwrap())
-            }
-        })
-        .collect::<Vec<RoutePart>>();
+pub struct Solution;
 
-    let result = evaluate(
-        &mut s.iter(),
-        &Destination::North,
-        &mut Coords { x: 0, y: 0 },
-    );
-    println!("Result: {:?}", result);
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        1
+    }
+    fn title(&self) -> &'static str {
+        "No Time for a Taxicab"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_1").unwrap();
-    let mut input = BufReader::new(input);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut s = String::new();
-
-    input.read_line(&mut s).unwrap();
-    let s = s
-        .split(", ")
-        .map(|s| {
-            if s.starts_with("L") {
-                RoutePart::CounterClockwise(s[1..].to_string().parse::<u8>().unwrap())
-            } else {
-                RoutePart::Clockwise(s[1..].parse::<u8>().unwrap())
-            }
-        })
-        .collect::<Vec<RoutePart>>();
+    #[test]
+    fn parses_route() {
+        let route = "R2, L3".parse::<Route>().unwrap();
+        assert!(matches!(
+            route.0.as_slice(),
+            [RoutePart::Clockwise(2), RoutePart::CounterClockwise(3)]
+        ));
+    }
 
-    let result = evaluate_second_visit(
-        &mut s.iter(),
-        &Destination::North,
-        &mut vec![],
-        &mut Coords { x: 0, y: 0 },
-    );
-    println!("Result: {:?}", result);
-}
+    #[test]
+    fn reports_column_of_bad_route_part() {
+        let e = "R2, X3".parse::<Route>().err().unwrap();
+        assert_eq!(e.column, 5);
+        assert_eq!(e.token, "X3");
+        let e = "R2, Lx".parse::<Route>().err().unwrap();
+        assert_eq!(e.column, 6);
+    }
 
-fn evaluate(
-    route: &mut dyn Iterator<Item = &RoutePart>,
-    destination: &Destination,
-    coords: &mut Coords,
-) -> i32 {
-    let route_part = route.next();
-    match route_part {
-        Some(RoutePart::Clockwise(r)) => {
-            let new_destination = destination.clockwise();
-            coords.move_by(*r, &new_destination);
-            evaluate(route, &new_destination, coords)
-        }
-        Some(RoutePart::CounterClockwise(r)) => {
-            let new_destination = destination.counter_clockwise();
-            coords.move_by(*r, &new_destination);
-            evaluate(route, &new_destination, coords)
-        }
-        None => coords.x.abs() + coords.y.abs(),
+    #[test]
+    fn measures_distance_to_destination() {
+        let ctx = Context::new();
+        assert_eq!(part1("R2, L3", &ctx).unwrap(), 5);
+        assert_eq!(part1("R2, R2, R2", &ctx).unwrap(), 2);
+        assert_eq!(part1("R5, L5, R5, R3", &ctx).unwrap(), 12);
     }
-}
 
-fn evaluate_second_visit(
-    route: &mut dyn Iterator<Item = &RoutePart>,
-    destination: &Destination,
-    path: &mut Vec<Coords>,
-    current: &mut Coords,
-) -> i32 {
-    let route_part = route.next();
-    match route_part {
-        Some(RoutePart::Clockwise(r)) => {
-            let new_destination = destination.clockwise();
-            for p in current.move_by(*r, &new_destination) {
-                if path.contains(&p) {
-                    return p.x.abs() + p.y.abs();
-                } else {
-                    path.push(p);
-                }
-            }
-            evaluate_second_visit(route, &new_destination, path, current)
-        }
-        Some(RoutePart::CounterClockwise(r)) => {
-            let new_destination = destination.counter_clockwise();
-            for p in current.move_by(*r, &new_destination) {
-                if path.contains(&p) {
-                    return p.x.abs() + p.y.abs();
-                } else {
-                    path.push(p);
-                }
-            }
-            evaluate_second_visit(route, &new_destination, path, current)
-        }
-        None => 0,
+    #[test]
+    fn finds_first_location_visited_twice() {
+        let ctx = Context::new();
+        assert_eq!(part2("R8, R4, R4, R8", &ctx).unwrap(), 4);
+        assert!(part2("R2, L3", &ctx).is_err());
     }
 }
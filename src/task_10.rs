@@ -1,7 +1,10 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::{Param, Solver};
 use std::collections::HashMap;
-use std::fs::File;
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
-use std::io::{BufRead, BufReader};
 use std::iter::{FromIterator, IntoIterator};
 use std::str::FromStr;
 
@@ -18,25 +21,18 @@ impl Package {
 }
 
 impl FromStr for Package {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(" ").collect::<Vec<&str>>();
-        let value = parts[1].parse::<usize>().unwrap();
-        let to = parts[5].parse::<usize>().unwrap();
-        let to = match parts[4] {
-            "bot" => Target::to_bot(to),
-            "output" => Target::to_output(to),
-            _ => return Err(()),
-        };
+        let tokens = Tokens::new(s);
+        tokens.expect(0, "value")?;
+        let value = tokens.parse::<usize>(1, "chip value")?;
+        tokens.expect(2, "goes")?;
+        tokens.expect(3, "to")?;
+        let to = Target::parse(&tokens, 4)?;
         Ok(Package::new(value, to))
     }
 }
 
-#[derive(Debug)]
-struct Output {
-    number: usize,
-}
-
 #[derive(Debug, Clone)]
 enum Target {
     ToBot(usize),
@@ -50,6 +46,24 @@ impl Target {
     fn to_output(number: usize) -> Self {
         Target::ToOutput(number)
     }
+
+    fn parse(tokens: &Tokens, i: usize) -> Result<Self, ParseError> {
+        let number = tokens.parse::<usize>(i + 1, "bot or output number")?;
+        match tokens.get(i, "`bot` or `output`")? {
+            "bot" => Ok(Target::to_bot(number)),
+            "output" => Ok(Target::to_output(number)),
+            _ => Err(tokens.error(i, "`bot` or `output`")),
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::ToBot(number) => write!(f, "bot {}", number),
+            Target::ToOutput(number) => write!(f, "output {}", number),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -70,26 +84,40 @@ impl Bot {
 }
 
 impl FromStr for Bot {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(" ").collect::<Vec<&str>>();
-        let number = parts[1].parse::<usize>().unwrap();
-        let l_to = parts[6].parse::<usize>().unwrap();
-        let h_to = parts[11].parse::<usize>().unwrap();
-        let l_to = match parts[5] {
-            "bot" => Target::to_bot(l_to),
-            "output" => Target::to_output(l_to),
-            _ => return Err(()),
-        };
-        let h_to = match parts[10] {
-            "bot" => Target::to_bot(h_to),
-            "output" => Target::to_output(h_to),
-            _ => return Err(()),
-        };
+        let tokens = Tokens::new(s);
+        tokens.expect(0, "bot")?;
+        let number = tokens.parse::<usize>(1, "bot number")?;
+        tokens.expect(2, "gives")?;
+        tokens.expect(3, "low")?;
+        tokens.expect(4, "to")?;
+        let l_to = Target::parse(&tokens, 5)?;
+        tokens.expect(7, "and")?;
+        tokens.expect(8, "high")?;
+        tokens.expect(9, "to")?;
+        let h_to = Target::parse(&tokens, 10)?;
         Ok(Bot::new(number, l_to, h_to))
     }
 }
 
+enum Instruction {
+    Input(Package),
+    Bot(Bot),
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        match tokens.get(0, "`value` or `bot`")? {
+            "value" => Ok(Instruction::Input(s.parse::<Package>()?)),
+            "bot" => Ok(Instruction::Bot(s.parse::<Bot>()?)),
+            _ => Err(tokens.error(0, "`value` or `bot`")),
+        }
+    }
+}
+
 impl PartialEq for Bot {
     fn eq(&self, other: &Self) -> bool {
         self.number == other.number
@@ -110,21 +138,20 @@ struct Factory {
 }
 
 impl Factory {
-    fn work(&self) -> FactoryIterator {
+    fn work(&self) -> FactoryIterator<'_> {
         FactoryIterator::new(self)
     }
 }
 
-impl FromIterator<String> for Factory {
-    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+impl FromIterator<Instruction> for Factory {
+    fn from_iter<I: IntoIterator<Item = Instruction>>(iter: I) -> Self {
         let mut inputs = vec![];
         let mut bots = vec![];
 
-        for l in iter {
-            if l.starts_with("bot ") {
-                bots.push(l.parse::<Bot>().unwrap());
-            } else if l.starts_with("value ") {
-                inputs.push(l.parse::<Package>().unwrap());
+        for i in iter {
+            match i {
+                Instruction::Bot(bot) => bots.push(bot),
+                Instruction::Input(package) => inputs.push(package),
             }
         }
 
@@ -156,16 +183,14 @@ impl Iterator for FactoryIterator<'_> {
             None => match self.packages.pop() {
                 Some((from, package)) => {
                     match package.target {
-                        Target::ToBot(number) => self
-                            .state
-                            .entry(number)
-                            .or_insert(vec![])
-                            .push(package.value),
+                        Target::ToBot(number) => {
+                            self.state.entry(number).or_default().push(package.value)
+                        }
                         Target::ToOutput(_) => {}
                     };
                     Some(FactoryHistoryRecord::Transmission {
-                        _from_bot: from,
-                        package: package,
+                        from_bot: from,
+                        package,
                     })
                 }
                 None => {
@@ -174,8 +199,7 @@ impl Iterator for FactoryIterator<'_> {
                         .iter()
                         .find_map(|(k, v)| if v.len() == 2 { Some(k) } else { None })
                         .cloned();
-                    if bot.is_some() {
-                        let bot = bot.unwrap();
+                    if let Some(bot) = bot {
                         let values = self.state.remove(&bot).unwrap();
                         let bot = self.factory.bots.iter().find(|b| b.number == bot).unwrap();
                         let min = values.iter().min().unwrap();
@@ -195,10 +219,10 @@ impl Iterator for FactoryIterator<'_> {
             },
             Some(p) => {
                 match p.target {
-                    Target::ToBot(b) => self.state.entry(b).or_insert(vec![]).push(p.value),
+                    Target::ToBot(b) => self.state.entry(b).or_default().push(p.value),
                     Target::ToOutput(_) => (),
                 };
-                Some(FactoryHistoryRecord::Input { _package: p })
+                Some(FactoryHistoryRecord::Input { package: p })
             }
         }
     }
@@ -206,47 +230,68 @@ impl Iterator for FactoryIterator<'_> {
 
 enum FactoryHistoryRecord {
     Comparation { bot: usize, values: (usize, usize) },
-    Transmission { _from_bot: usize, package: Package },
-    Input { _package: Package },
+    Transmission { from_bot: usize, package: Package },
+    Input { package: Package },
 }
 
-pub fn run() {
-    let input = File::open("input/task_10").unwrap();
-    let input = BufReader::new(input);
-
-    let factory = input.lines().filter_map(|l| l.ok()).collect::<Factory>();
-
-    let result = factory.work().find_map(|r| match r {
-        FactoryHistoryRecord::Comparation { bot, values } => {
-            let min = usize::min(values.0, values.1);
-            let max = usize::max(values.0, values.1);
-            if min == 17 && max == 61 {
-                Some(bot)
-            } else {
-                None
+impl Display for FactoryHistoryRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FactoryHistoryRecord::Comparation { bot, values } => {
+                write!(f, "bot {} compares {} with {}", bot, values.0, values.1)
+            }
+            FactoryHistoryRecord::Transmission { from_bot, package } => write!(
+                f,
+                "bot {} gives {} to {}",
+                from_bot, package.value, package.target
+            ),
+            FactoryHistoryRecord::Input { package } => {
+                write!(f, "value {} goes to {}", package.value, package.target)
             }
         }
-        _ => None,
-    });
-
-    println!("{:?}", result);
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_10").unwrap();
-    let input = BufReader::new(input);
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let chips = ctx.param_pair("chips", (17, 61), "two comma separated chip values")?;
+    let chips = (usize::min(chips.0, chips.1), usize::max(chips.0, chips.1));
+    let factory = ctx
+        .lines::<Instruction>(input)?
+        .into_iter()
+        .collect::<Factory>();
 
-    let factory = input.lines().filter_map(|l| l.ok()).collect::<Factory>();
+    factory
+        .work()
+        .inspect(|r| ctx.step(|| r.to_string()))
+        .find_map(|r| match r {
+            FactoryHistoryRecord::Comparation { bot, values } => {
+                let min = usize::min(values.0, values.1);
+                let max = usize::max(values.0, values.1);
+                if (min, max) == chips {
+                    Some(bot)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .ok_or(Error::NoAnswer("no bot compares the given chips"))
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let outputs = ctx.param_list("outputs", vec![0, 1, 2], "comma separated output numbers")?;
+    let factory = ctx
+        .lines::<Instruction>(input)?
+        .into_iter()
+        .collect::<Factory>();
 
-    let result = factory
+    Ok(factory
         .work()
+        .inspect(|r| ctx.step(|| r.to_string()))
         .filter_map(|r| match r {
-            FactoryHistoryRecord::Transmission {
-                _from_bot: _,
-                package,
-            } => match package.target {
+            FactoryHistoryRecord::Transmission { package, .. } => match package.target {
                 Target::ToOutput(number) => {
-                    if number == 0 || number == 1 || number == 2 {
+                    if outputs.contains(&number) {
                         Some(package.value)
                     } else {
                         None
@@ -256,7 +301,127 @@ pub fn run_e() {
             },
             _ => None,
         })
-        .product::<usize>();
+        .product::<usize>())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        10
+    }
+    fn title(&self) -> &'static str {
+        "Balance Bots"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "chips",
+                help: "pair of chips the wanted bot compares (17,61)",
+            },
+            Param {
+                name: "outputs",
+                help: "outputs whose chips are multiplied (0,1,2)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
 
-    println!("{:?}", result);
+    const EXAMPLE: &str = "value 5 goes to bot 2
+bot 2 gives low to bot 1 and high to bot 0
+value 3 goes to bot 1
+bot 1 gives low to output 1 and high to bot 0
+bot 0 gives low to output 2 and high to output 0
+value 2 goes to bot 2";
+
+    #[test]
+    fn parses_instructions() {
+        let package = "value 5 goes to bot 2".parse::<Package>().unwrap();
+        assert_eq!(package.value, 5);
+        assert!(matches!(package.target, Target::ToBot(2)));
+        let bot = "bot 1 gives low to output 1 and high to bot 0"
+            .parse::<Bot>()
+            .unwrap();
+        assert_eq!(bot.number, 1);
+        assert!(matches!(bot.lower_to, Target::ToOutput(1)));
+        assert!(matches!(bot.higher_to, Target::ToBot(0)));
+        let e = "bot 1 gives low to bin 1 and high to bot 0"
+            .parse::<Instruction>()
+            .err()
+            .unwrap();
+        assert_eq!((e.column, e.token.as_str()), (20, "bin"));
+        let e = "value five goes to bot 2"
+            .parse::<Instruction>()
+            .err()
+            .unwrap();
+        assert_eq!(e.column, 7);
+    }
+
+    #[test]
+    fn finds_bot_comparing_chips() {
+        let factory = EXAMPLE
+            .lines()
+            .map(|l| l.parse::<Instruction>().unwrap())
+            .collect::<Factory>();
+        let bot = factory.work().find_map(|r| match r {
+            FactoryHistoryRecord::Comparation { bot, values } => {
+                let mut values = [values.0, values.1];
+                values.sort_unstable();
+                if values == [2, 5] {
+                    Some(bot)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        });
+        assert_eq!(bot, Some(2));
+    }
+
+    #[test]
+    fn multiplies_outputs() {
+        assert_eq!(part2(EXAMPLE, &Context::new()).unwrap(), 30);
+        assert!(part1(EXAMPLE, &Context::new()).is_err());
+    }
+
+    #[test]
+    fn takes_chips_and_outputs_as_params() {
+        let ctx = Context::new()
+            .with_param("chips", "5,2")
+            .with_param("outputs", "0,2");
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), 2);
+        assert_eq!(part2(EXAMPLE, &ctx).unwrap(), 15);
+        let ctx = Context::new().with_param("chips", "5");
+        assert!(matches!(
+            part1(EXAMPLE, &ctx),
+            Err(Error::Param { name: "chips", .. })
+        ));
+    }
+
+    #[test]
+    fn traces_bots() {
+        let ctx = Context::new().with_trace(Trace::collected().with_filter("bot 2"));
+        part2(EXAMPLE, &ctx).unwrap();
+        assert_eq!(
+            ctx.trace().unwrap().lines(),
+            [
+                "step 1: value 5 goes to bot 2",
+                "step 3: value 2 goes to bot 2",
+                "step 4: bot 2 compares 5 with 2",
+                "step 5: bot 2 gives 5 to bot 0",
+                "step 6: bot 2 gives 2 to bot 1"
+            ]
+        );
+    }
 }
@@ -1,14 +1,52 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::search::{self, SearchState};
+use crate::solver::{Param, Solver};
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::iter::{FromIterator, IntoIterator};
+use std::rc::Rc;
+use std::str::FromStr;
 
+/// The floors as the input names them, from the bottom up.
+const FLOOR_NAMES: [&str; 10] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+];
+
+/// How many units the elevator carries at most unless told otherwise; it
+/// never moves empty.
+const CAPACITY: usize = 2;
+
+/// A unit of an element, known by its full name.
 #[derive(Eq, PartialEq, Hash, Debug, Clone)]
 enum Unit {
-    Generator(char),
-    Microchip(char),
+    Generator(Rc<str>),
+    Microchip(Rc<str>),
+}
+
+impl Unit {
+    fn element(&self) -> &str {
+        match self {
+            Unit::Generator(e) | Unit::Microchip(e) => e,
+        }
+    }
+
+    /// The first `len` letters of the element, capitalised, and the kind.
+    fn label(&self, len: usize) -> String {
+        let mut letters = self.element().chars().take(len);
+        let mut label = letters
+            .next()
+            .map(|c| c.to_uppercase().collect::<String>())
+            .unwrap_or_default();
+        label.extend(letters);
+        label.push(match self {
+            Unit::Generator(_) => 'G',
+            Unit::Microchip(_) => 'M',
+        });
+        label
+    }
 }
 
 impl Ord for Unit {
@@ -28,9 +66,11 @@ impl PartialOrd for Unit {
     }
 }
 
+#[derive(Clone)]
 struct FactoryLayout {
     floors: usize,
     elevator: usize,
+    capacity: usize,
     units: BTreeMap<Unit, usize>,
 }
 
@@ -50,7 +90,7 @@ impl<'a> IntoIterator for &'a FactoryLayout {
     }
 }
 
-impl<'a> Iterator for FactoryFloorIterator<'a> {
+impl Iterator for FactoryFloorIterator<'_> {
     type Item = Vec<Unit>;
     fn next(&mut self) -> Option<Self::Item> {
         if self.floor < self.factory.floors {
@@ -77,14 +117,21 @@ impl<'a> Iterator for FactoryFloorIterator<'a> {
 impl Display for Unit {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Unit::Generator(c) => write!(f, "{}G", c.to_uppercase()),
-            Unit::Microchip(c) => write!(f, "{}M", c.to_uppercase()),
+            Unit::Generator(e) => write!(f, "{} generator", e),
+            Unit::Microchip(e) => write!(f, "{}-compatible microchip", e),
         }
     }
 }
 
 impl Debug for FactoryLayout {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let len = self.label_len();
+        let width = self
+            .units
+            .keys()
+            .map(|u| u.label(len).len())
+            .max()
+            .unwrap_or(0);
         let result = (0..self.floors)
             .rev()
             .map(|floor| (floor, format!("F{}", floor + 1)))
@@ -101,9 +148,9 @@ impl Debug for FactoryLayout {
                     .iter()
                     .map(|(unit, unit_floor)| {
                         if &floor == unit_floor {
-                            format!("{} ", unit.to_string())
+                            format!("{:<w$} ", unit.label(len), w = width)
                         } else {
-                            ".  ".to_string()
+                            format!("{:<w$} ", ".", w = width)
                         }
                     })
                     .collect::<String>();
@@ -111,93 +158,647 @@ impl Debug for FactoryLayout {
             })
             .map(|s| format!("{}\n", s))
             .collect::<String>();
-        write!(f, "{}\n", result)
+        writeln!(f, "{}", result)
     }
 }
 
-impl FromIterator<String> for FactoryLayout {
-    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
-        let (floors, units) = iter.into_iter().enumerate().fold(
-            (0, BTreeMap::new()),
-            |(floor_acc, mut units_acc), (floor, l)| {
-                if l.ends_with(" floor contains nothing relevant.") {
-                    // do nothing
-                } else {
-                    let parts = l
-                        .split(" a ")
-                        .skip(1)
-                        .map(|l| l.split(" ").take(2).collect::<Vec<&str>>())
-                        .map(|l| {
-                            if l[1].starts_with("generator") {
-                                Unit::Generator(l[0].chars().nth(0).unwrap())
-                            } else {
-                                Unit::Microchip(l[0].chars().nth(0).unwrap())
-                            }
-                        })
-                        .collect::<Vec<Unit>>();
-                    for p in parts {
-                        units_acc.insert(p, floor);
-                    }
+/// An element name: lowercase letters only.
+fn element<'a>(tokens: &Tokens<'a>, i: usize, name: &'a str) -> Result<Rc<str>, ParseError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err(tokens.error(i, "element"));
+    }
+    Ok(name.into())
+}
+
+struct Floor {
+    number: usize,
+    units: Vec<Unit>,
+}
+
+impl FromStr for Floor {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        tokens.expect(0, "The")?;
+        let name = tokens.get(1, "floor name")?;
+        let number = match FLOOR_NAMES.iter().position(|n| *n == name) {
+            Some(number) => number,
+            None => return Err(tokens.error(1, "floor name like `first`")),
+        };
+        tokens.expect(2, "floor")?;
+        tokens.expect(3, "contains")?;
+        if tokens.get(4, "`a` or `nothing`")? == "nothing" {
+            tokens.expect(5, "relevant.")?;
+            return Ok(Floor {
+                number,
+                units: vec![],
+            });
+        }
+        let mut units = vec![];
+        let mut i = 4;
+        while i < tokens.len() {
+            match tokens.get(i, "`a`")? {
+                "and" => {
+                    i += 1;
+                    continue;
                 }
-                (usize::max(floor_acc, floor), units_acc)
-            },
-        );
+                "a" => (),
+                _ => return Err(tokens.error(i, "`a`")),
+            }
+            let name = tokens.get(i + 1, "element")?;
+            let kind = tokens
+                .get(i + 2, "`generator` or `microchip`")?
+                .trim_end_matches([',', '.']);
+            let unit = match kind {
+                "generator" => Unit::Generator(element(&tokens, i + 1, name)?),
+                "microchip" => match name.strip_suffix("-compatible") {
+                    Some(name) => Unit::Microchip(element(&tokens, i + 1, name)?),
+                    None => return Err(tokens.error(i + 1, "`<element>-compatible`")),
+                },
+                _ => return Err(tokens.error(i + 2, "`generator` or `microchip`")),
+            };
+            units.push(unit);
+            i += 3;
+        }
+        Ok(Floor { number, units })
+    }
+}
+
+/// Places the units on the floors they are named for. The building is as
+/// high as the highest floor named, the elevator waits on the first.
+impl FromIterator<Floor> for FactoryLayout {
+    fn from_iter<I: IntoIterator<Item = Floor>>(iter: I) -> Self {
+        let mut floors = 0;
+        let mut units = BTreeMap::new();
+        for floor in iter {
+            for unit in floor.units {
+                units.insert(unit, floor.number);
+            }
+            floors = floors.max(floor.number + 1);
+        }
         FactoryLayout {
-            floors: floors + 1,
+            floors,
             elevator: 0,
+            capacity: CAPACITY,
             units,
         }
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_11").unwrap();
-    let input = BufReader::new(input);
+/// Units added to the first floor by name: an element alone brings its
+/// generator and its microchip, `<element> generator` or `<element>
+/// microchip` just the one.
+struct Extra(Vec<Unit>);
 
-    let factory = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .collect::<FactoryLayout>();
+impl FromStr for Extra {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        let name = element(&tokens, 0, tokens.get(0, "element")?)?;
+        let units = match tokens.len() {
+            1 => vec![Unit::Generator(name.clone()), Unit::Microchip(name)],
+            2 => match tokens.get(1, "`generator` or `microchip`")? {
+                "generator" => vec![Unit::Generator(name)],
+                "microchip" => vec![Unit::Microchip(name)],
+                _ => return Err(tokens.error(1, "`generator` or `microchip`")),
+            },
+            _ => return Err(tokens.error(2, "end of unit")),
+        };
+        Ok(Extra(units))
+    }
+}
+
+impl FactoryLayout {
+    /// A microchip is fried on a floor with another generator unless its own
+    /// generator is there to shield it.
+    fn is_safe(&self, floor: usize) -> bool {
+        let generators = self
+            .units
+            .iter()
+            .any(|(u, f)| *f == floor && matches!(u, Unit::Generator(_)));
+        !generators
+            || self.units.iter().all(|(u, f)| match u {
+                Unit::Microchip(e) if *f == floor => {
+                    self.units.get(&Unit::Generator(e.clone())) == Some(&floor)
+                }
+                _ => true,
+            })
+    }
+
+    fn moved(&self, units: &[&Unit], to: usize) -> FactoryLayout {
+        let mut next = self.clone();
+        for unit in units {
+            next.units.insert((*unit).clone(), to);
+        }
+        next.elevator = to;
+        next
+    }
 
-    let result = (0..factory.floors - 1)
-        .map(|i| {
-            2 * factory
-                .into_iter()
-                .take(i + 1)
-                .map(|v| v.len())
-                .sum::<usize>()
-                - 3
+    /// How many letters of every element tell them all apart.
+    fn label_len(&self) -> usize {
+        let elements = self
+            .units
+            .keys()
+            .map(|u| u.element())
+            .collect::<BTreeSet<&str>>();
+        let longest = elements.iter().map(|e| e.len()).max().unwrap_or(1);
+        (1..longest)
+            .find(|len| {
+                let prefixes = elements
+                    .iter()
+                    .map(|e| e.get(..*len).unwrap_or(e))
+                    .collect::<BTreeSet<&str>>();
+                prefixes.len() == elements.len()
+            })
+            .unwrap_or(longest)
+    }
+}
+
+/// Every way to pick at least one and at most `capacity` of `units`.
+fn loads<'a>(units: &[&'a Unit], capacity: usize) -> Vec<Vec<&'a Unit>> {
+    let mut loads: Vec<Vec<&Unit>> = vec![];
+    for (i, unit) in units.iter().enumerate() {
+        loads.push(vec![*unit]);
+        if capacity > 1 {
+            for mut load in self::loads(&units[i + 1..], capacity - 1) {
+                load.insert(0, *unit);
+                loads.push(load);
+            }
+        }
+    }
+    loads
+}
+
+impl SearchState for FactoryLayout {
+    /// Elements are interchangeable: only the elevator and the floors of
+    /// every generator and microchip pair matter, not which element it is.
+    type Key = (usize, Vec<(Option<usize>, Option<usize>)>);
+
+    fn successors(&self) -> Vec<(Self, usize)> {
+        let here = self
+            .units
+            .iter()
+            .filter(|(_, f)| **f == self.elevator)
+            .map(|(u, _)| u)
+            .collect::<Vec<&Unit>>();
+        let loads = loads(&here, self.capacity);
+        let mut floors = vec![];
+        if self.elevator + 1 < self.floors {
+            floors.push(self.elevator + 1);
+        }
+        if self.elevator > 0 {
+            floors.push(self.elevator - 1);
+        }
+        floors
+            .into_iter()
+            .flat_map(|to| loads.iter().map(move |load| self.moved(load, to)))
+            .filter(|next| next.is_safe(self.elevator) && next.is_safe(next.elevator))
+            .map(|next| (next, 1))
+            .collect()
+    }
+
+    fn is_goal(&self) -> bool {
+        self.units.values().all(|f| *f + 1 == self.floors)
+    }
+
+    fn heuristic(&self) -> usize {
+        estimate(self)
+    }
+
+    fn key(&self) -> Self::Key {
+        let mut pairs = BTreeMap::new();
+        for (unit, floor) in &self.units {
+            let pair = pairs.entry(unit.element()).or_insert((None, None));
+            match unit {
+                Unit::Generator(_) => pair.0 = Some(*floor),
+                Unit::Microchip(_) => pair.1 = Some(*floor),
+            }
+        }
+        let mut pairs = pairs.into_values().collect::<Vec<_>>();
+        pairs.sort_unstable();
+        (self.elevator, pairs)
+    }
+}
+
+/// A lower bound of the moves left. Every move crosses one gap between
+/// floors. With the elevator below a gap, the `n` units below it cross in
+/// trips up with at most `capacity` units, each but the last followed by a
+/// trip down with at least one: `2 * n - 3` moves for two at a time. An
+/// elevator above the gap first comes down with a unit.
+fn estimate(factory: &FactoryLayout) -> usize {
+    let crossings = |n: usize| {
+        // One unit at a time never gets a second one across: any bound does.
+        let trips = match factory.capacity {
+            _ if n <= 1 => n,
+            1 => n,
+            c => (n - 1).div_ceil(c - 1),
+        };
+        (2 * trips).saturating_sub(1)
+    };
+    (0..factory.floors.saturating_sub(1))
+        .map(|gap| {
+            let below = factory.units.values().filter(|f| **f <= gap).count();
+            match below {
+                0 => 0,
+                n if factory.elevator <= gap => crossings(n),
+                n => 1 + crossings(n + 1),
+            }
         })
-        .sum::<usize>();
+        .sum::<usize>()
+}
 
-    println!("Result: {}", result);
+/// One trip of the elevator and the labels of what it carried.
+struct Move {
+    units: Vec<String>,
+    from: usize,
+    to: usize,
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_11").unwrap();
-    let input = BufReader::new(input);
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "F{} -> F{}: {}",
+            self.from + 1,
+            self.to + 1,
+            self.units.join(" ")
+        )
+    }
+}
 
-    let mut factory = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .collect::<FactoryLayout>();
-    factory.units.insert(Unit::Generator('e'), 0);
-    factory.units.insert(Unit::Microchip('e'), 0);
-    factory.units.insert(Unit::Generator('d'), 0);
-    factory.units.insert(Unit::Microchip('d'), 0);
-
-    println!("{:?}", factory);
-
-    let result = (0..factory.floors - 1)
-        .map(|i| {
-            2 * factory
-                .into_iter()
-                .take(i + 1)
-                .map(|v| v.len())
-                .sum::<usize>()
-                - 3
+/// The fewest moves that bring every unit to the top floor, tracing each
+/// with the layout it leaves.
+fn plan(factory: FactoryLayout, ctx: &Context) -> Result<Vec<Move>, Error> {
+    let len = factory.label_len();
+    let path = search::astar(factory)
+        .found
+        .ok_or(Error::NoAnswer("the units cannot all be brought up safely"))?
+        .path;
+    let moves = path
+        .windows(2)
+        .map(|w| Move {
+            units: w[1]
+                .units
+                .iter()
+                .filter(|(u, f)| w[0].units.get(u) != Some(f))
+                .map(|(u, _)| u.label(len))
+                .collect(),
+            from: w[0].elevator,
+            to: w[1].elevator,
         })
-        .sum::<usize>();
+        .collect::<Vec<Move>>();
+    for (m, layout) in moves.iter().zip(&path[1..]) {
+        ctx.step(|| format!("{}\n{}", m, format!("{:?}", layout).trim_end()));
+    }
+    Ok(moves)
+}
 
-    println!("Result: {}", result);
+/// The factory with the building it is given by the `floors` and `capacity`
+/// parameters.
+fn parse(input: &str, ctx: &Context) -> Result<FactoryLayout, Error> {
+    let mut factory = ctx
+        .lines::<Floor>(input)?
+        .into_iter()
+        .collect::<FactoryLayout>();
+    let floors = ctx.param("floors", factory.floors, "number of floors")?;
+    if floors == 0 || floors < factory.floors {
+        return Err(Error::Param {
+            name: "floors",
+            value: floors.to_string(),
+            expected: "at least as many floors as the input names",
+        });
+    }
+    let capacity = ctx.param("capacity", CAPACITY, "number of units")?;
+    if capacity == 0 {
+        return Err(Error::Param {
+            name: "capacity",
+            value: capacity.to_string(),
+            expected: "room for at least one unit",
+        });
+    }
+    factory.floors = floors;
+    factory.capacity = capacity;
+    Ok(factory)
+}
+
+/// How many moves there are, followed by the moves themselves, one per
+/// line, when the `moves` parameter asks for them.
+fn answer(moves: &[Move], ctx: &Context) -> Result<String, Error> {
+    let mut answer = moves.len().to_string();
+    if ctx.param("moves", false, "`true` or `false`")? {
+        for m in moves {
+            answer.push_str(&format!("\n{}", m));
+        }
+    }
+    Ok(answer)
+}
+
+fn moves1(input: &str, ctx: &Context) -> Result<Vec<Move>, Error> {
+    let factory = parse(input, ctx)?;
+
+    plan(factory, ctx)
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    moves1(input, ctx).map(|moves| moves.len())
+}
+
+fn moves2(input: &str, ctx: &Context) -> Result<Vec<Move>, Error> {
+    let extra = ctx.param_list(
+        "extra",
+        vec![
+            Extra(vec![
+                Unit::Generator("elerium".into()),
+                Unit::Microchip("elerium".into()),
+            ]),
+            Extra(vec![
+                Unit::Generator("dilithium".into()),
+                Unit::Microchip("dilithium".into()),
+            ]),
+        ],
+        "comma separated elements or `<element> generator` and `<element> microchip`",
+    )?;
+    let mut factory = parse(input, ctx)?;
+    for unit in extra.into_iter().flat_map(|Extra(units)| units) {
+        if factory.units.contains_key(&unit) {
+            return Err(Error::Param {
+                name: "extra",
+                value: unit.to_string(),
+                expected: "units not in the factory yet",
+            });
+        }
+        factory.units.insert(unit, 0);
+    }
+
+    plan(factory, ctx)
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    moves2(input, ctx).map(|moves| moves.len())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        11
+    }
+    fn title(&self) -> &'static str {
+        "Radioisotope Thermoelectric Generators"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "floors",
+                help: "number of floors of the building (as many as the input names)",
+            },
+            Param {
+                name: "capacity",
+                help: "most units the elevator carries at once (2)",
+            },
+            Param {
+                name: "extra",
+                help: "units that start on the first floor in part 2, an element for its generator and microchip or `<element> generator`/`<element> microchip` (elerium,dilithium)",
+            },
+            Param {
+                name: "moves",
+                help: "list the moves after how many there are, `true` or `false` (false)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        answer(&moves1(input, ctx)?, ctx)
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        answer(&moves2(input, ctx)?, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    const EXAMPLE: &str = "The first floor contains a hydrogen-compatible microchip and a lithium-compatible microchip.
+The second floor contains a hydrogen generator.
+The third floor contains a lithium generator.
+The fourth floor contains nothing relevant.";
+
+    fn units(kind: fn(Rc<str>) -> Unit, elements: &[&str]) -> Vec<Unit> {
+        elements.iter().map(|e| kind((*e).into())).collect()
+    }
+
+    #[test]
+    fn parses_floors() {
+        let floor = EXAMPLE.lines().next().unwrap().parse::<Floor>().unwrap();
+        assert_eq!(floor.number, 0);
+        assert_eq!(
+            floor.units,
+            units(Unit::Microchip, &["hydrogen", "lithium"])
+        );
+        let floor = "The fourth floor contains nothing relevant."
+            .parse::<Floor>()
+            .unwrap();
+        assert_eq!(floor.number, 3);
+        assert!(floor.units.is_empty());
+        for (line, column, token) in [
+            ("The first floor contains a hydrogen chip.", 37, "chip."),
+            (
+                "The first floor contains a hydrogen microchip.",
+                28,
+                "hydrogen",
+            ),
+            ("The top floor contains nothing relevant.", 5, "top"),
+            (
+                "The first floor contains a Hydrogen generator.",
+                28,
+                "Hydrogen",
+            ),
+        ] {
+            let e = line.parse::<Floor>().err().unwrap();
+            assert_eq!((e.column, e.token.as_str()), (column, token), "{}", line);
+        }
+    }
+
+    #[test]
+    fn keeps_elements_with_the_same_initial_apart() {
+        let input = "The second floor contains a sulfur generator and a strontium generator.
+The first floor contains a sulfur-compatible microchip and a strontium-compatible microchip.";
+        let factory = parse(input, &Context::new()).unwrap();
+        assert_eq!(factory.floors, 2);
+        let floors = factory.into_iter().collect::<Vec<Vec<Unit>>>();
+        assert_eq!(floors[0], units(Unit::Microchip, &["strontium", "sulfur"]));
+        assert_eq!(floors[1], units(Unit::Generator, &["strontium", "sulfur"]));
+        assert_eq!(factory.label_len(), 2);
+        assert!(format!("{:?}", factory).starts_with("F2 .  StG SuG .   .   \n"));
+        assert_eq!(part1(input, &Context::new()).unwrap(), 1);
+    }
+
+    #[test]
+    fn lays_out_units_by_floor() {
+        let factory = parse(EXAMPLE, &Context::new()).unwrap();
+        assert_eq!(factory.floors, 4);
+        let floors = factory.into_iter().collect::<Vec<Vec<Unit>>>();
+        assert_eq!(floors[1], units(Unit::Generator, &["hydrogen"]));
+        assert_eq!(floors[2], units(Unit::Generator, &["lithium"]));
+        assert!(floors[3].is_empty());
+    }
+
+    #[test]
+    fn keeps_microchips_safe() {
+        let factory = parse(EXAMPLE, &Context::new()).unwrap();
+        assert!((0..4).all(|floor| factory.is_safe(floor)));
+        let next = factory.moved(&[&Unit::Microchip("lithium".into())], 1);
+        assert!(!next.is_safe(1));
+        let moves = factory
+            .successors()
+            .into_iter()
+            .map(|(next, _)| {
+                next.units[&Unit::Microchip("hydrogen".into())]
+                    + 2 * next.units[&Unit::Microchip("lithium".into())]
+            })
+            .collect::<Vec<usize>>();
+        // Only the hydrogen microchip can go up to its generator alone.
+        assert_eq!(moves, [1]);
+        let units = [
+            Unit::Generator("hydrogen".into()),
+            Unit::Generator("lithium".into()),
+            Unit::Microchip("hydrogen".into()),
+        ];
+        let units = units.iter().collect::<Vec<&Unit>>();
+        assert_eq!(loads(&units, 1).len(), 3);
+        assert_eq!(loads(&units, 2).len(), 6);
+        assert!(loads(&units, 2).contains(&vec![units[0], units[2]]));
+    }
+
+    #[test]
+    fn prunes_swapped_elements() {
+        let input =
+            "The first floor contains a hydrogen generator and a lithium-compatible microchip.
+The second floor contains a hydrogen-compatible microchip and a lithium generator.";
+        let factory = parse(input, &Context::new()).unwrap();
+        let swapped = factory.moved(
+            &[
+                &Unit::Generator("hydrogen".into()),
+                &Unit::Microchip("lithium".into()),
+            ],
+            1,
+        );
+        let swapped = swapped.moved(
+            &[
+                &Unit::Microchip("hydrogen".into()),
+                &Unit::Generator("lithium".into()),
+            ],
+            0,
+        );
+        assert_eq!(factory.key(), swapped.key());
+    }
+
+    #[test]
+    fn finds_fewest_moves() {
+        let ctx = Context::new();
+        let moves = plan(parse(EXAMPLE, &ctx).unwrap(), &ctx).unwrap();
+        assert_eq!(
+            moves.iter().map(|m| m.to_string()).collect::<Vec<String>>(),
+            [
+                "F1 -> F2: HM",
+                "F2 -> F3: HG HM",
+                "F3 -> F4: HG LG",
+                "F4 -> F3: HG",
+                "F3 -> F4: HG HM",
+                "F4 -> F3: LG",
+                "F3 -> F2: LG",
+                "F2 -> F1: LG",
+                "F1 -> F2: LG LM",
+                "F2 -> F3: LG LM",
+                "F3 -> F4: LG LM",
+            ]
+        );
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), 11);
+
+        let input = "The first floor contains a hydrogen generator, a hydrogen-compatible microchip, a lithium generator, and a lithium-compatible microchip.
+The second floor contains nothing relevant.
+The third floor contains nothing relevant.
+The fourth floor contains nothing relevant.";
+        assert_eq!(part1(input, &ctx).unwrap(), 15);
+        let ctx = Context::new().with_param("extra", "");
+        assert_eq!(part2(input, &ctx).unwrap(), 15);
+        let ctx = Context::new().with_param("extra", "elerium");
+        assert_eq!(part2(input, &ctx).unwrap(), 27);
+        let ctx = Context::new().with_param("extra", "Elerium");
+        assert!(part2(input, &ctx).is_err());
+    }
+
+    #[test]
+    fn lists_moves() {
+        let ctx = Context::new();
+        assert_eq!(Solution.part1(EXAMPLE, &ctx).unwrap(), "11");
+        let ctx = Context::new().with_param("moves", "true");
+        let answer = Solution.part1(EXAMPLE, &ctx).unwrap();
+        let lines = answer.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[..3], ["11", "F1 -> F2: HM", "F2 -> F3: HG HM"]);
+        assert_eq!(lines[11], "F3 -> F4: LG LM");
+        let ctx = Context::new().with_param("moves", "yes");
+        assert!(matches!(
+            Solution.part1(EXAMPLE, &ctx),
+            Err(Error::Param { name: "moves", .. })
+        ));
+    }
+
+    #[test]
+    fn configures_building() {
+        let input = "The first floor contains a hydrogen generator, a hydrogen-compatible microchip, a lithium generator, and a lithium-compatible microchip.";
+        assert_eq!(part1(input, &Context::new()).unwrap(), 0);
+        let ctx = Context::new().with_param("floors", "5");
+        assert_eq!(part1(input, &ctx).unwrap(), 20);
+        let ctx = Context::new()
+            .with_param("floors", "4")
+            .with_param("capacity", "3");
+        assert_eq!(part1(input, &ctx).unwrap(), 9);
+        let ctx = Context::new()
+            .with_param("floors", "4")
+            .with_param("capacity", "1");
+        assert!(matches!(part1(input, &ctx), Err(Error::NoAnswer(_))));
+        for (name, value) in [("capacity", "0"), ("floors", "0")] {
+            let ctx = Context::new().with_param(name, value);
+            assert!(matches!(part1(input, &ctx), Err(Error::Param { .. })));
+        }
+        let ctx = Context::new().with_param("floors", "3");
+        assert!(matches!(
+            part1(EXAMPLE, &ctx),
+            Err(Error::Param { name: "floors", .. })
+        ));
+
+        let ctx = Context::new()
+            .with_param("floors", "2")
+            .with_param("extra", "elerium generator");
+        assert_eq!(part2(input, &ctx).unwrap(), 7);
+        for extra in ["hydrogen", "elerium gen", "elerium generator now"] {
+            let ctx = Context::new().with_param("extra", extra);
+            assert!(
+                matches!(part2(input, &ctx), Err(Error::Param { name: "extra", .. })),
+                "{}",
+                extra
+            );
+        }
+    }
+
+    #[test]
+    fn traces_moves() {
+        let ctx = Context::new().with_trace(Trace::collected());
+        part1(EXAMPLE, &ctx).unwrap();
+        let lines = ctx.trace().unwrap().lines();
+        assert_eq!(lines.len(), 11);
+        assert!(
+            lines[0].starts_with("step 1:\nF1 -> F2: HM\nF4 .  "),
+            "{}",
+            lines[0]
+        );
+        assert!(
+            lines[10].starts_with("step 11:\nF3 -> F4: "),
+            "{}",
+            lines[10]
+        );
+    }
 }
@@ -1,54 +1,58 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::Point;
+use crate::parse::Tokens;
+use crate::search::{self, SearchState};
+use crate::solver::{Param, Solver};
+use std::str::FromStr;
 
 struct Room {
     seed: usize,
 }
 
-struct Path<'a> {
+/// A position in the office, searching for `target` without leaving the
+/// square of side `radius`.
+#[derive(Clone)]
+struct Cubicle<'a> {
     room: &'a Room,
-    visited: HashMap<(usize, usize), usize>,
-    position: (usize, usize),
+    position: Point,
+    target: Point,
+    radius: i64,
 }
 
-impl<'a> Path<'a> {
-    fn new(start_x: usize, start_y: usize, room: &'a Room) -> Self {
-        Path {
+impl<'a> Cubicle<'a> {
+    fn new(room: &'a Room, target: Point, radius: usize) -> Self {
+        Cubicle {
             room,
-            visited: HashMap::new(),
-            position: (start_x, start_y),
+            position: Point::new(1, 1),
+            target,
+            radius: radius as i64,
         }
     }
+}
 
-    fn evaluate(&mut self, radius: usize) {
-        self.evaluate_iterative(radius, self.position, 0);
-    }
-
-    fn evaluate_iterative(&mut self, radius: usize, position: (usize, usize), step: usize) {
-        if !self.room.wall_at(position.0, position.1) {
-            if self
-                .visited
-                .entry(position)
-                .or_insert(std::usize::MAX)
-                .clone()
-                > step
-            {
-                self.visited.insert(position, step);
-                if 0 < position.0 {
-                    self.evaluate_iterative(radius, (position.0 - 1, position.1), step + 1);
-                }
-                if position.0 < radius {
-                    self.evaluate_iterative(radius, (position.0 + 1, position.1), step + 1);
-                }
-                if 0 < position.1 {
-                    self.evaluate_iterative(radius, (position.0, position.1 - 1), step + 1);
-                }
-                if position.1 < radius {
-                    self.evaluate_iterative(radius, (position.0, position.1 + 1), step + 1);
-                }
-            }
-        }
+impl SearchState for Cubicle<'_> {
+    type Key = Point;
+
+    fn successors(&self) -> Vec<(Self, usize)> {
+        self.position
+            .neighbours()
+            .filter(|p| (0..=self.radius).contains(&p.x) && (0..=self.radius).contains(&p.y))
+            .filter(|p| !self.room.wall_at(*p))
+            .map(|position| (Cubicle { position, ..*self }, 1))
+            .collect()
+    }
+
+    fn is_goal(&self) -> bool {
+        self.position == self.target
+    }
+
+    fn heuristic(&self) -> usize {
+        self.position.manhattan(self.target) as usize
+    }
+
+    fn key(&self) -> Point {
+        self.position
     }
 }
 
@@ -57,48 +61,119 @@ impl Room {
         Room { seed }
     }
 
-    fn wall_at(&self, x: usize, y: usize) -> bool {
+    fn wall_at(&self, p: Point) -> bool {
+        let (x, y) = (p.x as usize, p.y as usize);
         let sum = x * x + 3 * x + 2 * x * y + y + y * y + self.seed;
-        let sum = format!("{:b}", sum).replace("0", "").len();
-        sum % 2 == 1
+        sum.count_ones() % 2 == 1
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_13").unwrap();
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
-    let input = buffer.parse::<usize>().unwrap();
-
-    let room = Room::new(input);
+impl FromStr for Room {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        if tokens.len() > 1 {
+            return Err(tokens.error(1, "end of line"));
+        }
+        tokens.parse::<usize>(0, "favorite number").map(Room::new)
+    }
+}
 
-    let mut path = Path::new(1, 1, &room);
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let room = ctx.parse::<Room>(input)?;
 
-    let target = (31, 39);
+    let target = ctx.param_pair("target", (31, 39), "target coordinates `x,y`")?;
 
     let radius = usize::max(target.0, target.1) * 2;
 
-    path.evaluate(radius);
+    let target = Point::new(target.0 as i64, target.1 as i64);
 
-    println!("Result: {}", path.visited[&target])
+    search::astar(Cubicle::new(&room, target, radius))
+        .found
+        .map(|found| found.cost)
+        .ok_or(Error::NoAnswer("the target cannot be reached"))
 }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_13").unwrap();
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
-    let input = buffer.parse::<usize>().unwrap();
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let room = ctx.parse::<Room>(input)?;
+
+    let steps = ctx.param("steps", 50, "number of steps")?;
 
-    let room = Room::new(input);
+    let start = Cubicle::new(&room, Point::new(1, 1), steps + 1);
 
-    let mut path = Path::new(1, 1, &room);
+    Ok(search::within(start, steps).len())
+}
 
-    let target = (25, 25);
+pub struct Solution;
 
-    let radius = usize::max(target.0, target.1) * 2;
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        13
+    }
+    fn title(&self) -> &'static str {
+        "A Maze of Twisty Little Cubicles"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "target",
+                help: "location to reach in part 1 (31,39)",
+            },
+            Param {
+                name: "steps",
+                help: "most steps to take in part 2 (50)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn parses_favorite_number() {
+        assert_eq!("10".parse::<Room>().unwrap().seed, 10);
+        let e = "10 11".parse::<Room>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (4, "11"));
+        assert!("ten".parse::<Room>().is_err());
+    }
 
-    path.evaluate(radius);
-    let result = path.visited.iter().filter(|(_, v)| v <= &&50).count();
+    #[test]
+    fn draws_office_layout() {
+        let room = Room::new(10);
+        let office = Grid::from_fn(10, 3, |p| room.wall_at(p));
+        assert_eq!(
+            office.render(|wall| if *wall { '#' } else { '.' }),
+            ".#.####.##\n..#..#...#\n#....##..."
+        );
+    }
 
-    println!("Result: {}", result)
+    #[test]
+    fn finds_fewest_steps() {
+        let room = Room::new(10);
+        let found = search::astar(Cubicle::new(&room, Point::new(7, 4), 20))
+            .found
+            .unwrap();
+        assert_eq!(found.cost, 11);
+        assert_eq!(found.path.len(), 12);
+        assert_eq!(found.path[0].position, Point::new(1, 1));
+    }
+
+    #[test]
+    fn takes_target_and_steps_as_params() {
+        let ctx = Context::new().with_param("target", "7,4");
+        assert_eq!(part1("10", &ctx).unwrap(), 11);
+        let ctx = Context::new().with_param("steps", "2");
+        assert_eq!(part2("10", &ctx).unwrap(), 5);
+        let ctx = Context::new().with_param("target", "7;4");
+        assert!(part1("10", &ctx).is_err());
+    }
 }
@@ -1,8 +1,24 @@
-use iterslide::SlideIterator;
-use md5;
-use std::fs::File;
-use std::io::Read;
-use std::iter::Iterator;
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::mining;
+use crate::progress::Stopped;
+use crate::solver::Solver;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+struct Salt(String);
+
+impl FromStr for Salt {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find(|c: char| !c.is_ascii_alphanumeric()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "alphanumeric salt")),
+            None if s.is_empty() => Err(ParseError::new(1, s, "salt")),
+            None => Ok(Salt(s.to_string())),
+        }
+    }
+}
 
 struct PasswordGenerator {
     salt: String,
@@ -18,169 +34,146 @@ impl PasswordGenerator {
         PasswordGenerator { salt, times }
     }
 
-    fn hash(&self, step: usize) -> String {
-        let mut input = format!("{}{}", self.salt, step);
-        for _ in 0..=self.times {
-            input = format!("{:x}", md5::compute(input.as_bytes()));
+    fn generator<'a>(
+        &'a self,
+        ctx: &'a Context,
+    ) -> PasswordGeneratorIterator<impl Iterator<Item = Result<Candidate, Stopped>> + 'a> {
+        let times = self.times;
+        let candidates = mining::mine(self.salt.as_bytes(), ctx, move |d| {
+            let d = d.stretch(times);
+            d.first_run(3).map(|triplet| (triplet, d.runs(5)))
+        })
+        .map(|found| {
+            found.map(|(index, (triplet, fives))| Candidate {
+                index,
+                triplet,
+                fives,
+            })
+        });
+        PasswordGeneratorIterator {
+            candidates,
+            window: VecDeque::new(),
         }
-        input
-    }
-
-    fn generator(&self) -> PasswordGeneratorIterator {
-        PasswordGeneratorIterator::new(self)
-    }
-}
-
-struct Triplet {
-    c: char,
-    pos: usize,
-}
-
-impl PartialEq for Triplet {
-    fn eq(&self, other: &Self) -> bool {
-        self.c == other.c && self.pos == other.pos
     }
 }
-impl Eq for Triplet {}
 
-struct PasswordGeneratorIterator<'a> {
-    password: &'a PasswordGenerator,
-    step: usize,
-    waits: Vec<Triplet>,
-    results: Vec<usize>,
+/// A hash with a triplet, which makes it a key if one of the next thousand
+/// hashes has five of the same nibble.
+struct Candidate {
+    index: usize,
+    triplet: u8,
+    fives: u16,
 }
 
-enum Trigger {
-    Triplet(char),
-    Five(char),
-    Both(char, char),
+struct PasswordGeneratorIterator<I> {
+    candidates: I,
+    window: VecDeque<Candidate>,
 }
 
-impl<'a> PasswordGeneratorIterator<'a> {
-    fn new(generator: &'a PasswordGenerator) -> Self {
-        PasswordGeneratorIterator {
-            password: generator,
-            step: 0,
-            waits: vec![],
-            results: vec![],
-        }
+impl<I: Iterator<Item = Result<Candidate, Stopped>>> PasswordGeneratorIterator<I> {
+    fn pull(&mut self) -> Option<Result<(), Stopped>> {
+        Some(self.candidates.next()?.map(|c| self.window.push_back(c)))
     }
+}
 
-    fn pop_result(&mut self) -> Option<usize> {
-        let index = self.results.iter().enumerate().find_map(|v| {
-            if v.1 == &self.step {
-                Some(v.0)
-            } else {
-                None
+impl<I: Iterator<Item = Result<Candidate, Stopped>>> Iterator for PasswordGeneratorIterator<I> {
+    type Item = Result<usize, Stopped>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.window.is_empty() {
+                if let Err(stopped) = self.pull()? {
+                    return Some(Err(stopped));
+                }
             }
-        });
-        if index.is_none() {
-            None
-        } else {
-            let result = self.results.remove(index.unwrap());
-            Some(result - 1000)
-        }
-    }
-
-    fn clean_up(&mut self) {
-        let mut i = 0;
-        while i < self.waits.len() {
-            if self.waits[i].pos + 1000 < self.step {
-                self.waits.remove(i);
-            } else {
-                i += 1;
+            let last = self.window[0].index + 1000;
+            while self.window.back().unwrap().index <= last {
+                if let Err(stopped) = self.pull()? {
+                    return Some(Err(stopped));
+                }
+            }
+            let candidate = self.window.pop_front().unwrap();
+            if self
+                .window
+                .iter()
+                .take_while(|c| c.index <= last)
+                .any(|c| c.fives & 1 << candidate.triplet != 0)
+            {
+                return Some(Ok(candidate.index));
             }
         }
     }
+}
 
-    fn add_triplet(&mut self, c: char) {
-        self.waits.push(Triplet { c, pos: self.step });
+/// The index of the 64th key.
+fn last_key(password: &PasswordGenerator, ctx: &Context) -> Result<usize, Error> {
+    let mut keys = vec![];
+    for key in password.generator(ctx).take(64) {
+        let key = key.map_err(|s| {
+            let last = keys
+                .last()
+                .map_or(String::new(), |k| format!(", the last at {}", k));
+            s.with_partial(format!("{} of 64 keys{}", keys.len(), last))
+        })?;
+        keys.push(key);
     }
+    keys.get(63)
+        .copied()
+        .ok_or(Error::NoAnswer("the keys ran out"))
+}
 
-    fn try_with_fives(&mut self, c: char) -> Vec<usize> {
-        let triplets = self
-            .waits
-            .iter()
-            .enumerate()
-            .filter(|t| t.1.c == c)
-            .map(|t| (t.0, t.1.pos))
-            .collect::<Vec<(usize, usize)>>();
-        for i in 0..triplets.len() {
-            self.waits.remove(triplets[i].0 - i);
-        }
-        triplets.into_iter().map(|t| t.1).collect()
-    }
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let Salt(salt) = ctx.parse::<Salt>(input)?;
+    last_key(&PasswordGenerator::new(salt), ctx)
+}
 
-    fn trigger(&self, step: usize) -> Option<Trigger> {
-        let hash = self.password.hash(step);
-        let fives = hash.chars().slide(5).find_map(|c| {
-            if c[0] == c[1] && c[1] == c[2] && c[2] == c[3] && c[3] == c[4] {
-                Some(c[0])
-            } else {
-                None
-            }
-        });
-        let threes = hash.chars().slide(3).find_map(|c| {
-            if c[0] == c[1] && c[1] == c[2] {
-                Some(c[0])
-            } else {
-                None
-            }
-        });
-        match (threes, fives) {
-            (Some(t), Some(f)) => Some(Trigger::Both(t, f)),
-            (Some(t), None) => Some(Trigger::Triplet(t)),
-            (None, Some(f)) => Some(Trigger::Five(f)),
-            _ => None,
-        }
-    }
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let Salt(salt) = ctx.parse::<Salt>(input)?;
+    last_key(&PasswordGenerator::new_iterative(salt, 2016), ctx)
 }
 
-impl<'a> Iterator for PasswordGeneratorIterator<'a> {
-    type Item = usize;
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut result = None;
-        while result.is_none() {
-            self.clean_up();
-            let fives = match self.trigger(self.step) {
-                Some(Trigger::Both(t, f)) => {
-                    let r = self.try_with_fives(f);
-                    self.add_triplet(t);
-                    r
-                }
-                Some(Trigger::Five(f)) => self.try_with_fives(f),
-                Some(Trigger::Triplet(t)) => {
-                    self.add_triplet(t);
-                    vec![]
-                }
-                _ => vec![],
-            };
-            for f in fives {
-                self.results.push(f + 1000);
-            }
-            result = self.pop_result();
-            self.step += 1;
-        }
-        result
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        14
+    }
+    fn title(&self) -> &'static str {
+        "One-Time Pad"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_14").unwrap();
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let password = PasswordGenerator::new(buffer);
-    let result = password.generator().take(64).last().unwrap();
-    println!("Result: {}", result);
-}
+    #[test]
+    fn parses_salt() {
+        assert_eq!("abc".parse::<Salt>().unwrap().0, "abc");
+        let e = "a-c".parse::<Salt>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (2, "-"));
+    }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_14").unwrap();
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
+    #[test]
+    fn generates_keys() {
+        let password = PasswordGenerator::new("abc".to_string());
+        let ctx = Context::new();
+        let keys = password
+            .generator(&ctx)
+            .take(2)
+            .collect::<Result<Vec<usize>, _>>();
+        assert_eq!(keys.unwrap(), [39, 92]);
+        assert_eq!(part1("abc", &Context::new()).unwrap(), 22728);
+    }
 
-    let password = PasswordGenerator::new_iterative(buffer, 2016);
-    let result = password.generator().take(64).last().unwrap();
-    println!("Result: {}", result);
+    #[test]
+    #[ignore]
+    fn generates_keys_with_stretching() {
+        assert_eq!(part2("abc", &Context::new()).unwrap(), 22551);
+    }
 }
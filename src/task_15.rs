@@ -1,5 +1,7 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::Solver;
 use std::iter::FromIterator;
 use std::str::FromStr;
 
@@ -11,17 +13,21 @@ struct Disk {
 
 impl Disk {
     fn is_opened(&self, time: i32) -> bool {
-        let result = (self.offset + time) % self.positions == 0;
-        result
+        (self.offset + time) % self.positions == 0
     }
 }
 
 impl FromStr for Disk {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s[0..s.len() - 1].split(" ").collect::<Vec<&str>>();
-        let positions = parts[3].parse::<i32>().unwrap();
-        let offset = parts[11].parse::<i32>().unwrap();
+        let tokens = Tokens::new(s);
+        tokens.expect(0, "Disc")?;
+        let positions = tokens.parse::<i32>(3, "number of positions")?;
+        if positions <= 0 {
+            return Err(tokens.error(3, "positive number of positions"));
+        }
+        tokens.expect(4, "positions;")?;
+        let offset = tokens.parse_trimmed::<i32>(11, &['.'], "starting position")?;
         Ok(Disk { positions, offset })
     }
 }
@@ -51,32 +57,78 @@ impl FromIterator<Disk> for Construction {
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_15").unwrap();
-    let input = BufReader::new(input);
-
-    let disks = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Disk>().ok())
-        .collect::<Construction>();
-    let result = (0..).find(|&time| disks.can_fall(time)).unwrap();
-    println!("Result: {}", result);
+fn parse(input: &str, ctx: &Context) -> Result<Construction, Error> {
+    Ok(ctx
+        .lines::<Disk>(input)?
+        .into_iter()
+        .collect::<Construction>())
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_15").unwrap();
-    let input = BufReader::new(input);
+pub fn part1(input: &str, ctx: &Context) -> Result<i32, Error> {
+    let disks = parse(input, ctx)?;
+    Ok((0..).find(|&time| disks.can_fall(time)).unwrap())
+}
 
-    let mut disks = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Disk>().ok())
-        .collect::<Construction>();
+pub fn part2(input: &str, ctx: &Context) -> Result<i32, Error> {
+    let mut disks = parse(input, ctx)?;
     disks.disks.push(Disk {
         offset: 0,
         positions: 11,
     });
-    let result = (0..).find(|&time| disks.can_fall(time)).unwrap();
-    println!("Result: {}", result);
+    Ok((0..).find(|&time| disks.can_fall(time)).unwrap())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        15
+    }
+    fn title(&self) -> &'static str {
+        "Timing is Everything"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Disc #1 has 5 positions; at time=0, it is at position 4.
+Disc #2 has 2 positions; at time=0, it is at position 1.";
+
+    #[test]
+    fn parses_disk() {
+        let disk = EXAMPLE.lines().next().unwrap().parse::<Disk>().unwrap();
+        assert_eq!((disk.positions, disk.offset), (5, 4));
+        let e = "Disc #1 has 0 positions; at time=0, it is at position 4."
+            .parse::<Disk>()
+            .err()
+            .unwrap();
+        assert_eq!((e.column, e.token.as_str()), (13, "0"));
+        let e = "Disc #1 has 5 positions; at time=0, it is at position four."
+            .parse::<Disk>()
+            .err()
+            .unwrap();
+        assert_eq!(e.column, 55);
+    }
+
+    #[test]
+    fn opens_disks_in_turn() {
+        let disks = parse(EXAMPLE, &Context::new()).unwrap();
+        assert!(!disks.can_fall(0));
+        assert!(disks.can_fall(5));
+        assert!(disks.disks[0].is_opened(1));
+    }
+
+    #[test]
+    fn finds_first_time_to_press_button() {
+        assert_eq!(part1(EXAMPLE, &Context::new()).unwrap(), 5);
+        assert_eq!(part2(EXAMPLE, &Context::new()).unwrap(), 85);
+    }
 }
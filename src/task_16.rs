@@ -1,6 +1,8 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::{Param, Solver};
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::Read;
+use std::num::NonZeroUsize;
 use std::ops::AddAssign;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
@@ -29,12 +31,24 @@ impl DerefMut for Data {
 }
 
 impl FromStr for Data {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseError::new(1, s, "initial state of `0` and `1`"));
+        }
         let mut data = Data(vec![]);
-        for c in s.chars() {
-            data.0.push(c.to_digit(10).unwrap() as u8);
+        for (i, c) in s.char_indices() {
+            match c {
+                '0' | '1' => data.0.push(c as u8 - b'0'),
+                _ => {
+                    return Err(ParseError::new(
+                        i + 1,
+                        &s[i..i + c.len_utf8()],
+                        "`0` or `1`",
+                    ))
+                }
+            }
         }
         Ok(data)
     }
@@ -67,15 +81,23 @@ impl Data {
     }
 
     fn invert(&mut self) {
-        for i in 0..self.0.len() {
-            if self.0[i] == 0 {
-                self.0[i] = 1;
-            } else {
-                self.0[i] = 0;
-            }
+        for c in self.0.iter_mut() {
+            *c = if *c == 0 { 1 } else { 0 };
         }
     }
 
+    /// Up to the first 64 bits of the data, with its length.
+    fn summary(&self) -> String {
+        let bits = self.iter().take(64).map(|c| (b'0' + c) as char);
+        let ellipsis = if self.len() > 64 { "..." } else { "" };
+        format!(
+            "{} bits {}{}",
+            self.len(),
+            bits.collect::<String>(),
+            ellipsis
+        )
+    }
+
     fn increase(&mut self) {
         let mut right = self.clone();
         right.reverse();
@@ -91,48 +113,148 @@ impl AddAssign for Data {
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_16").unwrap();
-
-    let mut buffer = String::new();
-
-    input.read_to_string(&mut buffer).unwrap();
+fn fill(input: &str, ctx: &Context, target_length: usize) -> Result<Data, Error> {
+    let mut data = ctx.parse::<Data>(input)?;
 
-    let mut data = buffer.parse::<Data>().unwrap();
-
-    let target_length = 272;
     while data.len() < target_length {
         data.increase();
+        ctx.step(|| format!("expand to {}", data.summary()));
+        ctx.tick(
+            data.len().min(target_length) as u64,
+            Some(target_length as u64),
+        )
+        .map_err(|s| s.with_partial(data.summary()))?;
     }
 
     data.truncate(target_length);
+    ctx.step(|| format!("truncate to {}", data.summary()));
 
     while data.len() % 2 == 0 {
-        data.checksum()
+        data.checksum();
+        ctx.step(|| format!("checksum of {}", data.summary()));
     }
 
-    println!("Result: {}", data);
+    Ok(data)
 }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_16").unwrap();
+pub fn part1(input: &str, ctx: &Context) -> Result<String, Error> {
+    let length = ctx.param(
+        "length1",
+        NonZeroUsize::new(272).unwrap(),
+        "positive disk length",
+    )?;
+    fill(input, ctx, length.get()).map(|d| d.to_string())
+}
 
-    let mut buffer = String::new();
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    let length = ctx.param(
+        "length2",
+        NonZeroUsize::new(35651584).unwrap(),
+        "positive disk length",
+    )?;
+    fill(input, ctx, length.get()).map(|d| d.to_string())
+}
 
-    input.read_to_string(&mut buffer).unwrap();
+pub struct Solution;
 
-    let mut data = buffer.parse::<Data>().unwrap();
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        16
+    }
+    fn title(&self) -> &'static str {
+        "Dragon Checksum"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "length1",
+                help: "length of the disk to fill in part 1 (272)",
+            },
+            Param {
+                name: "length2",
+                help: "length of the disk to fill in part 2 (35651584)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
 
-    let target_length = 35651584;
-    while data.len() < target_length {
-        data.increase();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    fn data(s: &str) -> Data {
+        s.parse::<Data>().unwrap()
     }
 
-    data.truncate(target_length);
+    #[test]
+    fn parses_data() {
+        assert_eq!(data("10110").0, [1, 0, 1, 1, 0]);
+        let e = "1012".parse::<Data>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (4, "2"));
+        assert!("".parse::<Data>().is_err());
+    }
 
-    while data.len() % 2 == 0 {
-        data.checksum()
+    #[test]
+    fn applies_dragon_curve() {
+        for (from, to) in [
+            ("1", "100"),
+            ("0", "001"),
+            ("11111", "11111000000"),
+            ("111100001010", "1111000010100101011110000"),
+        ] {
+            let mut d = data(from);
+            d.increase();
+            assert_eq!(d.to_string(), to);
+        }
     }
 
-    println!("Result: {}", data);
+    #[test]
+    fn reduces_checksum() {
+        let mut d = data("110010110100");
+        d.checksum();
+        assert_eq!(d.to_string(), "110101");
+        d.checksum();
+        assert_eq!(d.to_string(), "100");
+    }
+
+    #[test]
+    fn fills_disk() {
+        assert_eq!(
+            fill("10000", &Context::new(), 20).unwrap().to_string(),
+            "01100"
+        );
+    }
+
+    #[test]
+    fn takes_disk_lengths_as_params() {
+        let ctx = Context::new().with_param("length1", "20");
+        assert_eq!(part1("10000", &ctx).unwrap(), "01100");
+        let ctx = Context::new().with_param("length2", "0");
+        assert!(part2("10000", &ctx).is_err());
+    }
+
+    #[test]
+    fn traces_expansion() {
+        let ctx = Context::new()
+            .with_param("length1", "20")
+            .with_trace(Trace::collected());
+        assert_eq!(part1("10000", &ctx).unwrap(), "01100");
+        assert_eq!(
+            ctx.trace().unwrap().lines(),
+            [
+                "step 1: expand to 11 bits 10000011110",
+                "step 2: expand to 23 bits 10000011110010000111110",
+                "step 3: truncate to 20 bits 10000011110010000111",
+                "step 4: checksum of 10 bits 0111110101",
+                "step 5: checksum of 5 bits 01100"
+            ]
+        );
+    }
 }
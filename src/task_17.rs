@@ -1,187 +1,228 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::{Direction, Grid, Point};
+use crate::mining::Digest;
+use crate::search::{self, SearchState};
+use crate::solver::Solver;
 use std::fmt::Display;
-use std::fs::File;
-use std::io::Read;
+use std::str::FromStr;
+
+struct Passcode(String);
+
+impl FromStr for Passcode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find(|c: char| !c.is_ascii_alphanumeric()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "alphanumeric passcode")),
+            None if s.is_empty() => Err(ParseError::new(1, s, "passcode")),
+            None => Ok(Passcode(s.to_string())),
+        }
+    }
+}
 
 struct Key {
     password: String,
-    width: i32,
-    height: i32,
+    rooms: Grid<()>,
 }
 
+/// The order in which the hash tells which doors are open.
+const DOORS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
 impl Key {
-    fn new(password: String, width: i32, height: i32) -> Self {
+    fn new(password: String, width: usize, height: usize) -> Self {
         Key {
             password,
-            width,
-            height,
+            rooms: Grid::new(width, height, ()),
         }
     }
 
-    fn next_steps(&self, path: &Path) -> Vec<Step> {
-        let mut result = vec![];
-        let (x, y) = path.position();
-        let password = format!("{}{}", self.password, path.to_string());
-        let digest = format!("{:x}", md5::compute(password));
-        if ('b'..='f').contains(&digest.chars().nth(0).unwrap())
-            && ((y != self.height && self.height > 0) || (y != 0 && self.height < 0))
-        {
-            result.push(Step::Up);
-        }
-        if ('b'..='f').contains(&digest.chars().nth(1).unwrap())
-            && ((y != 0 && self.height > 0) || (y != self.height && self.height < 0))
-        {
-            result.push(Step::Down);
-        }
-        if ('b'..='f').contains(&digest.chars().nth(2).unwrap())
-            && ((x != 0 && self.width > 0) || (x != self.width && self.width < 0))
-        {
-            result.push(Step::Left);
-        }
-        if ('b'..='f').contains(&digest.chars().nth(3).unwrap())
-            && ((x != self.width && self.width > 0) || (x != 0 && self.width < 0))
-        {
-            result.push(Step::Right);
-        }
-        result
+    fn next_steps(&self, path: &Path) -> Vec<Direction> {
+        let mut password = Vec::with_capacity(self.password.len() + path.steps.len());
+        password.extend_from_slice(self.password.as_bytes());
+        password.extend_from_slice(path.steps.as_bytes());
+        let digest = Digest::of(&password);
+        DOORS
+            .iter()
+            .zip(digest.nibbles())
+            .filter(|(d, n)| {
+                (0xb..=0xf).contains(n) && self.rooms.contains(path.position().step(**d))
+            })
+            .map(|(d, _)| *d)
+            .collect()
     }
 
     fn is_finished(&self, path: &Path) -> bool {
-        let (x, y) = path.position();
-        x == self.width && y == self.height
+        let vault = Point::new(
+            self.rooms.width() as i64 - 1,
+            self.rooms.height() as i64 - 1,
+        );
+        path.position() == vault
     }
 }
 
 #[derive(Clone)]
-enum Step {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Step {
-    fn apply(&self, x: &mut i32, y: &mut i32) {
-        match self {
-            Step::Up => *y += 1,
-            Step::Down => *y -= 1,
-            Step::Right => *x += 1,
-            Step::Left => *x -= 1,
-        }
-    }
+struct Path {
+    /// The letters of the steps taken, which is also what gets hashed.
+    steps: String,
+    position: Point,
 }
 
-#[derive(Clone)]
-struct Path(Vec<Step>);
-
 impl Path {
     fn new() -> Self {
-        Path(vec![])
+        Path {
+            steps: String::new(),
+            position: Point::ORIGIN,
+        }
     }
 
-    fn position(&self) -> (i32, i32) {
-        self.0
-            .iter()
-            .fold((0, 0), |(mut x, mut y): (i32, i32), step: &Step| {
-                step.apply(&mut x, &mut y);
-                (x, y)
-            })
+    fn position(&self) -> Point {
+        self.position
     }
 
-    fn move_to(&mut self, step: Step) {
-        self.0.push(step);
+    fn move_to(&mut self, step: Direction) {
+        self.steps.push(step.letter());
+        self.position = self.position.step(step);
     }
 }
 
 impl Display for Path {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let result = self
-            .0
-            .iter()
-            .map(|step| match step {
-                Step::Up => 'U',
-                Step::Down => 'D',
-                Step::Left => 'L',
-                Step::Right => 'R',
-            })
-            .collect::<String>();
-        write!(f, "{}", result)
+        write!(f, "{}", self.steps)
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_17").unwrap();
-    let mut password = String::new();
-
-    input.read_to_string(&mut password).unwrap();
-    let key = Key::new(password, 3, -3);
-
-    let mut steps = vec![Path::new()];
-
-    loop {
-        let result = steps.iter().find(|path: &&Path| key.is_finished(path));
-        match result {
-            Some(result) => {
-                println!("Result: {}", result);
-                break;
-            }
-            None => (),
-        };
-        steps = steps
+/// A path through the vault as it is being searched.
+#[derive(Clone)]
+struct Walk<'a> {
+    key: &'a Key,
+    path: Path,
+}
+
+impl SearchState for Walk<'_> {
+    type Key = String;
+
+    fn successors(&self) -> Vec<(Self, usize)> {
+        self.key
+            .next_steps(&self.path)
             .into_iter()
-            .flat_map(|path| {
-                let next_steps = key.next_steps(&path);
-                let mut result = vec![];
-                for step in next_steps {
-                    let mut p = path.clone();
-                    p.move_to(step);
-                    result.push(p);
-                }
-                result
+            .map(|step| {
+                let mut path = self.path.clone();
+                path.move_to(step);
+                (
+                    Walk {
+                        key: self.key,
+                        path,
+                    },
+                    1,
+                )
             })
-            .collect();
+            .collect()
+    }
+
+    fn is_goal(&self) -> bool {
+        self.key.is_finished(&self.path)
+    }
+
+    fn key(&self) -> String {
+        self.path.steps.clone()
     }
 }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_17").unwrap();
-    let mut password = String::new();
+fn walk(key: &Key) -> Walk<'_> {
+    Walk {
+        key,
+        path: Path::new(),
+    }
+}
 
-    input.read_to_string(&mut password).unwrap();
-    let key = Key::new(password, 3, -3);
+fn shortest(key: &Key) -> Option<Path> {
+    search::bfs(walk(key)).found.map(|f| f.goal().path.clone())
+}
 
-    let mut steps = vec![Path::new()];
+fn longest(key: &Key) -> Option<usize> {
+    search::longest(walk(key)).found.map(|f| f.cost)
+}
 
-    let mut i = 0;
+pub fn part1(input: &str, ctx: &Context) -> Result<String, Error> {
+    let Passcode(passcode) = ctx.parse::<Passcode>(input)?;
+    shortest(&Key::new(passcode, 4, 4))
+        .map(|path| path.to_string())
+        .ok_or(Error::NoAnswer("the vault cannot be reached"))
+}
 
-    let mut latest = 0;
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let Passcode(passcode) = ctx.parse::<Passcode>(input)?;
+    longest(&Key::new(passcode, 4, 4)).ok_or(Error::NoAnswer("the vault cannot be reached"))
+}
 
-    loop {
-        if steps.is_empty() {
-            println!("Result: {}", latest);
-            break;
-        }
-        steps = steps
-            .into_iter()
-            .inspect(|step| {
-                if key.is_finished(&step) {
-                    latest = i;
-                }
-            })
-            .filter(|step| !key.is_finished(&step))
-            .collect();
-        steps = steps
-            .into_iter()
-            .flat_map(|path| {
-                let next_steps = key.next_steps(&path);
-                let mut result = vec![];
-                for step in next_steps {
-                    let mut p = path.clone();
-                    p.move_to(step);
-                    result.push(p);
-                }
-                result
-            })
-            .collect();
-        i += 1;
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        17
+    }
+    fn title(&self) -> &'static str {
+        "Two Steps Forward"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(passcode: &str) -> Key {
+        Key::new(passcode.to_string(), 4, 4)
+    }
+
+    #[test]
+    fn parses_passcode() {
+        assert_eq!("hijkl".parse::<Passcode>().unwrap().0, "hijkl");
+        let e = "hij kl".parse::<Passcode>().err().unwrap();
+        assert_eq!(e.column, 4);
+    }
+
+    #[test]
+    fn opens_doors_by_hash() {
+        let key = vault("hijkl");
+        let steps = key.next_steps(&Path::new());
+        assert_eq!(steps, [Direction::South]);
+        let mut path = Path::new();
+        path.move_to(Direction::South);
+        let steps = key.next_steps(&path);
+        assert_eq!(steps, [Direction::North, Direction::East]);
+        assert_eq!(path.to_string(), "D");
+        assert_eq!(path.position(), Point::new(0, 1));
+    }
+
+    #[test]
+    fn finds_shortest_path() {
+        assert_eq!(shortest(&vault("ihgpwlah")).unwrap().to_string(), "DDRRRD");
+        assert_eq!(
+            shortest(&vault("kglvqrro")).unwrap().to_string(),
+            "DDUDRLRRUDRD"
+        );
+        assert_eq!(
+            shortest(&vault("ulqzkmiv")).unwrap().to_string(),
+            "DRURDRUDDLLDLUURRDULRLDUUDDDRR"
+        );
+        assert!(part1("hijkl", &Context::new()).is_err());
+    }
+
+    #[test]
+    fn finds_longest_path() {
+        assert_eq!(longest(&vault("ihgpwlah")), Some(370));
+        assert_eq!(longest(&vault("kglvqrro")), Some(492));
     }
 }
@@ -1,5 +1,7 @@
-use std::fs::File;
-use std::io::Read;
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::{Param, Solver};
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 enum Tile {
@@ -19,7 +21,7 @@ impl Tile {
 struct TrapRow(Vec<Tile>);
 
 impl TrapRow {
-    fn prev_row_weights<'a>(&'a self, position: usize) -> [&'a Tile; 3] {
+    fn prev_row_weights(&self, position: usize) -> [&Tile; 3] {
         let mut result = [&Tile::Safe; 3];
         if position == 0 {
             result[0] = &Tile::Safe;
@@ -39,9 +41,7 @@ impl TrapRow {
         let mut result = vec![];
         for i in 0..self.0.len() {
             let prev = self.prev_row_weights(i);
-            let next = if prev[0].is_trap() && !prev[2].is_trap() {
-                Tile::Trap
-            } else if !prev[0].is_trap() && prev[2].is_trap() {
+            let next = if prev[0].is_trap() != prev[2].is_trap() {
                 Tile::Trap
             } else {
                 Tile::Safe
@@ -57,52 +57,125 @@ impl TrapRow {
 }
 
 impl FromStr for TrapRow {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseError::new(1, s, "row of `.` and `^`"));
+        }
         let mut result = vec![];
-        for c in s.chars() {
+        for (i, c) in s.char_indices() {
             match c {
                 '.' => result.push(Tile::Safe),
                 '^' => result.push(Tile::Trap),
-                _ => (),
+                _ => {
+                    return Err(ParseError::new(
+                        i + 1,
+                        &s[i..i + c.len_utf8()],
+                        "`.` or `^`",
+                    ))
+                }
             }
         }
         Ok(TrapRow(result))
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_18").unwrap();
-    let mut buffer = String::new();
-
-    input.read_to_string(&mut buffer).unwrap();
-
-    let row = buffer.parse::<TrapRow>().unwrap();
+fn count_safe(input: &str, ctx: &Context, rows: usize) -> Result<usize, Error> {
+    let row = ctx.parse::<TrapRow>(input)?;
 
     let mut plane = vec![row];
-    for _ in 1..40 {
+    for _ in 1..rows {
         let next = plane.last().unwrap().next_row();
         plane.push(next);
     }
 
-    let result = plane.iter().map(|r| r.count_safe()).sum::<usize>();
-    println!("Result: {}", result);
+    Ok(plane.iter().map(|r| r.count_safe()).sum::<usize>())
 }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_18").unwrap();
-    let mut buffer = String::new();
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let rows = ctx.param(
+        "rows1",
+        NonZeroUsize::new(40).unwrap(),
+        "positive number of rows",
+    )?;
+    count_safe(input, ctx, rows.get())
+}
 
-    input.read_to_string(&mut buffer).unwrap();
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let rows = ctx.param(
+        "rows2",
+        NonZeroUsize::new(400000).unwrap(),
+        "positive number of rows",
+    )?;
+    count_safe(input, ctx, rows.get())
+}
 
-    let row = buffer.parse::<TrapRow>().unwrap();
+pub struct Solution;
 
-    let mut plane = vec![row];
-    for _ in 1..400000 {
-        let next = plane.last().unwrap().next_row();
-        plane.push(next);
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        18
+    }
+    fn title(&self) -> &'static str {
+        "Like a Rogue"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "rows1",
+                help: "number of rows in part 1 (40)",
+            },
+            Param {
+                name: "rows2",
+                help: "number of rows in part 2 (400000)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(row: &TrapRow) -> String {
+        row.0
+            .iter()
+            .map(|t| if t.is_trap() { '^' } else { '.' })
+            .collect()
+    }
+
+    #[test]
+    fn parses_row() {
+        assert_eq!(render(&"..^^.".parse::<TrapRow>().unwrap()), "..^^.");
+        let e = "..^x.".parse::<TrapRow>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (4, "x"));
+    }
+
+    #[test]
+    fn derives_next_rows() {
+        let row = "..^^.".parse::<TrapRow>().unwrap().next_row();
+        assert_eq!(render(&row), ".^^^^");
+        assert_eq!(render(&row.next_row()), "^^..^");
     }
 
-    let result = plane.iter().map(|r| r.count_safe()).sum::<usize>();
-    println!("Result: {}", result);
+    #[test]
+    fn counts_safe_tiles() {
+        assert_eq!(count_safe(".^^.^.^^^^", &Context::new(), 10).unwrap(), 38);
+    }
+
+    #[test]
+    fn takes_row_counts_as_params() {
+        let ctx = Context::new().with_param("rows1", "10");
+        assert_eq!(part1(".^^.^.^^^^", &ctx).unwrap(), 38);
+        let ctx = Context::new().with_param("rows2", "3");
+        assert_eq!(part2("..^^.", &ctx).unwrap(), 6);
+        let ctx = Context::new().with_param("rows1", "-1");
+        assert!(part1("..^^.", &ctx).is_err());
+    }
 }
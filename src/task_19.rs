@@ -1,6 +1,8 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::Solver;
 use std::collections::VecDeque;
-use std::fs::File;
-use std::io::Read;
 use std::ops::Index;
 use std::str::FromStr;
 
@@ -21,9 +23,16 @@ impl Index<usize> for Game {
 }
 
 impl FromStr for Game {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let count = s.parse::<usize>().unwrap();
+        let tokens = Tokens::new(s);
+        if tokens.len() > 1 {
+            return Err(tokens.error(1, "end of line"));
+        }
+        let count = tokens.parse::<usize>(0, "number of elves")?;
+        if count == 0 {
+            return Err(tokens.error(0, "at least one elf"));
+        }
         let mid_point = count / 2;
         Ok(Game {
             first_half: (1..=mid_point).collect(),
@@ -56,15 +65,32 @@ impl Game {
         }
     }
 
-    fn play(&mut self) -> usize {
+    fn play(&mut self, ctx: &Context) -> usize {
         while self.len() > 1 {
             let first = self.pop_front().unwrap();
-            self.pop_front();
+            let second = self.pop_front().unwrap();
             self.push_back(first);
+            ctx.step(|| self.describe(first, second));
         }
         self[0]
     }
 
+    /// The steal that just happened, with the elves still in the game
+    /// while there are few enough of them to list.
+    fn describe(&self, thief: usize, victim: usize) -> String {
+        let mut s = format!(
+            "elf {} takes from elf {}, {} left",
+            thief,
+            victim,
+            self.len()
+        );
+        if self.len() <= 20 {
+            let elves = (0..self.len()).map(|i| self[i].to_string());
+            s += &format!(": {}", elves.collect::<Vec<String>>().join(" "));
+        }
+        s
+    }
+
     fn remove(&mut self, i: usize) -> Option<usize> {
         if i < self.first_half.len() {
             self.first_half.remove(i)
@@ -73,38 +99,88 @@ impl Game {
         }
     }
 
-    fn play_b(&mut self) -> usize {
+    fn play_b(&mut self, ctx: &Context) -> usize {
         while self.len() > 1 {
             let length = self.len() / 2;
-            self.remove(length);
+            let across = self.remove(length).unwrap();
             let first = self.pop_front().unwrap();
             self.push_back(first);
-            self.stabilize()
+            self.stabilize();
+            ctx.step(|| self.describe(first, across));
         }
         self[0]
     }
 }
 
-pub fn run() {
-    let mut input = File::open("input/task_19").unwrap();
-    let mut buffer = String::new();
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let mut game = ctx.parse::<Game>(input)?;
 
-    input.read_to_string(&mut buffer).unwrap();
+    Ok(game.play(ctx))
+}
 
-    let mut game = buffer.parse::<Game>().unwrap();
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let mut game = ctx.parse::<Game>(input)?;
 
-    let result = game.play();
-    println!("Result: {}", result);
+    Ok(game.play_b(ctx))
 }
 
-pub fn run_e() {
-    let mut input = File::open("input/task_19").unwrap();
-    let mut buffer = String::new();
+pub struct Solution;
 
-    input.read_to_string(&mut buffer).unwrap();
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        19
+    }
+    fn title(&self) -> &'static str {
+        "An Elephant Named Joseph"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    #[test]
+    fn parses_number_of_elves() {
+        let game = "5".parse::<Game>().unwrap();
+        assert_eq!(game.len(), 5);
+        assert_eq!((game[0], game[4]), (1, 5));
+        let e = "0".parse::<Game>().err().unwrap();
+        assert_eq!(e.column, 1);
+        assert!("five".parse::<Game>().is_err());
+    }
 
-    let mut game = buffer.parse::<Game>().unwrap();
+    #[test]
+    fn steals_from_the_left() {
+        let ctx = Context::new();
+        assert_eq!("5".parse::<Game>().unwrap().play(&ctx), 3);
+        assert_eq!("1".parse::<Game>().unwrap().play(&ctx), 1);
+    }
+
+    #[test]
+    fn steals_from_across_the_circle() {
+        assert_eq!("5".parse::<Game>().unwrap().play_b(&Context::new()), 2);
+        assert_eq!(part2("5", &Context::new()).unwrap(), 2);
+    }
 
-    let result = game.play_b();
-    println!("Result: {}", result);
+    #[test]
+    fn traces_remaining_elves() {
+        let ctx = Context::new().with_trace(Trace::collected());
+        part2("5", &ctx).unwrap();
+        assert_eq!(
+            ctx.trace().unwrap().lines(),
+            [
+                "step 1: elf 1 takes from elf 3, 4 left: 2 4 5 1",
+                "step 2: elf 2 takes from elf 5, 3 left: 4 1 2",
+                "step 3: elf 4 takes from elf 1, 2 left: 2 4",
+                "step 4: elf 2 takes from elf 4, 1 left: 2"
+            ]
+        );
+    }
 }
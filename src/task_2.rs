@@ -1,16 +1,42 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::{Direction, Grid, Point};
+use crate::solver::Solver;
 use std::str::FromStr;
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+const SQUARE: &str = "123
+456
+789";
+
+const DIAMOND: &str = "  1
+ 234
+56789
+ ABC
+  D";
+
+/// A keypad laid out as text, spaces are gaps without a key.
+struct Keypad {
+    keys: Grid<char>,
+    current: Point,
 }
 
-struct NumPad {
-    current: u8,
+impl Keypad {
+    fn new(layout: &str) -> Self {
+        let keys = Grid::from_text(layout);
+        let current = keys.points().find(|p| keys[*p] == '5').unwrap();
+        Keypad { keys, current }
+    }
+
+    fn move_to(&mut self, d: Direction) {
+        let next = self.current.step(d);
+        if self.keys.get(next).is_some_and(|k| *k != ' ') {
+            self.current = next;
+        }
+    }
+
+    fn key(&self) -> char {
+        self.keys[self.current]
+    }
 }
 
 struct CommandSequence {
@@ -18,216 +44,108 @@ struct CommandSequence {
 }
 
 impl CommandSequence {
-    fn apply<F: Movable>(&self, num_pad: &mut F) {
+    fn apply(&self, keypad: &mut Keypad) {
         for d in &self.seq {
-            num_pad.move_to(&d);
+            keypad.move_to(*d);
         }
     }
 }
 
 impl FromStr for CommandSequence {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let directions: Vec<Direction> = s
+        let directions = s
+            .trim_end()
             .chars()
-            .filter_map(|c| match c as u8 {
-                b'R' => Some(Direction::Right),
-                b'L' => Some(Direction::Left),
-                b'D' => Some(Direction::Down),
-                b'U' => Some(Direction::Up),
-                _ => None,
+            .enumerate()
+            .map(|(i, c)| {
+                Direction::from_letter(c)
+                    .ok_or_else(|| ParseError::new(i + 1, &c.to_string(), "one of `UDLR`"))
             })
-            .collect();
+            .collect::<Result<Vec<Direction>, ParseError>>()?;
         Ok(CommandSequence { seq: directions })
     }
 }
 
-trait Movable {
-    fn move_to(&mut self, d: &Direction);
-}
-
-struct CoolerNumPad {
-    current: char,
-}
+fn code(input: &str, ctx: &Context, layout: &str) -> Result<String, Error> {
+    let mut keypad = Keypad::new(layout);
 
-impl CoolerNumPad {
-    fn new() -> Self {
-        CoolerNumPad { current: '5' }
-    }
-
-    fn move_up(&mut self) {
-        self.current = match self.current {
-            '6' => '2',
-            'A' => '6',
-            '3' => '1',
-            '7' => '3',
-            'B' => '7',
-            'D' => 'B',
-            '8' => '4',
-            'C' => '8',
-            c => c,
-        };
-    }
-
-    fn move_down(&mut self) {
-        self.current = match self.current {
-            '2' => '6',
-            '6' => 'A',
-            '1' => '3',
-            '3' => '7',
-            '7' => 'B',
-            'B' => 'D',
-            '4' => '8',
-            '8' => 'C',
-            c => c,
-        };
-    }
+    let mut result = String::new();
 
-    fn move_left(&mut self) {
-        self.current = match self.current {
-            '3' => '2',
-            '4' => '3',
-            '6' => '5',
-            '7' => '6',
-            '8' => '7',
-            '9' => '8',
-            'B' => 'A',
-            'C' => 'B',
-            c => c,
-        };
+    for c in ctx.lines::<CommandSequence>(input)? {
+        c.apply(&mut keypad);
+        result.push(keypad.key());
     }
 
-    fn move_right(&mut self) {
-        self.current = match self.current {
-            '2' => '3',
-            '3' => '4',
-            '5' => '6',
-            '6' => '7',
-            '7' => '8',
-            '8' => '9',
-            'A' => 'B',
-            'B' => 'C',
-            c => c,
-        };
-    }
+    Ok(result)
 }
 
-impl Movable for CoolerNumPad {
-    fn move_to(&mut self, d: &Direction) {
-        match d {
-            Direction::Up => self.move_up(),
-            Direction::Down => self.move_down(),
-            Direction::Left => self.move_left(),
-            Direction::Right => self.move_right(),
-        }
-    }
+pub fn part1(input: &str, ctx: &Context) -> Result<u32, Error> {
+    Ok(code(input, ctx, SQUARE)?
+        .chars()
+        .fold(0, |acc, c| acc * 10 + c.to_digit(10).unwrap()))
 }
 
-impl NumPad {
-    fn new() -> Self {
-        NumPad { current: 5 }
-    }
-
-    fn move_up(&mut self) {
-        self.current = match self.current {
-            4 => 1,
-            5 => 2,
-            6 => 3,
-            7 => 4,
-            8 => 5,
-            9 => 6,
-            i => i,
-        };
-    }
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    code(input, ctx, DIAMOND)
+}
 
-    fn move_down(&mut self) {
-        self.current = match self.current {
-            1 => 4,
-            2 => 5,
-            3 => 6,
-            4 => 7,
-            5 => 8,
-            6 => 9,
-            i => i,
-        };
-    }
+pub struct Solution;
 
-    fn move_left(&mut self) {
-        self.current = match self.current {
-            2 => 1,
-            3 => 2,
-            5 => 4,
-            6 => 5,
-            8 => 7,
-            9 => 8,
-            i => i,
-        };
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        2
     }
-
-    fn move_right(&mut self) {
-        self.current = match self.current {
-            1 => 2,
-            2 => 3,
-            4 => 5,
-            5 => 6,
-            7 => 8,
-            8 => 9,
-            i => i,
-        };
+    fn title(&self) -> &'static str {
+        "Bathroom Security"
     }
-}
-
-impl Movable for NumPad {
-    fn move_to(&mut self, d: &Direction) {
-        match d {
-            Direction::Up => self.move_up(),
-            Direction::Down => self.move_down(),
-            Direction::Left => self.move_left(),
-            Direction::Right => self.move_right(),
-        }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
     }
-}
-
-pub fn run() {
-    let input = File::open("input/task_2").unwrap();
-    let input = BufReader::new(input);
-
-    let commands: Vec<CommandSequence> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|s| s.parse::<CommandSequence>().ok())
-        .collect();
-
-    let mut num_pad = NumPad::new();
-
-    let mut result = 0_u32;
-
-    for c in commands {
-        c.apply(&mut num_pad);
-        result = result * 10 + num_pad.current as u32;
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
     }
-
-    println!("Result: {}", result);
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_2").unwrap();
-    let input = BufReader::new(input);
-
-    let commands: Vec<CommandSequence> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|s| s.parse::<CommandSequence>().ok())
-        .collect();
-
-    let mut num_pad = CoolerNumPad::new();
-
-    let mut result = String::new();
-
-    for c in commands {
-        c.apply(&mut num_pad);
-        result.push(num_pad.current);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "ULL\nRRDDD\nLURDL\nUUUUD";
+
+    #[test]
+    fn parses_command_sequence() {
+        let sequence = "ULDR".parse::<CommandSequence>().unwrap();
+        assert!(matches!(
+            sequence.seq.as_slice(),
+            [
+                Direction::North,
+                Direction::West,
+                Direction::South,
+                Direction::East
+            ]
+        ));
+        let e = "UUX".parse::<CommandSequence>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (3, "X"));
+    }
+
+    #[test]
+    fn stays_on_the_keypad() {
+        let mut keypad = Keypad::new(SQUARE);
+        "ULL".parse::<CommandSequence>().unwrap().apply(&mut keypad);
+        assert_eq!(keypad.key(), '1');
+        let mut keypad = Keypad::new(DIAMOND);
+        "RRDDD"
+            .parse::<CommandSequence>()
+            .unwrap()
+            .apply(&mut keypad);
+        assert_eq!(keypad.key(), 'D');
+    }
+
+    #[test]
+    fn finds_bathroom_code() {
+        let ctx = Context::new();
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), 1985);
+        assert_eq!(part2(EXAMPLE, &ctx).unwrap(), "5DB3");
     }
-
-    println!("Result: {}", result);
 }
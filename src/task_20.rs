@@ -1,130 +1,171 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::Solver;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
 #[derive(Debug)]
 struct IpRange {
     from: u32,
     to: u32,
 }
-impl IpRange {
-    fn contains(&self, target: u32) -> bool {
-        self.from <= target && target <= self.to
+
+impl FromStr for IpRange {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = match s.find('-') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(ParseError::new(1, s, "range `from-to`")),
+        };
+        let from = from
+            .parse::<u32>()
+            .map_err(|_| ParseError::new(1, from, "lower address"))?;
+        let column = s.len() - to.len() + 1;
+        let to = to
+            .parse::<u32>()
+            .map_err(|_| ParseError::new(column, to, "upper address"))?;
+        if from > to {
+            return Err(ParseError::new(
+                column,
+                &to.to_string(),
+                "upper address not below the lower one",
+            ));
+        }
+        Ok(IpRange { from, to })
     }
 }
 
+/// The blocked ranges, sorted and merged so that none overlap or touch.
 #[derive(Debug)]
 struct IpRangeFilter {
     content: Vec<IpRange>,
 }
 
 impl IpRangeFilter {
-    fn not_contains(&self, other: u32) -> bool {
-        self.content.iter().all(|range| !range.contains(other))
-    }
-
-    fn fold(&mut self) {
-        let len = self.content.len();
-        let mut i = 0;
-        while i < self.content.len() - 1 {
-            let mut j = i + 1;
-            while j < self.content.len() {
-                let left = &self.content[i];
-                let right = &self.content[j];
-                if left.contains(right.from) && !left.contains(right.to) {
-                    self.content[i].to = self.content[j].to;
-                    self.content.remove(j);
-                } else if right.contains(left.from) && !right.contains(left.to) {
-                    self.content[i].from = self.content[j].from;
-                    self.content.remove(j);
-                } else if !left.contains(right.from) && left.contains(right.to) {
-                    self.content[i].from = self.content[j].from;
-                    self.content.remove(j);
-                } else if !right.contains(left.from) && right.contains(left.to) {
-                    self.content[i].to = self.content[j].to;
-                    self.content.remove(j);
-                } else if left.contains(right.from) && left.contains(right.to) {
-                    self.content.remove(j);
-                } else if right.contains(left.from) && right.contains(left.to) {
-                    self.content[i].from = self.content[j].from;
-                    self.content[i].to = self.content[j].to;
-                    self.content.remove(j);
-                } else {
-                    j += 1;
+    fn new(mut ranges: Vec<IpRange>) -> Self {
+        ranges.sort_unstable_by_key(|range| range.from);
+        let mut content: Vec<IpRange> = vec![];
+        for range in ranges {
+            match content.last_mut() {
+                Some(last) if range.from <= last.to.saturating_add(1) => {
+                    last.to = last.to.max(range.to)
                 }
+                _ => content.push(range),
             }
-            i += 1;
-        }
-        if len != self.content.len() {
-            self.fold();
         }
+        IpRangeFilter { content }
     }
 
-    fn append(&mut self, other: IpRange) {
-        let mut contains = false;
-        for i in 0..self.content.len() {
-            if self.content[i].contains(other.from) && self.content[i].contains(other.to) {
-                contains = true;
+    /// The addresses between the blocked ranges, lowest first, up to and
+    /// including the last address.
+    fn allowed(&self) -> Vec<RangeInclusive<u32>> {
+        let mut allowed = vec![];
+        let mut next = 0;
+        for range in &self.content {
+            if next < range.from as u64 {
+                allowed.push(next as u32..=range.from - 1);
             }
+            next = range.to as u64 + 1;
         }
-        if !contains {
-            self.content.push(other);
+        if next <= u32::MAX as u64 {
+            allowed.push(next as u32..=u32::MAX);
         }
-        self.fold();
+        allowed
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_20").unwrap();
-    let input = BufReader::new(input);
-
-    let filter = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .map(|l| {
-            l.split("-")
-                .filter_map(|s| s.parse::<u32>().ok())
-                .collect::<Vec<u32>>()
-        })
-        .map(|l| IpRange {
-            from: l[0],
-            to: l[1],
-        })
-        .fold(IpRangeFilter { content: vec![] }, |mut acc, i| {
-            acc.append(i);
-            acc
-        });
-
-    let result = (0..std::u32::MAX)
-        .find(|i| filter.not_contains(*i))
-        .unwrap();
-
-    println!("Result: {}", result);
+fn parse(input: &str, ctx: &Context) -> Result<IpRangeFilter, Error> {
+    Ok(IpRangeFilter::new(ctx.lines::<IpRange>(input)?))
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<u32, Error> {
+    let filter = parse(input, ctx)?;
+
+    filter
+        .allowed()
+        .first()
+        .map(|range| *range.start())
+        .ok_or(Error::NoAnswer("every address is blocked"))
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<u64, Error> {
+    let filter = parse(input, ctx)?;
+
+    Ok(filter
+        .allowed()
+        .iter()
+        .map(|range| *range.end() as u64 - *range.start() as u64 + 1)
+        .sum())
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_20").unwrap();
-    let input = BufReader::new(input);
-
-    let filter = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .map(|l| {
-            l.split("-")
-                .filter_map(|s| s.parse::<u32>().ok())
-                .collect::<Vec<u32>>()
-        })
-        .map(|l| IpRange {
-            from: l[0],
-            to: l[1],
-        })
-        .fold(IpRangeFilter { content: vec![] }, |mut acc, i| {
-            acc.append(i);
-            acc
-        });
-
-    let result = (0..std::u32::MAX)
-        .filter(|i| filter.not_contains(*i))
-        .count();
-
-    println!("Result: {}", result);
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        20
+    }
+    fn title(&self) -> &'static str {
+        "Firewall Rules"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "5-8\n0-2\n4-7";
+
+    #[test]
+    fn parses_range() {
+        let range = "5-8".parse::<IpRange>().unwrap();
+        assert_eq!((range.from, range.to), (5, 8));
+        let e = "5-x".parse::<IpRange>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (3, "x"));
+        let e = "8-5".parse::<IpRange>().err().unwrap();
+        assert_eq!(e.column, 3);
+        assert!("58".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn merges_overlapping_ranges() {
+        let filter = parse(EXAMPLE, &Context::new()).unwrap();
+        let ranges = filter
+            .content
+            .iter()
+            .map(|r| (r.from, r.to))
+            .collect::<Vec<(u32, u32)>>();
+        assert_eq!(ranges, [(0, 2), (4, 8)]);
+        assert_eq!(filter.allowed(), [3..=3, 9..=u32::MAX]);
+        // Ranges that only touch are merged too, and one inside another
+        // changes nothing.
+        let filter = parse("3-4\n0-2\n1-1\n5-9", &Context::new()).unwrap();
+        assert_eq!(filter.content.len(), 1);
+        assert_eq!(filter.allowed(), [10..=u32::MAX]);
+    }
+
+    #[test]
+    fn finds_lowest_allowed_address() {
+        assert_eq!(part1(EXAMPLE, &Context::new()).unwrap(), 3);
+    }
+
+    #[test]
+    fn counts_allowed_addresses() {
+        let ctx = Context::new();
+        assert_eq!(part2("0-9", &ctx).unwrap(), u32::MAX as u64 - 9);
+        assert_eq!(part2("0-4294967295", &ctx).unwrap(), 0);
+        assert!(matches!(
+            part1("0-4294967295", &ctx),
+            Err(Error::NoAnswer(_))
+        ));
+        // Only the very last address is let through.
+        let last = "0-4294967294";
+        assert_eq!(part1(last, &ctx).unwrap(), u32::MAX);
+        assert_eq!(part2(last, &ctx).unwrap(), 1);
+    }
 }
@@ -1,5 +1,8 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::{Param, Solver};
+use std::fmt::{Display, Formatter};
 use std::iter::FromIterator;
 use std::str::FromStr;
 
@@ -16,20 +19,34 @@ impl FromIterator<Command> for CommandSequence {
 }
 
 impl CommandSequence {
-    fn apply(&self, s: &str) -> String {
+    /// Whether every command can be applied to (and undone on) `s`.
+    fn fits(&self, s: &str, reversed: bool) -> bool {
+        let len = s.chars().count();
+        self.data.iter().all(|c| match c {
+            Command::SwapPosition { left, right } => *left < len && *right < len,
+            Command::ReversePositions { from, to } => from <= to && *to < len,
+            Command::MoveToPosition { from, to } => *from < len && *to < len,
+            Command::RotateWithPosition(letter) => s.contains(*letter) && (!reversed || len == 8),
+            _ => len > 0,
+        })
+    }
+
+    fn apply(&self, s: &str, ctx: &Context) -> String {
         let mut chars = s.chars().collect::<Vec<char>>();
         for d in &self.data {
             d.apply(&mut chars);
+            ctx.step(|| format!("{} -> {}", d, chars.iter().collect::<String>()));
         }
         chars.iter().collect()
     }
-    fn reverse(&self, s: &str) -> String {
+    fn reverse(&self, s: &str, ctx: &Context) -> String {
         let chars = s.chars().collect::<Vec<char>>();
         self.data
             .iter()
             .rev()
             .fold(chars, |mut acc, i| {
                 i.reverse(&mut acc);
+                ctx.step(|| format!("undo {} -> {}", i, acc.iter().collect::<String>()));
                 acc
             })
             .iter()
@@ -74,17 +91,13 @@ impl Command {
 
     fn apply(&self, c: &mut Vec<char>) {
         match self {
-            Command::SwapPosition { left, right } => {
-                let b = c[*right];
-                c[*right] = c[*left];
-                c[*left] = b;
-            }
+            Command::SwapPosition { left, right } => c.swap(*left, *right),
             Command::SwapLetter { left, right } => {
-                for i in 0..c.len() {
-                    if c[i] == *left {
-                        c[i] = *right;
-                    } else if c[i] == *right {
-                        c[i] = *left;
+                for x in c.iter_mut() {
+                    if *x == *left {
+                        *x = *right;
+                    } else if *x == *right {
+                        *x = *left;
                     }
                 }
             }
@@ -120,15 +133,7 @@ impl Command {
                 }
             }
             Command::ReversePositions { from, to } => {
-                let mut f = *from;
-                let mut t = *to;
-                while f <= t {
-                    let a = c[f];
-                    c[f] = c[t];
-                    c[t] = a;
-                    f += 1;
-                    t -= 1;
-                }
+                c[*from..=*to].reverse();
             }
             Command::MoveToPosition { from, to } => {
                 let a = c.remove(*from);
@@ -138,59 +143,225 @@ impl Command {
     }
 }
 
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let steps = |n: &usize| if *n == 1 { "step" } else { "steps" };
+        match self {
+            Command::SwapPosition { left, right } => {
+                write!(f, "swap position {} with position {}", left, right)
+            }
+            Command::SwapLetter { left, right } => {
+                write!(f, "swap letter {} with letter {}", left, right)
+            }
+            Command::RotateLeft(n) => write!(f, "rotate left {} {}", n, steps(n)),
+            Command::RotateRight(n) => write!(f, "rotate right {} {}", n, steps(n)),
+            Command::RotateWithPosition(letter) => {
+                write!(f, "rotate based on position of letter {}", letter)
+            }
+            Command::ReversePositions { from, to } => {
+                write!(f, "reverse positions {} through {}", from, to)
+            }
+            Command::MoveToPosition { from, to } => {
+                write!(f, "move position {} to position {}", from, to)
+            }
+        }
+    }
+}
+
 impl FromStr for Command {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.split(" ").collect::<Vec<&str>>();
-        match (s[0], s[1]) {
-            ("swap", "position") => Ok(Command::SwapPosition {
-                left: s[2].parse::<usize>().unwrap(),
-                right: s[5].parse::<usize>().unwrap(),
-            }),
-            ("swap", "letter") => Ok(Command::SwapLetter {
-                left: s[2].parse::<char>().unwrap(),
-                right: s[5].parse::<char>().unwrap(),
-            }),
-            ("rotate", "left") => Ok(Command::RotateLeft(s[2].parse::<usize>().unwrap())),
-            ("rotate", "right") => Ok(Command::RotateRight(s[2].parse::<usize>().unwrap())),
-            ("rotate", "based") => Ok(Command::RotateWithPosition(s[6].parse::<char>().unwrap())),
-            ("reverse", "positions") => Ok(Command::ReversePositions {
-                from: s[2].parse::<usize>().unwrap(),
-                to: s[4].parse::<usize>().unwrap(),
-            }),
-            ("move", "position") => Ok(Command::MoveToPosition {
-                from: s[2].parse::<usize>().unwrap(),
-                to: s[5].parse::<usize>().unwrap(),
-            }),
-            _ => Err(()),
-        }
+        let t = Tokens::new(s);
+        let command = match (t.get(0, "operation")?, t.get(1, "operation kind")?) {
+            ("swap", "position") => Command::SwapPosition {
+                left: t.parse::<usize>(2, "position")?,
+                right: t.parse::<usize>(5, "position")?,
+            },
+            ("swap", "letter") => Command::SwapLetter {
+                left: t.parse::<char>(2, "letter")?,
+                right: t.parse::<char>(5, "letter")?,
+            },
+            ("rotate", "left") => Command::RotateLeft(t.parse::<usize>(2, "number of steps")?),
+            ("rotate", "right") => Command::RotateRight(t.parse::<usize>(2, "number of steps")?),
+            ("rotate", "based") => Command::RotateWithPosition(t.parse::<char>(6, "letter")?),
+            ("reverse", "positions") => Command::ReversePositions {
+                from: t.parse::<usize>(2, "position")?,
+                to: t.parse::<usize>(4, "position")?,
+            },
+            ("move", "position") => Command::MoveToPosition {
+                from: t.parse::<usize>(2, "position")?,
+                to: t.parse::<usize>(5, "position")?,
+            },
+            ("swap", _) => return Err(t.error(1, "`position` or `letter`")),
+            ("rotate", _) => return Err(t.error(1, "`left`, `right` or `based`")),
+            ("reverse", _) => return Err(t.error(1, "`positions`")),
+            ("move", _) => return Err(t.error(1, "`position`")),
+            _ => return Err(t.error(0, "one of `swap`, `rotate`, `reverse`, `move`")),
+        };
+        Ok(command)
+    }
+}
+
+fn parse(input: &str, ctx: &Context) -> Result<CommandSequence, Error> {
+    Ok(ctx
+        .lines::<Command>(input)?
+        .into_iter()
+        .collect::<CommandSequence>())
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<String, Error> {
+    let password = ctx.param("password", "abcdefgh".to_string(), "password")?;
+    let commands = parse(input, ctx)?;
+    if !commands.fits(&password, false) {
+        return Err(Error::Param {
+            name: "password",
+            value: password,
+            expected: "password with every position and letter the commands use",
+        });
     }
+    Ok(commands.apply(&password, ctx))
 }
 
-pub fn run() {
-    let input = File::open("input/task_21").unwrap();
-    let input = BufReader::new(input);
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    let scrambled = ctx.param("scrambled", "fbgdceah".to_string(), "scrambled password")?;
+    let commands = parse(input, ctx)?;
+    if !commands.fits(&scrambled, true) {
+        return Err(Error::Param {
+            name: "scrambled",
+            value: scrambled,
+            expected: "eight letters including every letter the commands use",
+        });
+    }
+    Ok(commands.reverse(&scrambled, ctx))
+}
 
-    let seq = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Command>().ok())
-        .collect::<CommandSequence>();
+pub struct Solution;
 
-    let result = seq.apply("abcdefgh");
-    println!("Result: {}", result);
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        21
+    }
+    fn title(&self) -> &'static str {
+        "Scrambled Letters and Hash"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "password",
+                help: "password to scramble in part 1 (abcdefgh)",
+            },
+            Param {
+                name: "scrambled",
+                help: "scrambled password to recover in part 2 (fbgdceah)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_21").unwrap();
-    let input = BufReader::new(input);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    const EXAMPLE: &str = "swap position 4 with position 0
+swap letter d with letter b
+reverse positions 0 through 4
+rotate left 1 step
+move position 1 to position 4
+move position 3 to position 0
+rotate based on position of letter b
+rotate based on position of letter d";
+
+    fn sequence(input: &str) -> CommandSequence {
+        input
+            .lines()
+            .map(|l| l.parse::<Command>().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(
+            "swap letter d with letter b".parse::<Command>().unwrap(),
+            Command::SwapLetter {
+                left: 'd',
+                right: 'b'
+            }
+        ));
+        assert!(matches!(
+            "rotate based on position of letter b"
+                .parse::<Command>()
+                .unwrap(),
+            Command::RotateWithPosition('b')
+        ));
+        let e = "rotate up 1 step".parse::<Command>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (8, "up"));
+        let e = "move position 1 to".parse::<Command>().err().unwrap();
+        assert_eq!(e.column, 19);
+    }
+
+    #[test]
+    fn scrambles_step_by_step() {
+        let expected = [
+            "ebcda", "edcba", "abcde", "bcdea", "bdeac", "abdec", "ecabd", "decab",
+        ];
+        let mut chars = "abcde".chars().collect::<Vec<char>>();
+        for (command, expected) in sequence(EXAMPLE).data.iter().zip(expected) {
+            command.apply(&mut chars);
+            assert_eq!(chars.iter().collect::<String>(), expected);
+        }
+    }
+
+    #[test]
+    fn unscrambles_eight_letter_passwords() {
+        let commands = sequence(EXAMPLE);
+        let ctx = Context::new();
+        for password in ["abcdefgh", "hgfedcba", "bfheacgd"] {
+            let scrambled = commands.apply(password, &ctx);
+            assert_eq!(commands.reverse(&scrambled, &ctx), password);
+        }
+    }
 
-    let seq = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Command>().ok())
-        .collect::<CommandSequence>();
+    #[test]
+    fn takes_passwords_as_params() {
+        let ctx = Context::new().with_param("password", "abcde");
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), "decab");
+        let ctx = Context::new().with_param("password", "abc");
+        assert!(part1(EXAMPLE, &ctx).is_err());
+        let ctx = Context::new().with_param("scrambled", "decab");
+        assert!(part2(EXAMPLE, &ctx).is_err());
+        let ctx = Context::new().with_param("scrambled", "hgfedcba");
+        assert_eq!(
+            part1(
+                EXAMPLE,
+                &Context::new().with_param("password", &part2(EXAMPLE, &ctx).unwrap())
+            )
+            .unwrap(),
+            "hgfedcba"
+        );
+    }
 
-    let result = seq.reverse("fbgdceah");
-    println!("Result: {}", result);
+    #[test]
+    fn traces_scrambled_password() {
+        let ctx = Context::new()
+            .with_param("password", "abcde")
+            .with_trace(Trace::collected().with_limit(2).with_filter("rotate"));
+        part1(EXAMPLE, &ctx).unwrap();
+        assert_eq!(
+            ctx.trace().unwrap().lines(),
+            [
+                "step 4: rotate left 1 step -> bcdea",
+                "step 7: rotate based on position of letter b -> ecabd"
+            ]
+        );
+        for line in EXAMPLE.lines() {
+            assert_eq!(line.parse::<Command>().unwrap().to_string(), line);
+        }
+    }
 }
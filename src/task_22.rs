@@ -1,24 +1,17 @@
-use objects::Nodes;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::Error;
+use crate::grid::{Grid, Point};
+use crate::search::{self, SearchState};
+use crate::solver::Solver;
+use objects::{Line, Nodes};
 
 mod objects {
-    use regex::Regex;
-    use std::collections::HashMap;
+    use crate::error::ParseError;
+    use crate::grid::{Grid, Point, SparseGrid};
+    use crate::parse::Tokens;
     use std::fmt::{Display, Formatter};
     use std::iter::FromIterator;
-
-    #[derive(Hash, Eq, PartialEq)]
-    pub struct NodePlacement {
-        x: usize,
-        y: usize,
-    }
-
-    impl NodePlacement {
-        pub fn new(x: usize, y: usize) -> Self {
-            NodePlacement { x, y }
-        }
-    }
+    use std::str::FromStr;
 
     pub struct NodeMeta {
         used: usize,
@@ -36,65 +29,102 @@ mod objects {
             self.available
         }
     }
-    pub struct Nodes(HashMap<NodePlacement, NodeMeta>);
+    pub enum Line {
+        Header,
+        Node(Point, NodeMeta),
+    }
+
+    fn size(tokens: &Tokens, i: usize, expected: &'static str) -> Result<usize, ParseError> {
+        tokens.parse_trimmed::<usize>(i, &['T'], expected)
+    }
+
+    impl FromStr for Line {
+        type Err = ParseError;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if s.starts_with("root@") || s.starts_with("Filesystem") {
+                return Ok(Line::Header);
+            }
+            let tokens = Tokens::new(s);
+            let name = tokens.get(0, "node name")?;
+            let placement = name
+                .strip_prefix("/dev/grid/node-x")
+                .and_then(|rest| {
+                    let mut parts = rest.split("-y");
+                    let x = parts.next()?.parse::<u32>().ok()?;
+                    let y = parts.next()?.parse::<u32>().ok()?;
+                    match parts.next() {
+                        None => Some(Point::new(x as i64, y as i64)),
+                        Some(_) => None,
+                    }
+                })
+                .ok_or_else(|| tokens.error(0, "`/dev/grid/node-x<x>-y<y>`"))?;
+            size(&tokens, 1, "size in terabytes")?;
+            let used = size(&tokens, 2, "used terabytes")?;
+            let available = size(&tokens, 3, "available terabytes")?;
+            tokens.parse_trimmed::<usize>(4, &['%'], "use percentage")?;
+            if tokens.len() > 5 {
+                return Err(tokens.error(5, "end of line"));
+            }
+            Ok(Line::Node(placement, NodeMeta::new(used, available)))
+        }
+    }
+
+    pub struct Nodes(SparseGrid<NodeMeta>);
 
     impl Nodes {
-        pub fn width(&self) -> usize {
-            self.0.iter().map(|(k, _)| k).map(|k| k.x).max().unwrap() + 1
+        pub fn is_empty(&self) -> bool {
+            self.0.is_empty()
         }
-        pub fn height(&self) -> usize {
-            self.0.iter().map(|(k, _)| k).map(|k| k.y).max().unwrap() + 1
+
+        pub fn iter(&self) -> impl Iterator<Item = (Point, &NodeMeta)> {
+            self.0.iter()
         }
 
-        pub fn get(&self, x: usize, y: usize) -> &NodeMeta {
-            &self.0[&NodePlacement::new(x, y)]
+        pub fn empty(&self) -> Option<(Point, &NodeMeta)> {
+            self.0.iter().find(|(_, v)| v.used == 0)
         }
 
-        pub fn empty(&self) -> (&NodePlacement, &NodeMeta) {
-            self.0
-                .iter()
-                .find_map(|(k, v)| if v.used == 0 { Some((k, v)) } else { None })
-                .unwrap()
+        /// The node at the top right, whose data is wanted.
+        pub fn wanted(&self) -> Point {
+            let (_, max) = self.0.bounds().unwrap();
+            Point::new(max.x, 0)
+        }
+
+        /// The nodes holding more than fits into the empty one, or missing
+        /// from the listing. Their data never moves.
+        pub fn walls(&self) -> Grid<bool> {
+            let available = self.empty().map_or(0, |(_, meta)| meta.available);
+            let (_, max) = self.0.bounds().unwrap();
+            Grid::from_fn(max.x as usize + 1, max.y as usize + 1, |p| {
+                self.0.get(p).is_none_or(|meta| meta.used > available)
+            })
         }
     }
 
     impl Display for Nodes {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            let (empty_pos, empty_meta) = self.empty();
-
-            let mut s = String::new();
-            for i in 0..self.height() {
-                for j in 0..self.width() {
-                    if self.get(j, i).used() == 0 {
-                        s.push(' ');
-                    } else if self.get(j, i).used() > empty_meta.available {
-                        s.push('#');
-                    } else {
-                        s.push('.');
-                    }
+            let available = self.empty().map_or(0, |(_, meta)| meta.available);
+
+            let s = self.0.render('?', |meta| {
+                if meta.used() == 0 {
+                    ' '
+                } else if meta.used() > available {
+                    '#'
+                } else {
+                    '.'
                 }
-                s.push('\n');
-            }
-            writeln!(f, "{}", s)
+            });
+            write!(f, "{}", s.trim_end())
         }
     }
 
-    impl FromIterator<String> for Nodes {
-        fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
-            let pattern =
-                Regex::new(r"^/dev/grid/node-x(\d+)-y(\d+)\s+(\d+)T\s+(\d+)T\s+(\d+)T\s+\d+%$")
-                    .unwrap();
+    impl FromIterator<Line> for Nodes {
+        fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
             let values = iter
                 .into_iter()
-                .map(|s| {
-                    let caps = pattern.captures(s.as_str()).unwrap();
-                    let x = caps[1].parse::<usize>().unwrap();
-                    let y = caps[2].parse::<usize>().unwrap();
-                    let u = caps[4].parse::<usize>().unwrap();
-                    let a = caps[5].parse::<usize>().unwrap();
-                    let node_placement = NodePlacement::new(x, y);
-                    let node_meta = NodeMeta::new(u, a);
-                    (node_placement, node_meta)
+                .filter_map(|line| match line {
+                    Line::Header => None,
+                    Line::Node(placement, meta) => Some((placement, meta)),
                 })
                 .collect();
             Nodes(values)
@@ -102,47 +132,165 @@ mod objects {
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_22").unwrap();
-    let input = BufReader::new(input);
+fn parse(input: &str, ctx: &Context) -> Result<Nodes, Error> {
+    let nodes = ctx.lines::<Line>(input)?.into_iter().collect::<Nodes>();
+    if nodes.is_empty() {
+        return Err(Error::NoAnswer("the listing contains no nodes"));
+    }
+    Ok(nodes)
+}
 
-    let nodes = input
-        .lines()
-        .skip(2)
-        .filter_map(|l| l.ok())
-        .collect::<Nodes>();
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let nodes = parse(input, ctx)?;
 
     let mut result = 0;
-    let width = nodes.width();
-    let height = nodes.height();
-    let size = width * height;
-    for i in 0..size {
-        for j in 0..size {
-            let x_i = i % width;
-            let y_i = i / width;
-            let x_j = j % width;
-            let y_j = j / width;
-            if i != j
-                && nodes.get(x_i, y_i).used() != 0
-                && nodes.get(x_i, y_i).used() <= nodes.get(x_j, y_j).available()
-            {
+    for (a, from) in nodes.iter() {
+        for (b, to) in nodes.iter() {
+            if a != b && from.used() != 0 && from.used() <= to.available() {
                 result += 1;
             }
         }
     }
 
-    println!("Result: {}", result);
+    Ok(result)
+}
+
+/// Where the empty node and the wanted data are. Data only ever moves into
+/// the empty node, which swaps places with it.
+#[derive(Clone)]
+struct Cluster<'a> {
+    walls: &'a Grid<bool>,
+    empty: Point,
+    data: Point,
+}
+
+impl SearchState for Cluster<'_> {
+    type Key = (Point, Point);
+
+    fn successors(&self) -> Vec<(Self, usize)> {
+        self.walls
+            .neighbours(self.empty)
+            .filter(|p| !self.walls[*p])
+            .map(|p| {
+                let data = if p == self.data {
+                    self.empty
+                } else {
+                    self.data
+                };
+                let next = Cluster {
+                    empty: p,
+                    data,
+                    ..*self
+                };
+                (next, 1)
+            })
+            .collect()
+    }
+
+    fn is_goal(&self) -> bool {
+        self.data == Point::ORIGIN
+    }
+
+    /// The data needs a move per step to the origin, and the empty node has
+    /// to get next to it first.
+    fn heuristic(&self) -> usize {
+        match self.data.manhattan(Point::ORIGIN) {
+            0 => 0,
+            d => (d + self.empty.manhattan(self.data) - 1) as usize,
+        }
+    }
+
+    fn key(&self) -> (Point, Point) {
+        (self.empty, self.data)
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_22").unwrap();
-    let input = BufReader::new(input);
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let nodes = parse(input, ctx)?;
+    let empty = match nodes.empty() {
+        Some((empty, _)) => empty,
+        None => return Err(Error::NoAnswer("there is no empty node to move data into")),
+    };
+    ctx.step(|| nodes.to_string());
+    let walls = nodes.walls();
+    let start = Cluster {
+        walls: &walls,
+        empty,
+        data: nodes.wanted(),
+    };
+    search::astar(start)
+        .found
+        .map(|found| found.cost)
+        .ok_or(Error::NoAnswer("the data cannot be moved to the origin"))
+}
 
-    let nodes = input
-        .lines()
-        .skip(2)
-        .filter_map(|l| l.ok())
-        .collect::<Nodes>();
+pub struct Solution;
 
-    println!("{}", nodes);
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        22
+    }
+    fn title(&self) -> &'static str {
+        "Grid Computing"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "root@ebhq-gridcenter# df -h
+Filesystem            Size  Used  Avail  Use%
+/dev/grid/node-x0-y0   10T    8T     2T   80%
+/dev/grid/node-x0-y1   11T    6T     5T   54%
+/dev/grid/node-x0-y2   32T   28T     4T   87%
+/dev/grid/node-x1-y0    9T    7T     2T   77%
+/dev/grid/node-x1-y1    8T    0T     8T    0%
+/dev/grid/node-x1-y2   11T    7T     4T   63%
+/dev/grid/node-x2-y0   10T    6T     4T   60%
+/dev/grid/node-x2-y1    9T    8T     1T   88%
+/dev/grid/node-x2-y2    9T    6T     3T   66%";
+
+    #[test]
+    fn parses_listing() {
+        assert!(matches!(
+            "Filesystem Size Used Avail Use%".parse::<Line>(),
+            Ok(Line::Header)
+        ));
+        match "/dev/grid/node-x2-y1    9T    8T     1T   88%".parse::<Line>() {
+            Ok(Line::Node(_, meta)) => assert_eq!((meta.used(), meta.available()), (8, 1)),
+            _ => panic!("node expected"),
+        }
+        let e = "/dev/grid/node-x2-y1    9T    8X     1T   88%"
+            .parse::<Line>()
+            .err()
+            .unwrap();
+        assert_eq!((e.column, e.token.as_str()), (31, "8X"));
+        let nodes = parse(EXAMPLE, &Context::new()).unwrap();
+        assert_eq!(nodes.iter().count(), 9);
+        let (_, meta) = nodes.iter().find(|(p, _)| *p == Point::new(0, 2)).unwrap();
+        assert_eq!(meta.used(), 28);
+    }
+
+    #[test]
+    fn counts_viable_pairs() {
+        assert_eq!(part1(EXAMPLE, &Context::new()).unwrap(), 7);
+    }
+
+    #[test]
+    fn draws_grid() {
+        let nodes = parse(EXAMPLE, &Context::new()).unwrap();
+        assert_eq!(nodes.to_string(), "...\n. .\n#..");
+    }
+
+    #[test]
+    fn moves_data_to_the_origin() {
+        assert_eq!(part2(EXAMPLE, &Context::new()).unwrap(), 7);
+    }
 }
@@ -0,0 +1,94 @@
+use crate::assembunny::limits::Limits;
+use crate::assembunny::Program;
+use crate::context::Context;
+use crate::error::Error;
+use crate::solver::{Param, Solver};
+
+/// The safe's program is an assembunny program that toggles its own
+/// instructions, it runs with the eggs in `a`.
+fn crack(input: &str, ctx: &Context, eggs: i64) -> Result<i64, Error> {
+    let mut machine = Program::parse(input, ctx)?
+        .machine()
+        .with_limits(Limits::new().with_loop_detection())
+        .with('a', eggs)?;
+    machine.run(ctx)?;
+    Ok(machine.get('a')?)
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<i64, Error> {
+    let eggs = ctx.param("eggs1", 7, "number of eggs")?;
+    crack(input, ctx, eggs)
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<i64, Error> {
+    let eggs = ctx.param("eggs2", 12, "number of eggs")?;
+    crack(input, ctx, eggs)
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        23
+    }
+    fn title(&self) -> &'static str {
+        "Safe Cracking"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "eggs1",
+                help: "eggs put into register a in part 1 (7)",
+            },
+            Param {
+                name: "eggs2",
+                help: "eggs put into register a in part 2 (12)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    const EXAMPLE: &str = "cpy 2 a
+tgl a
+tgl a
+tgl a
+cpy 1 a
+dec a
+dec a";
+
+    /// `a = b * d` by repeated increments, then `a` more.
+    const MULTIPLY: &str = "cpy a b
+cpy a d
+cpy 0 a
+cpy b c
+inc a
+dec c
+jnz c -2
+dec d
+jnz d -5
+inc a";
+
+    #[test]
+    fn toggles_commands_and_skips_invalid_ones() {
+        assert_eq!(crack(EXAMPLE, &Context::new(), 0).unwrap(), 3);
+    }
+
+    #[test]
+    fn multiplies_in_one_step() {
+        let ctx = Context::new().with_trace(Trace::collected());
+        assert_eq!(crack(MULTIPLY, &ctx, 12).unwrap(), 145);
+        assert_eq!(ctx.trace().unwrap().lines().len(), 5);
+        assert_eq!(crack(MULTIPLY, &Context::new(), 1).unwrap(), 2);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::{Grid, Point};
+use crate::search::{self, SearchState};
+use crate::solver::Solver;
+use std::str::FromStr;
+
+/// A row of the duct map: walls, open ducts and numbered points of interest.
+struct Row(Vec<char>);
+
+impl FromStr for Row {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s
+            .char_indices()
+            .find(|(_, c)| !(*c == '#' || *c == '.' || c.is_ascii_digit()))
+        {
+            Some((i, c)) => Err(ParseError::new(
+                i + 1,
+                &c.to_string(),
+                "`#`, `.` or a digit",
+            )),
+            None => Ok(Row(s.chars().collect())),
+        }
+    }
+}
+
+struct Ducts {
+    map: Grid<char>,
+}
+
+impl Ducts {
+    fn new(rows: Vec<Row>) -> Self {
+        let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+        let map = Grid::from_fn(width, rows.len(), |p| {
+            rows[p.y as usize]
+                .0
+                .get(p.x as usize)
+                .copied()
+                .unwrap_or('#')
+        });
+        Ducts { map }
+    }
+
+    /// The points of interest, ordered by their number.
+    fn targets(&self) -> Vec<(u32, Point)> {
+        let mut targets = self
+            .map
+            .points()
+            .filter_map(|p| self.map[p].to_digit(10).map(|n| (n, p)))
+            .collect::<Vec<(u32, Point)>>();
+        targets.sort_unstable();
+        targets
+    }
+
+    /// The length of the shortest walk between every two points of interest,
+    /// `None` when there is none.
+    fn distances(&self, targets: &[(u32, Point)]) -> Vec<Vec<Option<usize>>> {
+        targets
+            .iter()
+            .map(|(_, from)| {
+                let reached = search::within(
+                    Duct {
+                        map: &self.map,
+                        at: *from,
+                    },
+                    usize::MAX,
+                );
+                targets
+                    .iter()
+                    .map(|(_, to)| {
+                        reached
+                            .iter()
+                            .find(|(d, _)| d.at == *to)
+                            .map(|(_, cost)| *cost)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A position of the robot in the ducts.
+#[derive(Clone)]
+struct Duct<'a> {
+    map: &'a Grid<char>,
+    at: Point,
+}
+
+impl SearchState for Duct<'_> {
+    type Key = Point;
+
+    fn successors(&self) -> Vec<(Self, usize)> {
+        self.map
+            .neighbours(self.at)
+            .filter(|p| self.map[*p] != '#')
+            .map(|at| (Duct { at, ..*self }, 1))
+            .collect()
+    }
+
+    fn is_goal(&self) -> bool {
+        false
+    }
+
+    fn key(&self) -> Point {
+        self.at
+    }
+}
+
+/// The shortest tour from the first point through all the others, back to
+/// the first one when `back` is set. Held–Karp: `best[visited][last]` is the
+/// shortest walk from the first point through the set `visited` ending at
+/// `last`.
+fn tour(distances: &[Vec<Option<usize>>], back: bool) -> Option<usize> {
+    let n = distances.len();
+    if n == 0 {
+        return None;
+    }
+    let full = (1 << n) - 1;
+    let mut best = vec![vec![None; n]; 1 << n];
+    best[1][0] = Some(0);
+    for visited in (1..=full).filter(|v| v & 1 == 1) {
+        for last in (0..n).filter(|l| visited & 1 << l != 0) {
+            let so_far = match best[visited][last] {
+                Some(so_far) => so_far,
+                None => continue,
+            };
+            for next in (0..n).filter(|n| visited & 1 << n == 0) {
+                if let Some(d) = distances[last][next] {
+                    let slot: &mut Option<usize> = &mut best[visited | 1 << next][next];
+                    if slot.is_none_or(|s| so_far + d < s) {
+                        *slot = Some(so_far + d);
+                    }
+                }
+            }
+        }
+    }
+    (0..n)
+        .filter_map(|last| {
+            let home = if back { distances[last][0]? } else { 0 };
+            Some(best[full][last]? + home)
+        })
+        .min()
+}
+
+fn solve(input: &str, ctx: &Context, back: bool) -> Result<usize, Error> {
+    let ducts = Ducts::new(ctx.lines::<Row>(input)?);
+    let targets = ducts.targets();
+    if targets.first().is_none_or(|(n, _)| *n != 0) {
+        return Err(Error::NoAnswer("there is no point 0 to start from"));
+    }
+    let distances = ducts.distances(&targets);
+    ctx.step(|| {
+        distances
+            .iter()
+            .zip(&targets)
+            .map(|(row, (n, _))| {
+                let row = row
+                    .iter()
+                    .map(|d| d.map_or("-".to_string(), |d| d.to_string()))
+                    .collect::<Vec<String>>();
+                format!("{}: {}", n, row.join(" "))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    });
+    tour(&distances, back).ok_or(Error::NoAnswer("some point cannot be reached"))
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    solve(input, ctx, false)
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    solve(input, ctx, true)
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        24
+    }
+    fn title(&self) -> &'static str {
+        "Air Duct Spelunking"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "###########
+#0.1.....2#
+#.#######.#
+#4.......3#
+###########";
+
+    #[test]
+    fn parses_map() {
+        let ctx = Context::new().with_strict(true);
+        let ducts = Ducts::new(ctx.lines::<Row>(EXAMPLE).unwrap());
+        let targets = ducts.targets();
+        assert_eq!(targets.len(), 5);
+        assert_eq!(targets[4], (4, Point::new(1, 3)));
+        let e = ctx.lines::<Row>("#0.x#").err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (4, "x"));
+    }
+
+    #[test]
+    fn measures_distances() {
+        let ducts = Ducts::new(Context::new().lines::<Row>(EXAMPLE).unwrap());
+        let distances = ducts.distances(&ducts.targets());
+        assert_eq!(distances[0], [Some(0), Some(2), Some(8), Some(10), Some(2)]);
+    }
+
+    #[test]
+    fn finds_shortest_tours() {
+        let ctx = Context::new();
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), 14);
+        assert_eq!(part2(EXAMPLE, &ctx).unwrap(), 20);
+        assert!(part1("#0#1#", &ctx).is_err());
+        assert!(part1("#1.2#", &ctx).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::assembunny::compile::Exit;
+use crate::assembunny::limits::Limits;
+use crate::assembunny::{Fault, Program};
+use crate::context::Context;
+use crate::error::Error;
+use crate::solver::Solver;
+use std::collections::HashMap;
+
+/// What the antenna sends for some initial `a`.
+#[derive(Debug, PartialEq)]
+enum Signal {
+    /// `0, 1, 0, 1, …` forever, the values repeated after the first ones.
+    Clock(Vec<i64>),
+    /// Everything sent until the signal went wrong or the program halted.
+    Broken(Vec<i64>),
+    /// Everything sent before the program went on without sending anything,
+    /// looping or for longer than it may.
+    Silent(Vec<i64>),
+}
+
+/// How many steps run between two checks whether to stop.
+const STEPS: u64 = 1 << 16;
+
+/// How many steps a program may run without sending a value.
+const SILENCE: u64 = 1 << 28;
+
+/// Runs the program until its output stops alternating or it provably
+/// repeats itself. The state after every output is remembered; when one comes
+/// back every output since then comes back as well, forever. Between two
+/// outputs it runs under limits of `silence` steps and loop detection, so a
+/// program that stops sending is given up on too.
+fn listen(program: &Program, a: i64, silence: u64, ctx: &Context) -> Result<Signal, Error> {
+    let limits = Limits::new().with_steps(silence).with_loop_detection();
+    let mut compiled = program.compile().with('a', a)?.with_limits(limits.clone());
+    let mut seen = HashMap::new();
+    let mut tick = STEPS;
+    loop {
+        let exit = match compiled.run(tick - compiled.steps()) {
+            Err(Fault::OutOfSteps { .. }) | Err(Fault::Loops { .. }) => {
+                return Ok(Signal::Silent(compiled.output().to_vec()))
+            }
+            exit => exit?,
+        };
+        if compiled.steps() >= tick {
+            ctx.tick(compiled.steps(), None)?;
+            tick += STEPS;
+        }
+        let value = match exit {
+            Exit::Out(value) => value,
+            Exit::Halted => return Ok(Signal::Broken(compiled.output().to_vec())),
+            Exit::Paused => continue,
+        };
+        compiled.set_limits(limits.clone());
+        let output = compiled.output();
+        if value != ((output.len() - 1) % 2) as i64 {
+            return Ok(Signal::Broken(output.to_vec()));
+        }
+        if let Some(at) = seen.insert(compiled.snapshot(), output.len()) {
+            // An odd number of values would repeat with the wrong phase.
+            return Ok(if (output.len() - at).is_multiple_of(2) {
+                Signal::Clock(output[at..].to_vec())
+            } else {
+                Signal::Broken(output.to_vec())
+            });
+        }
+    }
+}
+
+/// The lowest initial `a` that makes the program a clock.
+pub fn part1(input: &str, ctx: &Context) -> Result<i64, Error> {
+    let program = Program::parse(input, ctx)?;
+    let silence = ctx.param("silence", SILENCE, "number of steps")?;
+    for a in 0..i64::MAX {
+        let signal = listen(&program, a, silence, ctx).map_err(|e| match e {
+            Error::Stopped(s) => {
+                Error::Stopped(s.with_partial(format!("every a below {} fails", a)))
+            }
+            e => e,
+        })?;
+        ctx.step(|| format!("a={} {:?}", a, signal));
+        if let Signal::Clock(_) = signal {
+            return Ok(a);
+        }
+    }
+    Err(Error::NoAnswer("no initial value makes a clock signal"))
+}
+
+/// There is no second puzzle on the last day: its star comes with all the
+/// others.
+pub fn part2(_input: &str, _ctx: &Context) -> Result<String, Error> {
+    Ok("Merry Christmas!".to_string())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        25
+    }
+    fn title(&self) -> &'static str {
+        "Clock Signal"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends the bits of `a + 4`, lowest first, over and over.
+    const BITS: &str = "cpy a d
+inc d
+inc d
+inc d
+inc d
+cpy d a
+jnz 0 0
+cpy a b
+cpy 0 a
+cpy 2 c
+jnz b 2
+jnz 1 6
+dec b
+dec c
+jnz c -4
+inc a
+jnz 1 -7
+cpy 2 b
+jnz c 2
+jnz 1 4
+dec b
+dec c
+jnz 1 -4
+jnz 0 0
+out b
+jnz a -19
+jnz 1 -21";
+
+    #[test]
+    fn proves_the_signal_repeats() {
+        let ctx = Context::new();
+        let bits = Program::parse(BITS, &ctx).unwrap();
+        // 6 + 4 is 0b1010, sent as 0 1 0 1. The state after the first
+        // value comes back four values later.
+        assert_eq!(
+            listen(&bits, 6, SILENCE, &ctx).unwrap(),
+            Signal::Clock(vec![1, 0, 1, 0])
+        );
+        // 2 + 4 is 0b110.
+        assert_eq!(
+            listen(&bits, 2, SILENCE, &ctx).unwrap(),
+            Signal::Broken(vec![0, 1, 1])
+        );
+        // Counts down from 3 sending 0 1 every time, so its state repeats
+        // every six values, starting after the first one.
+        let sixes = Program::parse(
+            "cpy 3 c\nout 0\nout 1\ndec c\njnz c -3\ncpy 3 c\njnz 1 -5",
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            listen(&sixes, 0, SILENCE, &ctx).unwrap(),
+            Signal::Clock(vec![1, 0, 1, 0, 1, 0])
+        );
+        let halts = Program::parse("out 0\nout 1", &ctx).unwrap();
+        assert_eq!(
+            listen(&halts, 0, SILENCE, &ctx).unwrap(),
+            Signal::Broken(vec![0, 1])
+        );
+    }
+
+    #[test]
+    fn gives_up_on_silent_programs() {
+        let ctx = Context::new();
+        // Sends a 0 and then loops without sending anything.
+        let stuck = Program::parse("out 0\njnz 1 0", &ctx).unwrap();
+        assert_eq!(
+            listen(&stuck, 0, SILENCE, &ctx).unwrap(),
+            Signal::Silent(vec![0])
+        );
+        // Counts up forever, never in the same state twice.
+        let counts = Program::parse("out 0\ninc b\njnz 1 -1", &ctx).unwrap();
+        assert_eq!(
+            listen(&counts, 0, 1000, &ctx).unwrap(),
+            Signal::Silent(vec![0])
+        );
+    }
+
+    #[test]
+    fn finds_lowest_clock() {
+        let ctx = Context::new();
+        assert_eq!(part1(BITS, &ctx).unwrap(), 6);
+        // With a at zero it loops before sending anything.
+        assert_eq!(
+            part1("jnz a 2\njnz 1 0\nout 0\nout 1\njnz 1 -2", &ctx).unwrap(),
+            1
+        );
+    }
+}
@@ -1,5 +1,7 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::parse::Tokens;
+use crate::solver::Solver;
 use std::str::FromStr;
 
 struct Triangle {
@@ -10,8 +12,8 @@ struct Triangle {
 
 impl Triangle {
     fn new_from_num(i1: u16, i2: u16, i3: u16) -> Result<Self, ()> {
-        let mut v = vec![i1, i2, i3];
-        v.sort();
+        let mut v = [i1, i2, i3];
+        v.sort_unstable();
 
         if v[2] >= v[0] + v[1] {
             Err(())
@@ -25,62 +27,39 @@ impl Triangle {
     }
 }
 
-impl FromStr for Triangle {
-    type Err = ();
+struct Sides([u16; 3]);
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s1 = s[0..5].trim().parse::<u16>();
-        let s2 = s[5..10].trim().parse::<u16>();
-        let s3 = s[10..].trim().parse::<u16>();
-
-        if s1.is_err() || s2.is_err() || s3.is_err() {
-            Err(())
-        } else {
-            let s1 = s1.unwrap();
-            let s2 = s2.unwrap();
-            let s3 = s3.unwrap();
+impl FromStr for Sides {
+    type Err = ParseError;
 
-            Triangle::new_from_num(s1, s2, s3)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = Tokens::new(s);
+        if tokens.len() > 3 {
+            return Err(tokens.error(3, "end of line"));
         }
+        Ok(Sides([
+            tokens.parse::<u16>(0, "side length")?,
+            tokens.parse::<u16>(1, "side length")?,
+            tokens.parse::<u16>(2, "side length")?,
+        ]))
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_3").unwrap();
-    let input = BufReader::new(input);
-
-    let commands: Vec<Triangle> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|s| s.parse::<Triangle>().ok())
-        .collect();
-
-    println!("Result: {}", commands.len());
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    Ok(ctx
+        .lines::<Sides>(input)?
+        .into_iter()
+        .filter_map(|Sides([s1, s2, s3])| Triangle::new_from_num(s1, s2, s3).ok())
+        .count())
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_3").unwrap();
-    let input = BufReader::new(input);
-
-    let commands: Vec<Vec<u16>> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .map(|s| {
-            vec![
-                s[0..5].trim().parse::<u16>().unwrap(),
-                s[5..10].trim().parse::<u16>().unwrap(),
-                s[10..15].trim().parse::<u16>().unwrap(),
-            ]
-        })
-        .collect();
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let commands = ctx.lines::<Sides>(input)?;
 
     let mut result = vec![];
 
-    for i in 0..commands.len() / 3 {
-        let p = i * 3;
-        let l1 = &commands[p];
-        let l2 = &commands[p + 1];
-        let l3 = &commands[p + 2];
+    for l in commands.chunks_exact(3) {
+        let (l1, l2, l3) = (&l[0].0, &l[1].0, &l[2].0);
 
         if let Ok(t) = Triangle::new_from_num(l1[0], l2[0], l3[0]) {
             result.push(t);
@@ -95,5 +74,52 @@ pub fn run_e() {
         }
     }
 
-    println!("Result: {}", result.len());
+    Ok(result.len())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        3
+    }
+    fn title(&self) -> &'static str {
+        "Squares With Three Sides"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sides() {
+        let Sides(sides) = "  5  10   25".parse::<Sides>().unwrap();
+        assert_eq!(sides, [5, 10, 25]);
+        let e = "5 10".parse::<Sides>().err().unwrap();
+        assert_eq!(e.column, 5);
+        let e = "5 10 25 1".parse::<Sides>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (9, "1"));
+    }
+
+    #[test]
+    fn rejects_impossible_triangles() {
+        assert!(Triangle::new_from_num(5, 10, 25).is_err());
+        assert!(Triangle::new_from_num(3, 4, 5).is_ok());
+        assert!(Triangle::new_from_num(1, 2, 3).is_err());
+    }
+
+    #[test]
+    fn counts_triangles_by_rows_and_columns() {
+        let input = "101 301 501\n102 302 502\n103 303 503\n201 401 601\n202 402 602\n203 403 603";
+        let ctx = Context::new();
+        assert_eq!(part1("5 10 25\n3 4 5", &ctx).unwrap(), 1);
+        assert_eq!(part2(input, &ctx).unwrap(), 6);
+    }
 }
@@ -1,16 +1,23 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::Solver;
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
 struct Room {
     name: String,
     number: u16,
+    given_checksum: String,
 }
 
 impl Room {
-    fn new(name: String, number: u16) -> Self {
-        Room { name, number }
+    fn new(name: String, number: u16, given_checksum: String) -> Self {
+        Room {
+            name,
+            number,
+            given_checksum,
+        }
     }
 
     fn checksum(&self) -> String {
@@ -72,74 +79,124 @@ impl Room {
                 }
             };
         }
-        return result;
+        result
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_4").unwrap();
-    let input = BufReader::new(input);
-
-    let rooms: Vec<Room> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|s| {
-            let s: Vec<&str> = s.split("[").collect();
-            let cs_l = s[1].len();
-            let checksum = &s[1][..cs_l - 1];
-            let mut name_and_number: Vec<&str> = s[0].split("-").collect();
-            let number = name_and_number.pop().unwrap().parse::<u16>().unwrap();
-            let name = name_and_number.join("-");
-
-            let room = Room::new(name, number);
-            if room.checksum() == checksum {
-                Some(room)
-            } else {
-                None
-            }
-        })
-        .collect();
+impl FromStr for Room {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let open = s
+            .find('[')
+            .ok_or_else(|| ParseError::new(s.len() + 1, "", "`[`"))?;
+        let checksum = s[open + 1..]
+            .strip_suffix(']')
+            .ok_or_else(|| ParseError::new(s.len() + 1, "", "`]`"))?;
+        if checksum.len() != 5 || !checksum.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(ParseError::new(open + 2, checksum, "five letter checksum"));
+        }
+        let (name, number) = s[..open]
+            .rsplit_once('-')
+            .ok_or_else(|| ParseError::new(1, &s[..open], "room name and sector id"))?;
+        if let Some(i) = name.find(|c: char| !c.is_ascii_lowercase() && c != '-') {
+            return Err(ParseError::new(
+                i + 1,
+                &name[i..=i],
+                "lowercase letter or `-`",
+            ));
+        }
+        let number = number
+            .parse::<u16>()
+            .map_err(|_| ParseError::new(name.len() + 2, number, "sector id"))?;
 
-    let result = rooms.iter().map(|r| r.number as u32).sum::<u32>();
+        Ok(Room::new(name.to_string(), number, checksum.to_string()))
+    }
+}
 
-    println!("Result: {}", result);
+fn real_rooms(input: &str, ctx: &Context) -> Result<Vec<Room>, Error> {
+    Ok(ctx
+        .lines::<Room>(input)?
+        .into_iter()
+        .filter(|room| room.checksum() == room.given_checksum)
+        .collect())
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_4").unwrap();
-    let input = BufReader::new(input);
-
-    let rooms: Vec<Room> = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|s| {
-            let s: Vec<&str> = s.split("[").collect();
-            let cs_l = s[1].len();
-            let checksum = &s[1][..cs_l - 1];
-            let mut name_and_number: Vec<&str> = s[0].split("-").collect();
-            let number = name_and_number.pop().unwrap().parse::<u16>().unwrap();
-            let name = name_and_number.join("-");
-
-            let room = Room::new(name, number);
-            if room.checksum() == checksum {
-                Some(room)
-            } else {
-                None
-            }
-        })
-        .collect();
+pub fn part1(input: &str, ctx: &Context) -> Result<u32, Error> {
+    Ok(real_rooms(input, ctx)?
+        .iter()
+        .map(|r| r.number as u32)
+        .sum::<u32>())
+}
 
-    let result = rooms
+pub fn part2(input: &str, ctx: &Context) -> Result<u16, Error> {
+    real_rooms(input, ctx)?
         .iter()
-        .filter_map(|r| {
+        .find_map(|r| {
             if r.decrypt() == "northpole object storage" {
                 Some(r.number)
             } else {
                 None
             }
         })
-        .next()
-        .unwrap();
+        .ok_or(Error::NoAnswer("no room stores northpole objects"))
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        4
+    }
+    fn title(&self) -> &'static str {
+        "Security Through Obscurity"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
 
-    println!("Result: {}", result);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "aaaaa-bbb-z-y-x-123[abxyz]
+a-b-c-d-e-f-g-h-987[abcde]
+not-a-real-room-404[oarel]
+totally-real-room-200[decoy]";
+
+    #[test]
+    fn parses_room() {
+        let room = "aaaaa-bbb-z-y-x-123[abxyz]".parse::<Room>().unwrap();
+        assert_eq!(room.name, "aaaaa-bbb-z-y-x");
+        assert_eq!(room.number, 123);
+        assert_eq!(room.given_checksum, "abxyz");
+        let e = "aaaaa-bbb-12x[abxyz]".parse::<Room>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (11, "12x"));
+        let e = "aaaaa-bbb-123[abx]".parse::<Room>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (15, "abx"));
+    }
+
+    #[test]
+    fn computes_checksum() {
+        let checksums = EXAMPLE
+            .lines()
+            .map(|l| l.parse::<Room>().unwrap().checksum())
+            .collect::<Vec<String>>();
+        assert_eq!(checksums, ["abxyz", "abcde", "oarel", "loart"]);
+    }
+
+    #[test]
+    fn decrypts_name() {
+        let room = "qzmt-zixmtkozy-ivhz-343[zimth]".parse::<Room>().unwrap();
+        assert_eq!(room.decrypt(), "very encrypted name");
+    }
+
+    #[test]
+    fn sums_sector_ids_of_real_rooms() {
+        assert_eq!(part1(EXAMPLE, &Context::new()).unwrap(), 1514);
+    }
 }
@@ -1,6 +1,8 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::mining;
+use crate::progress::Stopped;
+use crate::solver::Solver;
 use std::str::FromStr;
 
 struct DoorId {
@@ -14,85 +16,125 @@ impl DoorId {
         }
     }
 
-    fn iter(&self) -> DoorIdIterator {
-        DoorIdIterator {
-            door_id: self,
-            step: 0,
-        }
+    /// The sixth and seventh hex digit of every interesting hash, in order.
+    fn interesting<'a>(
+        &'a self,
+        ctx: &'a Context,
+    ) -> impl Iterator<Item = Result<(u8, u8), Stopped>> + 'a {
+        mining::mine(self.value.as_bytes(), ctx, |d| {
+            if d.leading_zeros() >= 5 {
+                Some((d.nibble(5), d.nibble(6)))
+            } else {
+                None
+            }
+        })
+        .map(|found| found.map(|(_, nibbles)| nibbles))
     }
 }
 
+fn hex(nibble: u8) -> char {
+    std::char::from_digit(nibble as u32, 16).unwrap()
+}
+
+/// The password as far as it is known, `_` for the characters still missing.
+fn partial(password: &[Option<char>; 8]) -> String {
+    password.iter().map(|c| c.unwrap_or('_')).collect()
+}
+
 impl FromStr for DoorId {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(DoorId::new(s))
+        match s.find(|c: char| !c.is_ascii_alphanumeric()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "alphanumeric door id")),
+            None if s.is_empty() => Err(ParseError::new(1, s, "door id")),
+            None => Ok(DoorId::new(s)),
+        }
     }
 }
 
-struct DoorIdIterator<'a> {
-    door_id: &'a DoorId,
-    step: usize,
+pub fn part1(input: &str, ctx: &Context) -> Result<String, Error> {
+    let door = ctx.parse::<DoorId>(input)?;
+    let mut password = String::new();
+    for found in door.interesting(ctx).take(8) {
+        let (sixth, _) = found.map_err(|s| s.with_partial(format!("{:_<8}", password)))?;
+        password.push(hex(sixth));
+    }
+    Ok(password)
 }
 
-impl<'a> Iterator for DoorIdIterator<'a> {
-    type Item = String;
-    fn next(&mut self) -> Option<Self::Item> {
-        let digest = format!("{}{}", self.door_id.value, self.step);
-        let digest = md5::compute(digest);
-        self.step += 1;
-
-        Some(format!("{:x}", digest))
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    let door = ctx.parse::<DoorId>(input)?;
+    let mut password = [None; 8];
+    for found in door.interesting(ctx) {
+        let (position, c) = found.map_err(|s| s.with_partial(partial(&password)))?;
+        if let Some(slot @ None) = password.get_mut(position as usize) {
+            *slot = Some(hex(c));
+            if password.iter().all(|c| c.is_some()) {
+                break;
+            }
+        }
     }
+    Ok(password.iter().flatten().collect::<String>())
 }
 
-pub fn run() {
-    let input = File::open("input/task_5").unwrap();
-    let mut input = BufReader::new(input);
-    let mut door_id = String::new();
-    input.read_line(&mut door_id).unwrap();
-
-    let door = door_id.parse::<DoorId>().unwrap();
-    let result = door
-        .iter()
-        .filter(|hash| hash.starts_with("00000"))
-        .map(|s| s.chars().nth(5).unwrap())
-        .take(8)
-        .collect::<String>();
-
-    println!("Result: {}", result);
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        5
+    }
+    fn title(&self) -> &'static str {
+        "How About a Nice Game of Chess?"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_5").unwrap();
-    let mut input = BufReader::new(input);
-    let mut door_id = String::new();
-    input.read_line(&mut door_id).unwrap();
-
-    let door = door_id.parse::<DoorId>().unwrap();
-    let mut password = HashMap::new();
-    let _result = door
-        .iter()
-        .filter(|hash| hash.starts_with("00000"))
-        .filter_map(|hash| {
-            let index = hash.chars().nth(5).unwrap().to_digit(10);
-            if let Some(index) = index {
-                if 8 > index && !password.contains_key(&index) {
-                    password.insert(index, hash.chars().nth(6).unwrap());
-                    Some(index)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .take(8)
-        .collect::<Vec<u32>>();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+    use std::time::Duration;
 
-    let mut password = password.into_iter().collect::<Vec<(u32, char)>>();
-    password.sort_by(|a, b| a.0.cmp(&b.0));
-    let password = password.iter().map(|(_, v)| v).collect::<String>();
+    #[test]
+    fn parses_door_id() {
+        assert_eq!("abc".parse::<DoorId>().unwrap().value, "abc");
+        let e = "ab c".parse::<DoorId>().err().unwrap();
+        assert_eq!(e.column, 3);
+    }
 
-    println!("Result: {}", password);
+    #[test]
+    fn finds_interesting_hashes() {
+        let door = DoorId::new("abc");
+        let ctx = Context::new().with_threads(2);
+        let first = door
+            .interesting(&ctx)
+            .take(3)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(first.unwrap(), [(0x1, 0x5), (0x8, 0xf), (0xf, 0x9)]);
+    }
+
+    #[test]
+    fn reports_partial_password_when_out_of_time() {
+        let ctx = Context::new().with_progress(Progress::new().with_budget(Duration::ZERO));
+        match part2("abc", &ctx) {
+            Err(Error::Stopped(stopped)) => {
+                assert_eq!(stopped.partial.unwrap(), "________")
+            }
+            other => panic!("expected to run out of time, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn finds_passwords() {
+        let ctx = Context::new();
+        assert_eq!(part1("abc", &ctx).unwrap(), "18f47a30");
+        assert_eq!(part2("abc", &ctx).unwrap(), "05ace8e3");
+    }
 }
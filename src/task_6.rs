@@ -1,5 +1,7 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::Solver;
+use std::str::FromStr;
 
 struct CharCounter {
     counts: Vec<usize>,
@@ -25,9 +27,9 @@ impl CharCounter {
             .counts
             .iter()
             .enumerate()
-            .max_by(|a, b| a.1.cmp(&b.1))
+            .max_by(|a, b| a.1.cmp(b.1))
             .unwrap();
-        ('a' as u8 + max as u8) as char
+        (b'a' + max as u8) as char
     }
 
     fn least_frequent(&self) -> char {
@@ -36,58 +38,108 @@ impl CharCounter {
             .iter()
             .enumerate()
             .filter(|(_, c)| **c != 0)
-            .min_by(|a, b| a.1.cmp(&b.1))
+            .min_by(|a, b| a.1.cmp(b.1))
             .unwrap();
-        ('a' as u8 + min as u8) as char
+        (b'a' + min as u8) as char
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_6").unwrap();
-    let input = BufReader::new(input);
-
-    let result = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .fold(vec![], |mut acc, l| {
-            if acc.is_empty() {
-                for _ in 0..l.len() {
-                    acc.push(CharCounter::new());
-                }
-            }
-            l.chars().enumerate().for_each(|(i, c)| {
-                acc[i].inc(c);
-            });
-            acc
-        })
+struct Message(String);
+
+impl FromStr for Message {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find(|c: char| !c.is_ascii_lowercase()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "lowercase letter")),
+            None => Ok(Message(s.to_string())),
+        }
+    }
+}
+
+fn count(input: &str, ctx: &Context) -> Result<Vec<CharCounter>, Error> {
+    let messages = ctx.lines::<Message>(input)?;
+    Ok(messages.iter().fold(vec![], |mut acc, Message(l)| {
+        while acc.len() < l.len() {
+            acc.push(CharCounter::new());
+        }
+        l.chars().enumerate().for_each(|(i, c)| {
+            acc[i].inc(c);
+        });
+        acc
+    }))
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<String, Error> {
+    Ok(count(input, ctx)?
         .iter()
         .map(|c| c.most_frequent())
-        .collect::<String>();
-
-    println!("Result: {}", result);
+        .collect::<String>())
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_6").unwrap();
-    let input = BufReader::new(input);
-
-    let result = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .fold(vec![], |mut acc, l| {
-            if acc.is_empty() {
-                for _ in 0..l.len() {
-                    acc.push(CharCounter::new());
-                }
-            }
-            l.chars().enumerate().for_each(|(i, c)| {
-                acc[i].inc(c);
-            });
-            acc
-        })
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    Ok(count(input, ctx)?
         .iter()
         .map(|c| c.least_frequent())
-        .collect::<String>();
+        .collect::<String>())
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        6
+    }
+    fn title(&self) -> &'static str {
+        "Signals and Noise"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Result: {}", result);
+    const EXAMPLE: &str = "eedadn
+drvtee
+eandsr
+raavrd
+atevrs
+tsrnev
+sdttsa
+rasrtv
+nssdts
+ntnada
+svetve
+tesnvt
+vntsnd
+vrdear
+dvrsen
+enarar";
+
+    #[test]
+    fn parses_message() {
+        assert_eq!("eedadn".parse::<Message>().unwrap().0, "eedadn");
+        let e = "eeDadn".parse::<Message>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (3, "D"));
+    }
+
+    #[test]
+    fn counts_characters() {
+        let mut counter = CharCounter::new();
+        "abbbcc".chars().for_each(|c| counter.inc(c));
+        assert_eq!(counter.most_frequent(), 'b');
+        assert_eq!(counter.least_frequent(), 'a');
+    }
+
+    #[test]
+    fn corrects_message() {
+        let ctx = Context::new();
+        assert_eq!(part1(EXAMPLE, &ctx).unwrap(), "easter");
+        assert_eq!(part2(EXAMPLE, &ctx).unwrap(), "advent");
+    }
 }
@@ -1,6 +1,7 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::Solver;
 use std::fmt::{Debug, Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
 trait AbbaValid {
@@ -17,7 +18,7 @@ trait ByteAllocationBlockGenerator {
 }
 
 trait ByteAllocationConsumer {
-    fn validate_byte_allocation_block(&self, blocks: &Vec<ByteAllocationBlock>) -> bool;
+    fn validate_byte_allocation_block(&self, blocks: &[ByteAllocationBlock]) -> bool;
 }
 
 #[derive(Debug)]
@@ -35,7 +36,7 @@ impl ByteAllocationBlockGenerator for AddressPart {
     fn get_byte_allocation_blocks(&self) -> Vec<ByteAllocationBlock> {
         let mut result = vec![];
         if self.value.len() >= 3 {
-            let s = self.value.as_str().as_bytes();
+            let s = self.value.as_bytes();
             for i in 1..s.len() - 1 {
                 if s[i - 1] == s[i + 1] && s[i] != s[i - 1] {
                     result.push(ByteAllocationBlock {
@@ -55,10 +56,10 @@ struct HypernetSequence {
 }
 
 impl ByteAllocationConsumer for HypernetSequence {
-    fn validate_byte_allocation_block(&self, blocks: &Vec<ByteAllocationBlock>) -> bool {
+    fn validate_byte_allocation_block(&self, blocks: &[ByteAllocationBlock]) -> bool {
         let mut result = false;
         if self.value.len() >= 3 {
-            let s = self.value.as_str().as_bytes();
+            let s = self.value.as_bytes();
             for i in 1..s.len() - 1 {
                 if s[i - 1] == s[i + 1]
                     && s[i] != s[i - 1]
@@ -81,11 +82,14 @@ impl Display for HypernetSequence {
 }
 
 impl FromStr for AddressPart {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(AddressPart {
-            value: s.to_string(),
-        })
+        match s.find(|c: char| !c.is_ascii_lowercase()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "lowercase letter")),
+            None => Ok(AddressPart {
+                value: s.to_string(),
+            }),
+        }
     }
 }
 
@@ -99,7 +103,7 @@ impl AbbaValid for AddressPart {
             false
         } else {
             let mut result = false;
-            let s = self.value.as_str().as_bytes();
+            let s = self.value.as_bytes();
             for i in 1..s.len() - 2 {
                 if s[i - 1] == s[i + 2] && s[i] == s[i + 1] && s[i] != s[i - 1] {
                     result = true;
@@ -111,11 +115,14 @@ impl AbbaValid for AddressPart {
 }
 
 impl FromStr for HypernetSequence {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(HypernetSequence {
-            value: s.to_string(),
-        })
+        match s.find(|c: char| !c.is_ascii_lowercase()) {
+            Some(i) => Err(ParseError::new(i + 1, &s[i..=i], "lowercase letter")),
+            None => Ok(HypernetSequence {
+                value: s.to_string(),
+            }),
+        }
     }
 }
 
@@ -125,7 +132,7 @@ impl AbbaValid for HypernetSequence {
             true
         } else {
             let mut result = true;
-            let s = self.value.as_str().as_bytes();
+            let s = self.value.as_bytes();
             for i in 1..s.len() - 2 {
                 if s[i - 1] == s[i + 2] && s[i] == s[i + 1] {
                     result = false;
@@ -164,24 +171,32 @@ impl<A: ByteAllocationBlockGenerator, B: ByteAllocationConsumer> BabValid for Ad
     }
 }
 
-impl<A: FromStr, B: FromStr> FromStr for Address<A, B> {
-    type Err = ();
+impl<A, B> FromStr for Address<A, B>
+where
+    A: FromStr<Err = ParseError>,
+    B: FromStr<Err = ParseError>,
+{
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (parts, hypernets) =
-            s.split(|c| c == '[' || c == ']')
-                .fold((vec![], vec![]), |mut acc, p| {
-                    if acc.0.len() == acc.1.len() {
-                        acc.0.push(p);
-                    } else {
-                        acc.1.push(p);
-                    }
-                    acc
-                });
-        let parts = parts.iter().filter_map(|p| p.parse::<A>().ok()).collect();
-        let hypernets = hypernets
-            .iter()
-            .filter_map(|p| p.parse::<B>().ok())
-            .collect();
+        let mut parts = vec![];
+        let mut hypernets = vec![];
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            match (c, parts.len() == hypernets.len()) {
+                ('[', true) => parts.push(s[start..i].parse::<A>().map_err(|e| e.shifted(start))?),
+                (']', false) => {
+                    hypernets.push(s[start..i].parse::<B>().map_err(|e| e.shifted(start))?)
+                }
+                ('[', false) => return Err(ParseError::new(i + 1, "[", "`]`")),
+                (']', true) => return Err(ParseError::new(i + 1, "]", "`[`")),
+                _ => continue,
+            }
+            start = i + 1;
+        }
+        if parts.len() != hypernets.len() {
+            return Err(ParseError::new(s.len() + 1, "", "`]`"));
+        }
+        parts.push(s[start..].parse::<A>().map_err(|e| e.shifted(start))?);
         Ok(Address {
             address: parts,
             hypernet_seq: hypernets,
@@ -189,30 +204,85 @@ impl<A: FromStr, B: FromStr> FromStr for Address<A, B> {
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_7").unwrap();
-    let input = BufReader::new(input);
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    Ok(ctx
+        .lines::<Address<AddressPart, HypernetSequence>>(input)?
+        .iter()
+        .filter(|a| AbbaValid::is_valid(*a))
+        .count())
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    Ok(ctx
+        .lines::<Address<AddressPart, HypernetSequence>>(input)?
+        .iter()
+        .filter(|a| BabValid::is_valid(*a))
+        .count())
+}
 
-    let result = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Address<AddressPart, HypernetSequence>>().ok())
-        .filter(|a| AbbaValid::is_valid(a))
-        .collect::<Vec<_>>();
+pub struct Solution;
 
-    println!("Result: {}", result.len());
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        7
+    }
+    fn title(&self) -> &'static str {
+        "Internet Protocol Version 7"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_7").unwrap();
-    let input = BufReader::new(input);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Ip = Address<AddressPart, HypernetSequence>;
+
+    fn ip(s: &str) -> Ip {
+        s.parse::<Ip>().unwrap()
+    }
+
+    #[test]
+    fn parses_address() {
+        let address = ip("abba[mnop]qrst[xyyx]ab");
+        assert_eq!(address.address.len(), 3);
+        assert_eq!(address.hypernet_seq.len(), 2);
+        assert_eq!(address.hypernet_seq[1].to_string(), "xyyx");
+        let e = "abba[mn[op]qrst".parse::<Ip>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (8, "["));
+        let e = "abba[mnop".parse::<Ip>().err().unwrap();
+        assert_eq!(e.column, 10);
+        let e = "abba[mnop]qR".parse::<Ip>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (12, "R"));
+    }
+
+    #[test]
+    fn supports_tls() {
+        assert!(AbbaValid::is_valid(&ip("abba[mnop]qrst")));
+        assert!(!AbbaValid::is_valid(&ip("abcd[bddb]xyyx")));
+        assert!(!AbbaValid::is_valid(&ip("aaaa[qwer]tyui")));
+        assert!(AbbaValid::is_valid(&ip("ioxxoj[asdfgh]zxcvbn")));
+    }
 
-    let result = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Address<AddressPart, HypernetSequence>>().ok())
-        .filter(|a| BabValid::is_valid(a))
-        .collect::<Vec<_>>();
+    #[test]
+    fn supports_ssl() {
+        assert!(BabValid::is_valid(&ip("aba[bab]xyz")));
+        assert!(!BabValid::is_valid(&ip("xyx[xyx]xyx")));
+        assert!(BabValid::is_valid(&ip("aaa[kek]eke")));
+        assert!(BabValid::is_valid(&ip("zazbz[bzb]cdb")));
+    }
 
-    println!("Result: {}", result.len());
+    #[test]
+    fn counts_addresses() {
+        let ctx = Context::new();
+        let input = "abba[mnop]qrst\nabcd[bddb]xyyx\naaaa[qwer]tyui\nioxxoj[asdfgh]zxcvbn";
+        assert_eq!(part1(input, &ctx).unwrap(), 2);
+        let input = "aba[bab]xyz\nxyx[xyx]xyx\naaa[kek]eke\nzazbz[bzb]cdb";
+        assert_eq!(part2(input, &ctx).unwrap(), 3);
+    }
 }
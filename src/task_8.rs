@@ -1,70 +1,70 @@
-use std::fmt::Debug;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::grid::{Grid, Point};
+use crate::parse::Tokens;
+use crate::solver::{Param, Solver};
+use std::fmt::{Debug, Display, Formatter};
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 struct Board {
-    content: Vec<Vec<bool>>,
+    content: Grid<bool>,
 }
 
 impl Board {
     fn new(wide: usize, tall: usize) -> Self {
         Board {
-            content: vec![vec![false; wide]; tall],
+            content: Grid::new(wide, tall, false),
         }
     }
 
-    fn apply_command(&mut self, command: Command) {
-        match command {
+    fn apply_command(&mut self, command: &Command) {
+        match *command {
             Command::DrawRect { wide: w, tall: t } => {
-                for i in 0..t {
-                    for j in 0..w {
-                        self.content[i][j] = true;
+                for y in 0..t.min(self.content.height()) {
+                    for cell in self.content.row_mut(y).iter_mut().take(w) {
+                        *cell = true;
                     }
                 }
             }
             Command::RotateCol { col: c, times: t } => {
-                let size = self.content.len();
-                for _ in 0..t {
-                    for i in 0..size - 1 {
-                        let b = self.content[size - i - 2][c];
-                        self.content[size - i - 2][c] = self.content[size - i - 1][c];
-                        self.content[size - i - 1][c] = b;
-                    }
+                let points = (0..self.content.height())
+                    .map(|y| Point::new(c as i64, y as i64))
+                    .collect::<Vec<Point>>();
+                let mut column = points
+                    .iter()
+                    .map(|p| self.content[*p])
+                    .collect::<Vec<bool>>();
+                column.rotate_right(t % points.len());
+                for (p, value) in points.into_iter().zip(column) {
+                    self.content[p] = value;
                 }
             }
             Command::RotateRow { row: r, times: t } => {
-                let size = self.content[0].len();
-                for _ in 0..t {
-                    for i in 0..size - 1 {
-                        let b = self.content[r][size - i - 2];
-                        self.content[r][size - i - 2] = self.content[r][size - i - 1];
-                        self.content[r][size - i - 1] = b;
-                    }
-                }
+                let row = self.content.row_mut(r);
+                let size = row.len();
+                row.rotate_right(t % size);
             }
         }
     }
 
+    fn contains(&self, command: &Command) -> bool {
+        match command {
+            Command::DrawRect { .. } => true,
+            Command::RotateCol { col, .. } => *col < self.content.width(),
+            Command::RotateRow { row, .. } => *row < self.content.height(),
+        }
+    }
+
     fn count_enabled(&self) -> usize {
-        self.content
-            .iter()
-            .flatten()
-            .filter(|c| **c)
-            .collect::<Vec<_>>()
-            .len()
+        self.content.iter().filter(|c| **c).count()
     }
 }
 
 impl Debug for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for r in &self.content {
-            for c in r {
-                write!(f, "{}", if *c { '#' } else { '.' }).unwrap();
-            }
-            writeln!(f).unwrap();
-        }
-        Ok(())
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let screen = self.content.render(|c| if *c { '#' } else { '.' });
+        write!(f, "{}", screen)
     }
 }
 
@@ -74,70 +74,193 @@ enum Command {
     RotateCol { col: usize, times: usize },
 }
 
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::DrawRect { wide, tall } => write!(f, "rect {}x{}", wide, tall),
+            Command::RotateRow { row, times } => write!(f, "rotate row y={} by {}", row, times),
+            Command::RotateCol { col, times } => write!(f, "rotate column x={} by {}", col, times),
+        }
+    }
+}
+
+fn axis_index(
+    tokens: &Tokens,
+    i: usize,
+    prefix: &str,
+    expected: &'static str,
+) -> Result<usize, ParseError> {
+    tokens
+        .get(i, expected)?
+        .strip_prefix(prefix)
+        .and_then(|n| n.parse::<usize>().ok())
+        .ok_or_else(|| tokens.error(i, expected))
+}
+
 impl FromStr for Command {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(" ").collect::<Vec<_>>();
-        if parts[0] == "rect" {
-            let size = parts[1]
-                .split("x")
-                .filter_map(|s| s.parse::<usize>().ok())
-                .take(2)
-                .collect::<Vec<_>>();
-            Ok(Command::DrawRect {
-                wide: size[0],
-                tall: size[1],
-            })
-        } else if parts[0] == "rotate" && parts[1] == "row" {
-            let row_num = parts[2].split("=").collect::<Vec<&str>>()[1]
-                .parse::<usize>()
-                .unwrap();
-            let times = parts[4].parse::<usize>().unwrap();
-            Ok(Command::RotateRow {
-                row: row_num,
-                times: times,
-            })
-        } else if parts[0] == "rotate" && parts[1] == "column" {
-            let col_num = parts[2].split("=").collect::<Vec<&str>>()[1]
-                .parse::<usize>()
-                .unwrap();
-            let times = parts[4].parse::<usize>().unwrap();
-            Ok(Command::RotateCol {
-                col: col_num,
-                times: times,
-            })
-        } else {
-            Err(())
+        let tokens = Tokens::new(s);
+        match tokens.get(0, "`rect` or `rotate`")? {
+            "rect" => {
+                let size = tokens.get(1, "<wide>x<tall>")?;
+                match size.split_once('x') {
+                    Some((w, t)) => match (w.parse::<usize>(), t.parse::<usize>()) {
+                        (Ok(wide), Ok(tall)) => Ok(Command::DrawRect { wide, tall }),
+                        _ => Err(tokens.error(1, "<wide>x<tall>")),
+                    },
+                    None => Err(tokens.error(1, "<wide>x<tall>")),
+                }
+            }
+            "rotate" => {
+                tokens.expect(3, "by")?;
+                let times = tokens.parse::<usize>(4, "number of steps")?;
+                match tokens.get(1, "`row` or `column`")? {
+                    "row" => Ok(Command::RotateRow {
+                        row: axis_index(&tokens, 2, "y=", "`y=<row>`")?,
+                        times,
+                    }),
+                    "column" => Ok(Command::RotateCol {
+                        col: axis_index(&tokens, 2, "x=", "`x=<column>`")?,
+                        times,
+                    }),
+                    _ => Err(tokens.error(1, "`row` or `column`")),
+                }
+            }
+            _ => Err(tokens.error(0, "`rect` or `rotate`")),
+        }
+    }
+}
+
+fn draw(input: &str, ctx: &Context) -> Result<Board, Error> {
+    let wide = ctx.param(
+        "width",
+        NonZeroUsize::new(50).unwrap(),
+        "positive number of columns",
+    )?;
+    let tall = ctx.param(
+        "height",
+        NonZeroUsize::new(6).unwrap(),
+        "positive number of rows",
+    )?;
+    let mut board = Board::new(wide.get(), tall.get());
+    for command in ctx.lines::<Command>(input)? {
+        if !board.contains(&command) {
+            return Err(Error::NoAnswer("a command rotates outside of the screen"));
         }
+        board.apply_command(&command);
+        ctx.step(|| format!("{}\n{:?}", command, board));
     }
+    Ok(board)
+}
+
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    Ok(draw(input, ctx)?.count_enabled())
 }
 
-pub fn run() {
-    let input = File::open("input/task_8").unwrap();
-    let input = BufReader::new(input);
-
-    let board = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Command>().ok())
-        .fold(Board::new(50, 6), |mut board, c| {
-            board.apply_command(c);
-            board
-        });
-    println!("{:?}", board.count_enabled());
+pub fn part2(input: &str, ctx: &Context) -> Result<String, Error> {
+    Ok(format!("{:?}", draw(input, ctx)?))
+}
+
+pub struct Solution;
+
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        8
+    }
+    fn title(&self) -> &'static str {
+        "Two-Factor Authentication"
+    }
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param {
+                name: "width",
+                help: "screen width in pixels (50)",
+            },
+            Param {
+                name: "height",
+                help: "screen height in pixels (6)",
+            },
+        ]
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_8").unwrap();
-    let input = BufReader::new(input);
-
-    let board = input
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| l.parse::<Command>().ok())
-        .fold(Board::new(50, 6), |mut board, c| {
-            board.apply_command(c);
-            board
-        });
-    println!("{:?}", board);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Trace;
+
+    const EXAMPLE: &str = "rect 3x2
+rotate column x=1 by 1
+rotate row y=0 by 4
+rotate column x=1 by 1";
+
+    #[test]
+    fn parses_command() {
+        assert!(matches!(
+            "rect 3x2".parse::<Command>().unwrap(),
+            Command::DrawRect { wide: 3, tall: 2 }
+        ));
+        assert!(matches!(
+            "rotate row y=0 by 4".parse::<Command>().unwrap(),
+            Command::RotateRow { row: 0, times: 4 }
+        ));
+        assert!(matches!(
+            "rotate column x=1 by 1".parse::<Command>().unwrap(),
+            Command::RotateCol { col: 1, times: 1 }
+        ));
+        let e = "rotate column y=1 by 1".parse::<Command>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (15, "y=1"));
+        let e = "rect 3by2".parse::<Command>().err().unwrap();
+        assert_eq!(e.column, 6);
+    }
+
+    #[test]
+    fn draws_on_small_screen() {
+        let mut board = Board::new(7, 3);
+        for command in EXAMPLE.lines() {
+            board.apply_command(&command.parse::<Command>().unwrap());
+        }
+        assert_eq!(format!("{:?}", board), ".#..#.#\n#.#....\n.#.....");
+        assert_eq!(board.count_enabled(), 6);
+    }
+
+    #[test]
+    fn counts_lit_pixels() {
+        assert_eq!(part1(EXAMPLE, &Context::new()).unwrap(), 6);
+    }
+
+    #[test]
+    fn draws_on_screen_of_given_size() {
+        let ctx = Context::new()
+            .with_param("width", "7")
+            .with_param("height", "3");
+        assert_eq!(part2(EXAMPLE, &ctx).unwrap(), ".#..#.#\n#.#....\n.#.....");
+        let ctx = Context::new().with_param("width", "1");
+        assert!(part1(EXAMPLE, &ctx).is_err());
+        let ctx = Context::new().with_param("height", "0");
+        assert!(part1(EXAMPLE, &ctx).is_err());
+    }
+
+    #[test]
+    fn traces_frames() {
+        let ctx = Context::new()
+            .with_param("width", "7")
+            .with_param("height", "3")
+            .with_trace(Trace::collected().with_from(3));
+        part1(EXAMPLE, &ctx).unwrap();
+        assert_eq!(
+            ctx.trace().unwrap().lines(),
+            [
+                "step 3:\nrotate row y=0 by 4\n....#.#\n###....\n.#.....",
+                "step 4:\nrotate column x=1 by 1\n.#..#.#\n#.#....\n.#....."
+            ]
+        );
+    }
 }
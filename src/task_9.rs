@@ -1,7 +1,9 @@
+use crate::context::Context;
+use crate::error::{Error, ParseError};
+use crate::solver::Solver;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::Read;
 use std::ops::Deref;
+use std::str::FromStr;
 
 #[derive(Clone, Debug)]
 struct Data<A: Clone> {
@@ -18,54 +20,49 @@ impl<A: Clone> Deref for Data<A> {
 
 impl Compressed for Data<Chunk> {
     type Into = Self;
-    fn decompressable(&self) -> bool {
-        self.iter().any(|c| c.is_compressed())
-    }
     fn decompress_top(&self) -> Self::Into {
         Data {
-            chunks: self.iter().map(|c| c.decompress_top()).flatten().collect(),
+            chunks: self.iter().flat_map(|c| c.decompress_top()).collect(),
         }
     }
 }
 
-impl<A: Read> From<A> for Data<Chunk> {
-    fn from(read: A) -> Self {
-        let mut bytes = read.bytes().filter_map(|b| b.ok());
+impl FromStr for Data<Chunk> {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut i = 0;
         let mut counter = String::new();
         let mut chunks = vec![];
-        while let Some(b) = bytes.next() {
-            if b == b'(' {
-                if counter.len() != 0 {
+        while let Some(b) = s[i..].chars().next() {
+            if b == '(' {
+                if !counter.is_empty() {
                     chunks.push(Chunk::plain(&counter));
                     counter = String::new();
                 }
-                let mut c = String::new();
-                let mut buf = bytes.next().unwrap();
-                while buf != b'x' {
-                    c.push(buf as char);
-                    buf = bytes.next().unwrap();
-                }
-                let length = c.parse::<usize>().unwrap();
-                c = String::new();
-                buf = bytes.next().unwrap();
-                while buf != b')' {
-                    c.push(buf as char);
-                    buf = bytes.next().unwrap();
-                }
-                let times = c.parse::<usize>().unwrap();
-                c = String::new();
-                for _ in 0..length {
-                    c.push(bytes.next().unwrap() as char);
-                }
-                chunks.push(Chunk::compressed(&c, times));
+                let close = s[i..]
+                    .find(')')
+                    .map(|p| i + p)
+                    .ok_or_else(|| ParseError::new(s.len() + 1, "", "`)`"))?;
+                let marker = &s[i + 1..close];
+                let (length, times) = marker
+                    .split_once('x')
+                    .and_then(|(l, t)| Some((l.parse::<usize>().ok()?, t.parse::<usize>().ok()?)))
+                    .ok_or_else(|| ParseError::new(i + 2, marker, "marker `(<length>x<times>)`"))?;
+                let start = close + 1;
+                let content = s.get(start..start + length).ok_or_else(|| {
+                    ParseError::new(start + 1, &s[start..], "enough characters to repeat")
+                })?;
+                chunks.push(Chunk::compressed(content, times).map_err(|e| e.shifted(start))?);
+                i = start + length;
             } else {
-                counter.push(b as char);
+                counter.push(b);
+                i += b.len_utf8();
             }
         }
-        if counter.len() != 0 {
+        if !counter.is_empty() {
             chunks.push(Chunk::plain(&counter));
         }
-        Data { chunks }
+        Ok(Data { chunks })
     }
 }
 
@@ -81,27 +78,16 @@ impl Chunk {
             content: s.to_string(),
         }
     }
-    fn compressed(s: &str, l: usize) -> Self {
-        Chunk::Compressed {
-            content: Data::from(s.as_bytes()).to_vec(),
+    fn compressed(s: &str, l: usize) -> Result<Self, ParseError> {
+        Ok(Chunk::Compressed {
+            content: s.parse::<Data<Chunk>>()?.to_vec(),
             repeats: l,
-        }
-    }
-
-    fn is_compressed(&self) -> bool {
-        match self {
-            Chunk::Plain { content: _ } => false,
-            Chunk::Compressed {
-                content: _,
-                repeats: _,
-            } => true,
-        }
+        })
     }
 }
 
 trait Compressed {
     type Into;
-    fn decompressable(&self) -> bool;
     fn decompress_top(&self) -> Self::Into;
 }
 
@@ -119,32 +105,30 @@ impl Display for Chunk {
 
 impl Compressed for Chunk {
     type Into = Vec<Self>;
-    fn decompressable(&self) -> bool {
-        self.is_compressed()
-    }
     fn decompress_top(&self) -> Self::Into {
         match self {
-            Chunk::Plain { content: _ } => vec![self.clone()],
-            Chunk::Compressed { content, repeats } => std::iter::repeat(content.clone())
-                .take(*repeats)
-                .flatten()
-                .collect(),
+            Chunk::Plain { .. } => vec![self.clone()],
+            Chunk::Compressed { content, repeats } => {
+                std::iter::repeat_n(content.clone(), *repeats)
+                    .flatten()
+                    .collect()
+            }
         }
     }
 }
 
 trait Decompressable<A> {
-    fn decompress(&self) -> Decompressed<A>;
+    fn decompress(&self) -> Decompressed<'_, A>;
 }
 
 impl<A: Clone> Decompressable<Data<A>> for Data<A> {
-    fn decompress(&self) -> Decompressed<Self> {
+    fn decompress(&self) -> Decompressed<'_, Self> {
         Decompressed { content: self }
     }
 }
 
 impl Decompressable<Chunk> for Chunk {
-    fn decompress(&self) -> Decompressed<Self> {
+    fn decompress(&self) -> Decompressed<'_, Self> {
         Decompressed { content: self }
     }
 }
@@ -177,10 +161,10 @@ impl Decompressed<'_, Chunk> {
     }
 }
 
-impl<'a> Display for Decompressed<'a, Data<Chunk>> {
+impl Display for Decompressed<'_, Data<Chunk>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut result = vec![];
-        for c in self.content.to_vec() {
+        for c in self.content.iter() {
             result.push(c.decompress().to_string());
         }
         let result = result.into_iter().collect::<String>();
@@ -188,7 +172,7 @@ impl<'a> Display for Decompressed<'a, Data<Chunk>> {
     }
 }
 
-impl<'a> Display for Decompressed<'a, Chunk> {
+impl Display for Decompressed<'_, Chunk> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self.content {
             Chunk::Plain { content } => write!(f, "{}", content),
@@ -198,29 +182,88 @@ impl<'a> Display for Decompressed<'a, Chunk> {
                     result.push(c.decompress().to_string())
                 }
                 let result = result.into_iter().collect::<String>();
-                write!(
-                    f,
-                    "{}",
-                    std::iter::repeat(result).take(*repeats).collect::<String>()
-                )
+                write!(f, "{}", result.repeat(*repeats))
             }
         }
     }
 }
 
-pub fn run() {
-    let input = File::open("input/task_9").unwrap();
+pub fn part1(input: &str, ctx: &Context) -> Result<usize, Error> {
+    let chunks = ctx.parse::<Data<Chunk>>(input)?.decompress_top();
+
+    Ok(chunks.iter().map(|c| c.to_string().len()).sum::<usize>())
+}
+
+pub fn part2(input: &str, ctx: &Context) -> Result<usize, Error> {
+    Ok(ctx
+        .parse::<Data<Chunk>>(input)?
+        .decompress()
+        .decompressed_len())
+}
 
-    let chunks = Data::from(input).decompress_top();
+pub struct Solution;
 
-    let result = chunks.iter().map(|c| c.to_string().len()).sum::<usize>();
-    println!("Result: {}", result);
+impl Solver for Solution {
+    fn day(&self) -> u8 {
+        9
+    }
+    fn title(&self) -> &'static str {
+        "Explosives in Cyberspace"
+    }
+    fn part1(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part1(input, ctx).map(|r| r.to_string())
+    }
+    fn part2(&self, input: &str, ctx: &Context) -> Result<String, Error> {
+        part2(input, ctx).map(|r| r.to_string())
+    }
 }
 
-pub fn run_e() {
-    let input = File::open("input/task_9").unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let result = Data::from(input).decompress().decompressed_len();
+    #[test]
+    fn parses_markers() {
+        let data = "A(1x5)BC".parse::<Data<Chunk>>().unwrap();
+        assert_eq!(data.len(), 3);
+        assert!(matches!(&data[1], Chunk::Compressed { repeats: 5, .. }));
+        assert_eq!(data.decompress().to_string(), "ABBBBBC");
+        let e = "A(1y5)BC".parse::<Data<Chunk>>().err().unwrap();
+        assert_eq!((e.column, e.token.as_str()), (3, "1y5"));
+        let e = "A(1x5".parse::<Data<Chunk>>().err().unwrap();
+        assert_eq!(e.column, 6);
+        let e = "A(4x5)BC".parse::<Data<Chunk>>().err().unwrap();
+        assert_eq!(e.column, 7);
+    }
+
+    #[test]
+    fn decompresses_top_level_markers() {
+        let ctx = Context::new();
+        for (input, len) in [
+            ("ADVENT", 6),
+            ("A(1x5)BC", 7),
+            ("(3x3)XYZ", 9),
+            ("A(2x2)BCD(2x2)EFG", 11),
+            ("(6x1)(1x3)A", 6),
+            ("X(8x2)(3x3)ABCY", 18),
+        ] {
+            assert_eq!(part1(input, &ctx).unwrap(), len, "{}", input);
+        }
+    }
 
-    println!("Result: {}", result);
+    #[test]
+    fn decompresses_nested_markers() {
+        let ctx = Context::new();
+        for (input, len) in [
+            ("(3x3)XYZ", 9),
+            ("X(8x2)(3x3)ABCY", 20),
+            ("(27x12)(20x12)(13x14)(7x10)(1x12)A", 241920),
+            (
+                "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN",
+                445,
+            ),
+        ] {
+            assert_eq!(part2(input, &ctx).unwrap(), len, "{}", input);
+        }
+    }
 }
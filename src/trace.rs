@@ -0,0 +1,131 @@
+use std::sync::Mutex;
+
+/// Streams the intermediate states of a solver as numbered steps.
+///
+/// Steps are counted whether they are shown or not, so `from` and `limit`
+/// refer to the same numbers that appear in the output. A `filter` only shows
+/// the steps that contain the given text.
+#[derive(Default)]
+pub struct Trace {
+    from: usize,
+    limit: Option<usize>,
+    filter: Option<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    step: usize,
+    shown: usize,
+    collected: Option<Vec<String>>,
+}
+
+impl Trace {
+    /// A trace written to stderr.
+    pub fn new() -> Self {
+        Trace::default()
+    }
+
+    /// A trace that keeps the lines it shows, see `lines`.
+    pub fn collected() -> Self {
+        let trace = Trace::default();
+        trace.state.lock().unwrap().collected = Some(vec![]);
+        trace
+    }
+
+    /// Skips the steps before `step`.
+    pub fn with_from(mut self, step: usize) -> Self {
+        self.from = step;
+        self
+    }
+
+    /// Shows at most `limit` steps.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: &str) -> Self {
+        self.filter = Some(filter.to_string());
+        self
+    }
+
+    /// Starts counting steps again under a new heading.
+    pub fn begin(&self, heading: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.step = 0;
+        state.shown = 0;
+        state.write(format!("== {} ==", heading));
+    }
+
+    /// Records the next step, `event` is only called when it may be shown.
+    pub fn step<F: FnOnce() -> String>(&self, event: F) {
+        let mut state = self.state.lock().unwrap();
+        state.step += 1;
+        if state.step < self.from || self.limit.is_some_and(|l| state.shown >= l) {
+            return;
+        }
+        let event = event();
+        if let Some(filter) = &self.filter {
+            if !event.contains(filter.as_str()) {
+                return;
+            }
+        }
+        state.shown += 1;
+        let line = if event.contains('\n') {
+            format!("step {}:\n{}", state.step, event)
+        } else {
+            format!("step {}: {}", state.step, event)
+        };
+        state.write(line);
+    }
+
+    /// What a collected trace has shown so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .collected
+            .clone()
+            .unwrap_or_default()
+    }
+}
+
+impl State {
+    fn write(&mut self, line: String) {
+        match &mut self.collected {
+            Some(lines) => lines.push(line),
+            None => eprintln!("{}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_steps() {
+        let trace = Trace::collected();
+        trace.begin("day 1");
+        trace.step(|| "a".to_string());
+        trace.step(|| "b\nc".to_string());
+        assert_eq!(trace.lines(), ["== day 1 ==", "step 1: a", "step 2:\nb\nc"]);
+    }
+
+    #[test]
+    fn limits_and_filters_steps() {
+        let trace = Trace::collected()
+            .with_from(2)
+            .with_limit(2)
+            .with_filter("x");
+        for event in ["x1", "x2", "y3", "x4", "x5"] {
+            trace.step(|| event.to_string());
+        }
+        assert_eq!(trace.lines(), ["step 2: x2", "step 4: x4"]);
+        trace.begin("again");
+        trace.step(|| "x1".to_string());
+        trace.step(|| "x2".to_string());
+        assert_eq!(trace.lines().last().unwrap(), "step 2: x2");
+    }
+}
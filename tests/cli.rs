@@ -0,0 +1,241 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn aoc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aoc-2016"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn runs_day_over_fixture() {
+    let output = aoc(&["run", "2", "--input", "tests/fixtures/task_2"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Day 2 part 1: 1985\nDay 2 part 2: 5DB3\n");
+}
+
+#[test]
+fn prints_multi_line_answers_as_block() {
+    let output = aoc(&["run", "8", "2", "--input", "tests/fixtures/task_8"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    let lines = out.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "Day 8 part 2:");
+    assert!(lines[1].starts_with("....#.#."));
+}
+
+#[test]
+fn skips_malformed_lines_with_warning() {
+    let output = aoc(&[
+        "run",
+        "12",
+        "1",
+        "--input",
+        "tests/fixtures/task_12_malformed",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Day 12 part 1: 42\n");
+    assert_eq!(
+        stderr(&output),
+        "warning: skipped tests/fixtures/task_12_malformed:2:5: expected register a-d, found `e`\n"
+    );
+}
+
+#[test]
+fn fails_on_malformed_lines_in_strict_mode() {
+    let output = aoc(&[
+        "run",
+        "12",
+        "1",
+        "--strict",
+        "--input",
+        "tests/fixtures/task_12_malformed",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).contains("task_12_malformed:2:5: expected register a-d"));
+}
+
+#[test]
+fn rejects_unknown_commands_and_options() {
+    let output = aoc(&["run", "1", "--stirct"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Unknown option: --stirct"));
+    let output = aoc(&["runn", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).starts_with("Usage:"));
+    assert_eq!(aoc(&[]).status.code(), Some(2));
+}
+
+#[test]
+fn reports_runs_as_json() {
+    let output = aoc(&[
+        "run",
+        "12",
+        "--format",
+        "json",
+        "--input",
+        "tests/fixtures/task_12",
+    ]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    let lines = out.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(r#"{"day":12,"part":1,"answer":"42","error":null,"duration_ms":"#));
+    assert!(lines[1].starts_with(r#"{"day":12,"part":2,"answer":"42","#));
+    assert!(lines[1].contains(r#""input_md5":""#));
+}
+
+#[test]
+fn reads_inline_input() {
+    let output = aoc(&["run", "19", "1", "--inline", "5"]);
+    assert_eq!(stdout(&output), "Day 19 part 1: 3\n");
+}
+
+#[test]
+fn overrides_puzzle_constants_with_params() {
+    let output = aoc(&[
+        "run",
+        "8",
+        "1",
+        "--input",
+        "tests/fixtures/task_8",
+        "--param",
+        "width=7",
+        "--param",
+        "height=3",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Day 8 part 1: 6\n");
+}
+
+#[test]
+fn rejects_unknown_params() {
+    let output = aoc(&["run", "19", "--inline", "5", "--param", "elves=3"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "Day 19 does not take parameter `elves`, accepted: none\n"
+    );
+    let output = aoc(&["run", "8", "--inline", "rect 1x1", "--param", "width=0"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("invalid parameter width=0"));
+}
+
+#[test]
+fn traces_steps_to_stderr() {
+    let output = aoc(&[
+        "run",
+        "19",
+        "1",
+        "--inline",
+        "5",
+        "--trace-limit",
+        "2",
+        "--trace-filter",
+        "elf 3",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Day 19 part 1: 3\n");
+    assert_eq!(
+        stderr(&output),
+        "== Day 19 part 1 ==\n\
+         step 2: elf 3 takes from elf 4, 3 left: 5 1 3\n\
+         step 4: elf 3 takes from elf 5, 1 left: 3\n"
+    );
+    let output = aoc(&["all", "--trace"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn stops_long_runs_when_out_of_time() {
+    let output = aoc(&["run", "5", "2", "--inline", "abc", "--budget", "0.2"]);
+    assert!(!output.status.success());
+    let err = stderr(&output);
+    assert!(err.starts_with("Day 5 part 2: error: stopped: out of time after "));
+    assert!(err.contains(", got as far as "), "{}", err);
+    let output = aoc(&["run", "20", "--budget", "0"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid time budget: 0"));
+}
+
+#[test]
+fn debugs_assembunny_programs_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aoc-2016"))
+        .args(["debug", "12", "--input", "tests/fixtures/task_12"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"break 2\ncontinue\nset a a * 10\nwatch a + 1\nc\nquit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(out.contains("breakpoint 1: at 2\n"), "{}", out);
+    assert!(out.contains("=>*  2: inc a\n"), "{}", out);
+    assert!(
+        out.contains("a=420 b=0 c=0 d=0\nwatch 1: a + 1 = 421\n"),
+        "{}",
+        out
+    );
+    assert!(out.contains("halted after"), "{}", out);
+}
+
+#[test]
+fn stops_at_conditions_inside_fused_loops() {
+    // The add loop takes `a` from 3 to 5, one `inc` at a time.
+    let fibonacci =
+        "cpy 1 a\ncpy 1 b\ncpy 6 d\ncpy a c\ninc a\ndec b\njnz b -2\ncpy c b\ndec d\njnz d -6";
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aoc-2016"))
+        .args(["debug", "12", "--inline", fibonacci])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"break if a == 4\ncontinue\nquit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(out.contains("breakpoint 1: if a == 4\n"), "{}", out);
+    assert!(out.contains("a=4 b=2 c=3 d=4"), "{}", out);
+    assert!(!out.contains("halted after"), "{}", out);
+}
+
+#[test]
+fn decompiles_assembunny_programs() {
+    let output = aoc(&["decompile", "12", "--input", "tests/fixtures/task_12"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "a = 41\na += 1\na += 1\na -= 1\nif a == 0 {\n    a -= 1\n}\n"
+    );
+    let output = aoc(&["decompile", "12", "rust", "--inline", "cpy 2 a\nout a"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("fn day_12(registers: [i64; 4], out: &mut Vec<i64>)"));
+    let output = aoc(&["decompile", "23", "rust"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "Day 23 toggles or jumps in ways Rust has no loop for\n"
+    );
+}
@@ -0,0 +1,159 @@
+use aoc_2016::answers::Answers;
+use aoc_2016::context::Context;
+use aoc_2016::input::InputSource;
+use aoc_2016::solver::{Part, Registry};
+
+/// Solves both parts of a day over its puzzle input in strict mode and
+/// compares them with the recorded answers.
+fn check(day: u8) {
+    let answers = InputSource::from_arg("answers").read().unwrap();
+    let answers = Answers::parse(&answers, &Context::new().with_strict(true)).unwrap();
+    let registry = Registry::new();
+    let solver = registry.get(day).unwrap();
+    let input = InputSource::for_day(day).read().unwrap();
+    let ctx = Context::new().with_strict(true);
+    for part in Part::both().iter() {
+        let answer = solver.solve(*part, &input, &ctx).unwrap();
+        assert_eq!(
+            Some(answer.as_str()),
+            answers.get(day, *part),
+            "day {} part {}",
+            day,
+            part
+        );
+    }
+}
+
+#[test]
+fn registers_every_day_once() {
+    let registry = Registry::new();
+    let days = registry.iter().map(|s| s.day()).collect::<Vec<u8>>();
+    assert_eq!(days, (1..=25).collect::<Vec<u8>>());
+}
+
+#[test]
+fn day_1() {
+    check(1);
+}
+
+#[test]
+fn day_2() {
+    check(2);
+}
+
+#[test]
+fn day_3() {
+    check(3);
+}
+
+#[test]
+fn day_4() {
+    check(4);
+}
+
+#[test]
+#[ignore]
+fn day_5() {
+    check(5);
+}
+
+#[test]
+fn day_6() {
+    check(6);
+}
+
+#[test]
+fn day_7() {
+    check(7);
+}
+
+#[test]
+fn day_8() {
+    check(8);
+}
+
+#[test]
+fn day_9() {
+    check(9);
+}
+
+#[test]
+fn day_10() {
+    check(10);
+}
+
+#[test]
+fn day_11() {
+    check(11);
+}
+
+#[test]
+fn day_12() {
+    check(12);
+}
+
+#[test]
+fn day_13() {
+    check(13);
+}
+
+#[test]
+#[ignore]
+fn day_14() {
+    check(14);
+}
+
+#[test]
+fn day_15() {
+    check(15);
+}
+
+#[test]
+fn day_16() {
+    check(16);
+}
+
+#[test]
+fn day_17() {
+    check(17);
+}
+
+#[test]
+fn day_18() {
+    check(18);
+}
+
+#[test]
+fn day_19() {
+    check(19);
+}
+
+#[test]
+fn day_20() {
+    check(20);
+}
+
+#[test]
+fn day_21() {
+    check(21);
+}
+
+#[test]
+fn day_22() {
+    check(22);
+}
+
+#[test]
+fn day_23() {
+    check(23);
+}
+
+#[test]
+fn day_24() {
+    check(24);
+}
+
+#[test]
+fn day_25() {
+    check(25);
+}